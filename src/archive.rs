@@ -0,0 +1,315 @@
+//! Reading and writing packages and input files in `.zip` and `.tar.gz`
+//! archives.
+
+use crate::utils::list_files;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use sha2::{Digest, Sha256};
+use std::ffi::OsString;
+use std::fs::File;
+use std::io::Write;
+use std::path::{Component, Path, PathBuf};
+use std::time::UNIX_EPOCH;
+use std::{env, fs, io};
+use uuid::Uuid;
+use zip::write::FileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ArchiveFormat {
+    Zip,
+    TarGz,
+}
+
+impl ArchiveFormat {
+    const EXTENSIONS: [(&'static str, ArchiveFormat); 3] = [
+        (".zip", ArchiveFormat::Zip),
+        (".tar.gz", ArchiveFormat::TarGz),
+        (".tgz", ArchiveFormat::TarGz),
+    ];
+
+    /// Detects the format from the extension of `path`.
+    pub fn from_path(path: &Path) -> Option<ArchiveFormat> {
+        let name = path.file_name()?.to_str()?;
+        ArchiveFormat::EXTENSIONS
+            .iter()
+            .find(|(ext, _)| name.ends_with(ext))
+            .map(|(_, format)| *format)
+    }
+}
+
+/// Name of `path` with its archive extension removed, e.g. `tests` for
+/// `tests.tar.gz`.
+fn strip_archive_extension(name: &str) -> &str {
+    ArchiveFormat::EXTENSIONS
+        .iter()
+        .find_map(|(ext, _)| name.strip_suffix(ext))
+        .unwrap_or(name)
+}
+
+/// Splits `path` into an archive and a path inside of it, if one of the
+/// components of `path` relative to `base` is an archive file.
+fn split_archive_path(base: &Path, path: &Path) -> Option<(PathBuf, PathBuf)> {
+    let mut archive = base.to_path_buf();
+    let mut components = path.components();
+    while let Some(component) = components.next() {
+        archive.push(component);
+        if archive.is_file() && ArchiveFormat::from_path(&archive).is_some() {
+            return Some((archive, components.as_path().to_path_buf()));
+        }
+    }
+    None
+}
+
+/// Directory with the files at `path` relative to `base`. Paths going through
+/// an archive, like `tests.zip` or `tests.zip/in`, are resolved to the
+/// archive extracted with [`extract`].
+pub fn resolve(base: &Path, path: &Path) -> io::Result<PathBuf> {
+    match split_archive_path(base, path) {
+        Some((archive, inner)) => Ok(extract(&archive)?.join(inner)),
+        None => Ok(base.join(path)),
+    }
+}
+
+/// `path` with the archive extension removed from its components, used for
+/// the directory archived files are copied to.
+pub fn unpacked_path(path: &Path) -> PathBuf {
+    path.components()
+        .map(|component| match component {
+            Component::Normal(name) => match name.to_str() {
+                Some(name) => OsString::from(strip_archive_extension(name)),
+                None => name.to_os_string(),
+            },
+            component => component.as_os_str().to_os_string(),
+        })
+        .collect()
+}
+
+/// Directory of the package in `path`, which is extracted first if it is an
+/// archive. Archives with a single top-level directory resolve to it.
+pub fn package_dir(path: &Path) -> io::Result<PathBuf> {
+    if !path.is_file() || ArchiveFormat::from_path(path).is_none() {
+        return Ok(path.to_path_buf());
+    }
+    let dir = extract(path)?;
+    let entries = dir
+        .read_dir()?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<io::Result<Vec<PathBuf>>>()?;
+    match entries.as_slice() {
+        [only] if only.is_dir() => Ok(only.clone()),
+        _ => Ok(dir),
+    }
+}
+
+/// Extracts `archive` to a temporary directory and returns its path. The
+/// directory is reused until the archive is modified.
+pub fn extract(archive: &Path) -> io::Result<PathBuf> {
+    let format = ArchiveFormat::from_path(archive).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("{} is not a .zip or .tar.gz archive", archive.display()),
+        )
+    })?;
+    let archive = archive.canonicalize()?;
+    let metadata = fs::metadata(&archive)?;
+    let modified = metadata
+        .modified()?
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    let mut hasher = Sha256::new();
+    hasher.update(archive.as_os_str().as_encoded_bytes());
+    hasher.update(metadata.len().to_le_bytes());
+    hasher.update(modified.as_nanos().to_le_bytes());
+    let archives = env::temp_dir().join("iobot/archives");
+    let dir = archives.join(format!("{:x}", hasher.finalize()));
+    if dir.is_dir() {
+        return Ok(dir);
+    }
+    // Extracts next to the final directory first, so an interrupted
+    // extraction is never reused.
+    let partial = archives.join(Uuid::new_v4().to_string());
+    fs::create_dir_all(&partial)?;
+    let result = match format {
+        ArchiveFormat::Zip => ZipArchive::new(File::open(&archive)?)
+            .and_then(|mut zip| zip.extract(&partial))
+            .map_err(io::Error::from),
+        ArchiveFormat::TarGz => {
+            tar::Archive::new(GzDecoder::new(File::open(&archive)?)).unpack(&partial)
+        }
+    };
+    if let Err(error) = result.and_then(|_| fs::rename(&partial, &dir)) {
+        let _ = fs::remove_dir_all(&partial);
+        if !dir.is_dir() {
+            return Err(error);
+        }
+    }
+    Ok(dir)
+}
+
+/// A directory or archive files are written to.
+pub enum Target {
+    Directory(PathBuf),
+    Zip(ZipWriter<File>),
+    TarGz(tar::Builder<GzEncoder<File>>),
+}
+
+impl Target {
+    /// Writes to an archive if `path` has the `.zip`, `.tar.gz` or `.tgz`
+    /// extension and to a directory otherwise.
+    pub fn create(path: &Path) -> io::Result<Target> {
+        let format = match ArchiveFormat::from_path(path) {
+            Some(format) => format,
+            None => {
+                fs::create_dir_all(path)?;
+                return Ok(Target::Directory(path.to_path_buf()));
+            }
+        };
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let file = File::create(path)?;
+        Ok(match format {
+            ArchiveFormat::Zip => Target::Zip(ZipWriter::new(file)),
+            ArchiveFormat::TarGz => Target::TarGz(tar::Builder::new(GzEncoder::new(
+                file,
+                Compression::default(),
+            ))),
+        })
+    }
+
+    /// Writes `contents` to the file `name`, a `/` separated path.
+    pub fn write(&mut self, name: &str, contents: &[u8]) -> io::Result<()> {
+        match self {
+            Target::Directory(dir) => {
+                let path = dir.join(name);
+                if let Some(parent) = path.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                fs::write(path, contents)
+            }
+            Target::Zip(zip) => {
+                let options =
+                    FileOptions::default().compression_method(CompressionMethod::Deflated);
+                zip.start_file(name, options)?;
+                zip.write_all(contents)
+            }
+            Target::TarGz(tar) => {
+                let mut header = tar::Header::new_gnu();
+                header.set_size(contents.len() as u64);
+                header.set_mode(0o644);
+                tar.append_data(&mut header, name, contents)
+            }
+        }
+    }
+
+    pub fn copy(&mut self, name: &str, source: &Path) -> io::Result<()> {
+        self.write(name, &fs::read(source)?)
+    }
+
+    pub fn finish(self) -> io::Result<()> {
+        match self {
+            Target::Directory(_) => Ok(()),
+            Target::Zip(mut zip) => {
+                zip.finish()?;
+                Ok(())
+            }
+            Target::TarGz(tar) => {
+                tar.into_inner()?.finish()?;
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Writes all files in `dir` to the archive `target`, with paths relative to
+/// `dir`.
+pub fn pack(dir: &Path, target: &Path) -> io::Result<()> {
+    let mut files = list_files(dir)?;
+    files.sort();
+    let mut target = Target::create(target)?;
+    for file in files {
+        let name = file
+            .strip_prefix(dir)
+            .expect("listed files are in the directory")
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        target.copy(&name, &file)?;
+    }
+    target.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::{relative_files, test_dir};
+
+    /// Writes the archive `path` with `files`.
+    fn archive(path: &Path, files: &[&str]) {
+        let mut target = Target::create(path).unwrap();
+        for file in files {
+            target.write(file, file.as_bytes()).unwrap();
+        }
+        target.finish().unwrap();
+    }
+
+    #[test]
+    fn unpacked_path_strips_archive_extensions() {
+        assert_eq!(
+            unpacked_path(Path::new("data/tests.zip/in")),
+            Path::new("data/tests/in")
+        );
+        assert_eq!(unpacked_path(Path::new("tests.tar.gz")), Path::new("tests"));
+        assert_eq!(unpacked_path(Path::new("tests.tgz")), Path::new("tests"));
+        assert_eq!(unpacked_path(Path::new("tests/in")), Path::new("tests/in"));
+    }
+
+    #[test]
+    fn resolve_goes_through_archives() {
+        let base = test_dir();
+        archive(&base.join("tests.zip"), &["in/1.in", "out/1.out"]);
+        fs::create_dir(base.join("in")).unwrap();
+
+        let (archive, inner) = split_archive_path(&base, Path::new("tests.zip/in")).unwrap();
+        assert_eq!(
+            (archive, inner),
+            (base.join("tests.zip"), PathBuf::from("in"))
+        );
+        assert_eq!(split_archive_path(&base, Path::new("in")), None);
+
+        let inputs = resolve(&base, Path::new("tests.zip/in")).unwrap();
+        assert_eq!(relative_files(&inputs), ["1.in"]);
+        let all = resolve(&base, Path::new("tests.zip")).unwrap();
+        assert_eq!(relative_files(&all), ["in/1.in", "out/1.out"]);
+        assert_eq!(resolve(&base, Path::new("in")).unwrap(), base.join("in"));
+    }
+
+    #[test]
+    fn extract_reads_tgz_and_reuses_the_directory() {
+        let base = test_dir();
+        let path = base.join("tests.tgz");
+        archive(&path, &["1.in", "2.in"]);
+        let dir = extract(&path).unwrap();
+        assert_eq!(relative_files(&dir), ["1.in", "2.in"]);
+        assert_eq!(fs::read_to_string(dir.join("2.in")).unwrap(), "2.in");
+        assert_eq!(extract(&path).unwrap(), dir);
+    }
+
+    #[test]
+    fn package_dir_resolves_a_single_top_level_directory() {
+        let base = test_dir();
+        archive(&base.join("sum.tar.gz"), &["sum/iobot.yaml", "sum/in/1.in"]);
+        let dir = package_dir(&base.join("sum.tar.gz")).unwrap();
+        assert_eq!(dir.file_name().unwrap(), "sum");
+        assert_eq!(relative_files(&dir), ["in/1.in", "iobot.yaml"]);
+
+        archive(&base.join("flat.zip"), &["iobot.yaml", "in/1.in"]);
+        let dir = package_dir(&base.join("flat.zip")).unwrap();
+        assert_eq!(relative_files(&dir), ["in/1.in", "iobot.yaml"]);
+
+        assert_eq!(package_dir(&base).unwrap(), base);
+    }
+}
@@ -0,0 +1,74 @@
+//! Timing the model program to choose a time limit.
+
+use crate::judge::{JudgeError, TestCase};
+use crate::runner::Runner;
+use crate::utils::{run_parallel, PoolError};
+use indicatif::ProgressBar;
+use std::fs;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Suggested time limits are rounded up to a multiple of this.
+const TIME_LIMIT_STEP: Duration = Duration::from_millis(100);
+
+/// Times of repeated runs on a test, sorted from the fastest.
+#[derive(Clone, Debug)]
+pub struct TestTimes {
+    pub times: Vec<Duration>,
+}
+
+impl TestTimes {
+    pub fn min(&self) -> Duration {
+        self.times[0]
+    }
+
+    pub fn median(&self) -> Duration {
+        self.times[self.times.len() / 2]
+    }
+
+    pub fn max(&self) -> Duration {
+        self.times[self.times.len() - 1]
+    }
+}
+
+/// Runs `model` `runs` times on every test, on `jobs` threads, and returns the
+/// times in test order. `progress` is increased after every run.
+pub fn measure(
+    model: &Arc<Runner>,
+    tests: &[TestCase],
+    runs: usize,
+    jobs: usize,
+    progress: &ProgressBar,
+) -> Result<Vec<TestTimes>, JudgeError> {
+    let model = model.clone();
+    let progress = progress.clone();
+    let runs = runs.max(1);
+    run_parallel(tests.to_vec(), jobs, move |test| {
+        let input = fs::read(&test.input)?;
+        let mut times = vec![];
+        for _ in 0..runs {
+            let result = model
+                .run(input.clone(), vec![])
+                .map_err(JudgeError::Model)?;
+            times.push(result.time);
+            progress.inc(1);
+        }
+        times.sort();
+        Ok(TestTimes { times })
+    })
+    .map_err(|error| match error {
+        PoolError::Job(error) => error,
+        PoolError::Panicked => JudgeError::Panicked,
+    })
+}
+
+/// `multiplier` times the `slowest` model time, rounded up to a multiple of
+/// 100 ms.
+pub fn suggest_time_limit(slowest: Duration, multiplier: f64) -> Duration {
+    let step = TIME_LIMIT_STEP.as_millis() as f64;
+    let millis = (slowest.as_millis() as f64 * multiplier / step)
+        .ceil()
+        .max(1.0)
+        * step;
+    Duration::from_millis(millis as u64)
+}
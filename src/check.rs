@@ -0,0 +1,83 @@
+//! Consistency checks of packages with output files, whose expected outputs
+//! are otherwise trusted as they are.
+
+use crate::judge::{Judge, JudgeError, JudgeProgress, TestCase, Verdict};
+use crate::runner::Runner;
+use indicatif::ProgressBar;
+use std::fmt;
+use std::fs;
+use std::sync::Arc;
+
+/// Why a test of a package is inconsistent.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Inconsistency {
+    /// The output of the model program is not accepted against the reference
+    /// output.
+    Model(Verdict),
+    /// The verifier does not accept the reference output.
+    Reference(Verdict),
+}
+
+impl fmt::Display for Inconsistency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Inconsistency::Model(verdict) => write!(f, "model program got {}", verdict),
+            Inconsistency::Reference(verdict) => {
+                write!(f, "verifier gives the reference output {}", verdict)
+            }
+        }
+    }
+}
+
+/// An inconsistent test.
+#[derive(Clone, Debug)]
+pub struct Finding {
+    /// Index of the test among the tests of the judge.
+    pub index: usize,
+    pub test: TestCase,
+    pub inconsistency: Inconsistency,
+}
+
+/// Runs `model` on all tests of `judge` and reports the tests where its
+/// output is not accepted, compared by the verifier if the package has one.
+pub fn check_model<P: JudgeProgress + Clone + 'static>(
+    judge: &Arc<Judge>,
+    model: &Arc<Runner>,
+    jobs: usize,
+    progress: &P,
+) -> Result<Vec<Finding>, JudgeError> {
+    let results = judge.judge_all(model, jobs, progress)?;
+    Ok(judge
+        .tests()
+        .iter()
+        .zip(results)
+        .enumerate()
+        .filter(|(_, (_, result))| !result.verdict.is_accepted())
+        .map(|(index, (test, result))| Finding {
+            index,
+            test: test.clone(),
+            inconsistency: Inconsistency::Model(result.verdict),
+        })
+        .collect())
+}
+
+/// Checks the reference output of every test of `judge` with its verifier
+/// and reports the tests where it is not accepted. Without a verifier the
+/// reference outputs are always accepted.
+pub fn check_references(judge: &Judge, progress: &ProgressBar) -> Result<Vec<Finding>, JudgeError> {
+    let mut findings = vec![];
+    for (index, test) in judge.tests().iter().enumerate() {
+        if let Some(reference) = &test.output {
+            let (verdict, _) = judge.check_output(test, &fs::read(reference)?)?;
+            if !verdict.is_accepted() {
+                findings.push(Finding {
+                    index,
+                    test: test.clone(),
+                    inconsistency: Inconsistency::Reference(verdict),
+                });
+            }
+        }
+        progress.inc(1);
+    }
+    Ok(findings)
+}
@@ -0,0 +1,215 @@
+use clap::Parser;
+use console::style;
+use indicatif::ProgressBar;
+use iobot::archive;
+use iobot::calibrate::{measure, suggest_time_limit, TestTimes};
+use iobot::config::{Config, Expectation, ProgramOrShorthand};
+use iobot::error::Error;
+use iobot::judge::{list_tests, meets_expectation, Judge, Verdict};
+use iobot::runner::{build_all, describe, ProgramRole, Runner};
+use iobot::utils::format_time;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+#[derive(Parser)]
+pub struct Params {
+    /// Generated package, a directory or a .zip or .tar.gz archive
+    pub tests: PathBuf,
+    /// Model program to time, the model program of the package by default
+    #[clap(long)]
+    pub model: Option<PathBuf>,
+    /// Number of runs on every test
+    #[clap(short, long, default_value = "5")]
+    pub runs: usize,
+    /// Suggested time limit as a multiple of the slowest model time
+    #[clap(short, long, default_value = "3")]
+    pub multiplier: f64,
+    /// Also judge the solutions of the package with the suggested time limit
+    #[clap(short, long)]
+    pub solutions: bool,
+    /// Number of times every solution is judged, the median time of every test
+    /// is reported
+    #[clap(long, default_value = "1")]
+    pub solution_runs: usize,
+    /// Number of programs run in parallel, the jobs key of the config or
+    /// sequential by default as parallel runs distort the times
+    #[clap(short, long)]
+    pub jobs: Option<usize>,
+    /// Write the suggested time limit to iobot.yaml, editing only its timeLimit
+    /// line
+    #[clap(short, long)]
+    pub write: bool,
+}
+
+pub fn run(params: Params) -> Result<(), Error> {
+    let package = archive::package_dir(&params.tests)?;
+    if params.write && package != params.tests {
+        return Err(Error::Usage(
+            "Writing the time limit needs a package directory, not an archive".to_string(),
+        ));
+    }
+    if params.multiplier <= 0.0 {
+        return Err(Error::Usage("Multiplier should be positive".to_string()));
+    }
+    let config = Config::load(&package)?;
+    let model = match (&params.model, config.model_program()) {
+        (Some(path), _) => {
+            let path = path.canonicalize().map_err(|error| {
+                Error::Usage(format!("Cannot find program {}: {}", path.display(), error))
+            })?;
+            ProgramOrShorthand::Shorthand(path).to_program()?
+        }
+        (None, Some(model)) => model.to_program()?,
+        (None, None) => {
+            return Err(Error::Usage(
+                "The package has no model program, pass one with --model".to_string(),
+            ))
+        }
+    };
+    let solutions = if params.solutions {
+        config.options().solutions.clone()
+    } else {
+        vec![]
+    };
+    let tests = list_tests(&config, &package)?;
+    let mut programs = vec![(ProgramRole::ModelProgram, model)];
+    let mut names = vec![];
+    for solution in &solutions {
+        let program = solution.program.to_program()?;
+        names.push(describe(&program));
+        programs.push((ProgramRole::Solution, program));
+    }
+    programs.extend(Judge::programs(&config)?);
+    let mut runners =
+        build_all(&programs, &package).map_err(|(role, error)| Error::Compile(role, error))?;
+    let model = Arc::new(runners.remove(0).1);
+    let solution_runners: Vec<Arc<Runner>> = runners
+        .drain(..solutions.len())
+        .map(|(_, runner)| Arc::new(runner))
+        .collect();
+    // The model runs are always timed, even if the package has no time limit
+    // yet.
+    let jobs = config.options().job_count(params.jobs, true);
+
+    let bar = ProgressBar::new((tests.len() * params.runs.max(1)) as u64);
+    bar.tick();
+    let times = measure(&model, &tests, params.runs, jobs, &bar)?;
+    bar.finish();
+    let name_width = tests
+        .iter()
+        .map(|test| test.name.display().to_string().chars().count())
+        .max()
+        .unwrap_or(0)
+        .max("test".len());
+    println!(
+        "{}",
+        style(format!(
+            "{:<width$} {:>9} {:>9} {:>9}",
+            "test",
+            "min",
+            "median",
+            "max",
+            width = name_width
+        ))
+        .bold()
+    );
+    for (test, times) in tests.iter().zip(&times) {
+        println!(
+            "{:<width$} {:>9} {:>9} {:>9}",
+            test.name.display().to_string(),
+            format_time(times.min()),
+            format_time(times.median()),
+            format_time(times.max()),
+            width = name_width
+        );
+    }
+    let (slowest_test, slowest) = match tests
+        .iter()
+        .zip(&times)
+        .map(|(test, times)| (test, times.max()))
+        .max_by_key(|(_, time)| *time)
+    {
+        Some(slowest) => slowest,
+        None => return Err(Error::Usage("The package has no tests".to_string())),
+    };
+    let time_limit = suggest_time_limit(slowest, params.multiplier);
+    println!(
+        "Slowest model time {} on {}",
+        format_time(slowest),
+        slowest_test.name.display()
+    );
+    println!(
+        "{}",
+        style(format!(
+            "Suggested time limit {} ({}x the slowest time)",
+            format_time(time_limit),
+            params.multiplier
+        ))
+        .green()
+        .bold()
+    );
+
+    if params.solutions {
+        if solutions.is_empty() {
+            println!("The package has no solutions to judge");
+        }
+        let judge = Arc::new(Judge::new(tests, runners).with_time_limit(Some(time_limit)));
+        for ((solution, name), runner) in solutions.iter().zip(&names).zip(&solution_runners) {
+            let runs = params.solution_runs.max(1);
+            let bar = ProgressBar::new((judge.tests().len() * runs) as u64);
+            let mut verdicts: Vec<Verdict> = vec![];
+            let mut times = vec![vec![]; judge.tests().len()];
+            for _ in 0..runs {
+                for (index, result) in judge.judge_all(runner, jobs, &bar)?.iter().enumerate() {
+                    verdicts.push(result.verdict);
+                    times[index].push(result.time);
+                }
+            }
+            bar.finish_and_clear();
+            // The median of every test, so that a single slow run does not
+            // count as the time of the solution.
+            let slowest = times
+                .into_iter()
+                .map(|mut times| {
+                    times.sort();
+                    TestTimes { times }.median()
+                })
+                .max()
+                .unwrap_or(Duration::ZERO);
+            let accepted = verdicts.iter().all(Verdict::is_accepted);
+            println!(
+                "{} {} with the slowest time {}",
+                style(name).bold(),
+                if accepted { "accepted" } else { "rejected" },
+                format_time(slowest)
+            );
+            if !meets_expectation(solution.expected, &verdicts) {
+                let message = match solution.expected {
+                    Expectation::TimeLimit if accepted => {
+                        format!("{} would still pass with this time limit", name)
+                    }
+                    expected => format!("{} is expected to be {}", name, expected),
+                };
+                println!("{}", style(format!("warning: {}", message)).yellow());
+            }
+        }
+    }
+
+    if params.write {
+        let time_limit = time_limit.as_millis() as u64;
+        if Config::set_time_limit(&package, time_limit)? {
+            println!("Wrote the time limit to the package config");
+        } else {
+            println!(
+                "{}",
+                style(format!(
+                    "warning: cannot edit the time limit in the package config, set timeLimit: {} yourself",
+                    time_limit
+                ))
+                .yellow()
+            );
+        }
+    }
+    Ok(())
+}
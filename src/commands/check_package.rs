@@ -0,0 +1,100 @@
+use clap::Parser;
+use console::style;
+use indicatif::ProgressBar;
+use iobot::archive;
+use iobot::check::{check_model, check_references, Finding};
+use iobot::config::{Config, ProgramOrShorthand};
+use iobot::error::Error;
+use iobot::judge::{list_tests, Judge};
+use iobot::runner::{build_all, take_runner, ProgramRole};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+#[derive(Parser)]
+pub struct Params {
+    /// Generated package with output files, a directory or a .zip or .tar.gz
+    /// archive
+    pub tests: PathBuf,
+    /// Model program to compare the outputs with, the model program recorded
+    /// in the package by default
+    #[clap(long)]
+    pub model: Option<PathBuf>,
+    /// Number of programs run in parallel
+    #[clap(short, long)]
+    pub jobs: Option<usize>,
+}
+
+pub fn run(params: Params) -> Result<(), Error> {
+    let package = archive::package_dir(&params.tests)?;
+    let config = Config::load(&package)?;
+    if !matches!(config, Config::OutputFiles(_)) {
+        return Err(Error::Usage(
+            "Only packages with output files can be checked".to_string(),
+        ));
+    }
+    let model = match (&params.model, config.model_program()) {
+        (Some(path), _) => {
+            let path = path.canonicalize().map_err(|error| {
+                Error::Usage(format!("Cannot find program {}: {}", path.display(), error))
+            })?;
+            Some(ProgramOrShorthand::Shorthand(path).to_program()?)
+        }
+        (None, Some(model)) => Some(model.to_program()?),
+        (None, None) => None,
+    };
+    if model.is_none() && config.verifier().is_none() {
+        return Err(Error::Usage(
+            "The package has neither a model program nor a verifier to check its outputs with, \
+             pass a model program with --model"
+                .to_string(),
+        ));
+    }
+    let mut programs = Judge::programs(&config)?;
+    if let Some(model) = model {
+        programs.push((ProgramRole::ModelProgram, model));
+    }
+    let mut runners =
+        build_all(&programs, &package).map_err(|(role, error)| Error::Compile(role, error))?;
+    let model = take_runner(&mut runners, ProgramRole::ModelProgram);
+    let judge = Arc::new(Judge::new(list_tests(&config, &package)?, runners));
+    let tests = judge.tests().len();
+    let jobs = config.options().job_count(params.jobs, false);
+
+    let mut findings: Vec<Finding> = vec![];
+    if let Some(model) = model {
+        println!("Running the model program on {} tests", tests);
+        let bar = ProgressBar::new(tests as u64);
+        bar.tick();
+        findings.extend(check_model(&judge, &Arc::new(model), jobs, &bar)?);
+        bar.finish_and_clear();
+    }
+    if judge.has_verifier() {
+        println!("Verifying {} reference outputs", tests);
+        let bar = ProgressBar::new(tests as u64);
+        bar.tick();
+        findings.extend(check_references(&judge, &bar)?);
+        bar.finish_and_clear();
+    }
+    // In the order of the tests, the model finding first for the same test.
+    findings.sort_by_key(|finding| finding.index);
+    for finding in &findings {
+        println!(
+            "{} {}",
+            style(finding.test.name.display()).bold(),
+            style(finding.inconsistency).red()
+        );
+    }
+    let mut inconsistent: Vec<usize> = findings.iter().map(|finding| finding.index).collect();
+    inconsistent.dedup();
+    if !inconsistent.is_empty() {
+        return Err(Error::InconsistentTests {
+            inconsistent: inconsistent.len(),
+            total: tests,
+        });
+    }
+    println!(
+        "{}",
+        style(format!("All {} tests are consistent", tests)).green()
+    );
+    Ok(())
+}
@@ -0,0 +1,623 @@
+//! Full-screen dashboard of a test run, for `iobot test --tui`.
+
+use iobot::error::Error;
+use iobot::judge::{Judge, JudgeError, JudgeProgress, TestOutputs, TestResult, Verdict};
+use iobot::runner::{RunMonitor, Runner};
+use iobot::score::{format_points, Scoring};
+use iobot::utils::{self, diff_outputs, format_memory, format_time, truncated_text, DiffLine};
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span, Text};
+use ratatui::widgets::{Block, Borders, Paragraph};
+use ratatui::{DefaultTerminal, Frame};
+use std::io;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+/// How often the screen is redrawn while no key is pressed.
+const REFRESH_INTERVAL: Duration = Duration::from_millis(100);
+/// Width of a test cell in the grid, including the space after it.
+const CELL_WIDTH: u16 = 6;
+/// Files and outputs longer than this are truncated in the detail pane.
+const PREVIEW_BYTES: usize = 16 * 1024;
+
+enum TestState {
+    Pending,
+    Running(Arc<RunMonitor>),
+    Done(TestResult),
+    /// Judging the test failed, e.g. because the verifier crashed.
+    Failed(String),
+}
+
+struct Slot {
+    state: TestState,
+    /// Increased on every change of the state, to know when the cached
+    /// detail pane is outdated.
+    version: u64,
+}
+
+/// States of all tests, shared with the judging threads.
+#[derive(Clone)]
+struct Progress {
+    slots: Arc<Mutex<Vec<Slot>>>,
+    /// Set when the user quits, so that the remaining tests are not judged.
+    cancelled: Arc<AtomicBool>,
+}
+
+impl Progress {
+    fn new(count: usize) -> Progress {
+        let slots = (0..count)
+            .map(|_| Slot {
+                state: TestState::Pending,
+                version: 0,
+            })
+            .collect();
+        Progress {
+            slots: Arc::new(Mutex::new(slots)),
+            cancelled: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Vec<Slot>> {
+        self.slots.lock().unwrap_or_else(|error| error.into_inner())
+    }
+
+    fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+    }
+
+    /// Results of the finished tests, `None` for the others.
+    fn results(&self) -> Vec<Option<TestResult>> {
+        self.lock()
+            .iter()
+            .map(|slot| match &slot.state {
+                TestState::Done(result) => Some(result.clone()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn set(&self, index: usize, state: TestState) {
+        let mut slots = self.lock();
+        slots[index].state = state;
+        slots[index].version += 1;
+    }
+}
+
+impl JudgeProgress for Progress {
+    fn started(&self, index: usize, monitor: &Arc<RunMonitor>) {
+        self.set(index, TestState::Running(monitor.clone()));
+    }
+
+    fn finished(&self, index: usize, result: &TestResult) {
+        self.set(index, TestState::Done(result.clone()));
+    }
+
+    fn cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Filter {
+    All,
+    Failed,
+    Accepted,
+    WrongAnswer,
+    RuntimeError,
+    TimeLimitExceeded,
+}
+
+impl Filter {
+    fn next(self) -> Filter {
+        match self {
+            Filter::All => Filter::Failed,
+            Filter::Failed => Filter::Accepted,
+            Filter::Accepted => Filter::WrongAnswer,
+            Filter::WrongAnswer => Filter::RuntimeError,
+            Filter::RuntimeError => Filter::TimeLimitExceeded,
+            Filter::TimeLimitExceeded => Filter::All,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Filter::All => "all",
+            Filter::Failed => "failed",
+            Filter::Accepted => "accepted",
+            Filter::WrongAnswer => "wrong answer",
+            Filter::RuntimeError => "runtime error",
+            Filter::TimeLimitExceeded => "time limit exceeded",
+        }
+    }
+
+    fn matches(self, state: &TestState) -> bool {
+        let verdict = match state {
+            TestState::Done(result) => Some(result.verdict),
+            _ => None,
+        };
+        match self {
+            Filter::All => true,
+            Filter::Failed => {
+                matches!(state, TestState::Failed(_))
+                    || verdict.is_some_and(|verdict| !verdict.is_accepted())
+            }
+            Filter::Accepted => verdict == Some(Verdict::Accepted),
+            Filter::WrongAnswer => verdict == Some(Verdict::WrongAnswer),
+            Filter::RuntimeError => matches!(verdict, Some(Verdict::RuntimeError(_))),
+            Filter::TimeLimitExceeded => verdict == Some(Verdict::TimeLimitExceeded),
+        }
+    }
+}
+
+fn state_color(state: &TestState) -> Color {
+    match state {
+        TestState::Pending => Color::DarkGray,
+        TestState::Running(_) => Color::Yellow,
+        TestState::Done(result) => match result.verdict {
+            Verdict::Accepted => Color::Green,
+            Verdict::PartiallyAccepted => Color::Cyan,
+            Verdict::WrongAnswer => Color::Red,
+            Verdict::RuntimeError(_) => Color::Magenta,
+            Verdict::TimeLimitExceeded => Color::Blue,
+            Verdict::Skipped => Color::Gray,
+        },
+        TestState::Failed(_) => Color::LightRed,
+    }
+}
+
+fn preview(bytes: &[u8]) -> String {
+    truncated_text(bytes, bytes.len() as u64, PREVIEW_BYTES)
+}
+
+fn preview_file(path: &Path) -> String {
+    utils::preview_file(path, PREVIEW_BYTES)
+        .unwrap_or_else(|error| format!("cannot read {}: {}", path.display(), error))
+}
+
+fn diff_text(outputs: &TestOutputs) -> Text<'static> {
+    let expected = match &outputs.expected {
+        Some(expected) => expected,
+        None => return Text::raw("no expected output"),
+    };
+    let diff = match diff_outputs(expected, &outputs.output, PREVIEW_BYTES) {
+        Some(diff) => diff,
+        None => return Text::raw("outputs are not valid UTF-8"),
+    };
+    diff.into_iter()
+        .map(|line| match line {
+            DiffLine::Same(line) => Line::raw(format!("  {}", line)),
+            DiffLine::Expected(line) => {
+                Line::styled(format!("- {}", line), Style::default().fg(Color::Red))
+            }
+            DiffLine::Actual(line) => {
+                Line::styled(format!("+ {}", line), Style::default().fg(Color::Green))
+            }
+        })
+        .collect()
+}
+
+/// Contents of the detail pane columns, cached as reading and diffing them
+/// on every redraw would be slow.
+struct Detail {
+    index: usize,
+    version: u64,
+    input: String,
+    expected: String,
+    actual: String,
+    diff: Text<'static>,
+}
+
+struct Dashboard<'a> {
+    judge: Arc<Judge>,
+    scoring: Option<&'a Scoring>,
+    solution: Arc<Runner>,
+    progress: Progress,
+    run: Option<JoinHandle<Result<Vec<TestResult>, JudgeError>>>,
+    /// Error of the run of all tests, which stops judging the remaining tests.
+    error: Option<JudgeError>,
+    filter: Filter,
+    /// Index of the selected test among all tests.
+    selected: usize,
+    scroll: u16,
+    detail: Option<Detail>,
+}
+
+impl Dashboard<'_> {
+    /// Indices of the tests shown with the current filter.
+    fn visible(&self) -> Vec<usize> {
+        self.progress
+            .lock()
+            .iter()
+            .enumerate()
+            .filter(|(_, slot)| self.filter.matches(&slot.state))
+            .map(|(index, _)| index)
+            .collect()
+    }
+
+    fn collect_run(&mut self) {
+        if self.run.as_ref().is_some_and(|run| run.is_finished()) {
+            let result = self.run.take().expect("run is finished").join();
+            self.error = match result {
+                Ok(Ok(_)) => None,
+                Ok(Err(error)) => Some(error),
+                Err(_) => Some(JudgeError::Panicked),
+            };
+        }
+    }
+
+    /// Whether the selected test exists and is shown with the current filter,
+    /// there is none when no test matches the filter.
+    fn has_selection(&self) -> bool {
+        self.progress
+            .lock()
+            .get(self.selected)
+            .is_some_and(|slot| self.filter.matches(&slot.state))
+    }
+
+    fn move_selection(&mut self, offset: isize, columns: usize) {
+        let visible = self.visible();
+        if visible.is_empty() {
+            return;
+        }
+        let position = visible
+            .iter()
+            .position(|index| *index >= self.selected)
+            .unwrap_or(visible.len() - 1);
+        let step = if offset.abs() > 1 {
+            offset.signum() * columns as isize
+        } else {
+            offset
+        };
+        let position = (position as isize + step).clamp(0, visible.len() as isize - 1);
+        self.selected = visible[position as usize];
+        self.scroll = 0;
+    }
+
+    /// Judges the selected test again on a new thread.
+    fn rerun(&self) {
+        if !self.has_selection()
+            || matches!(
+                self.progress.lock()[self.selected].state,
+                TestState::Running(_) | TestState::Pending
+            )
+        {
+            return;
+        }
+        let judge = self.judge.clone();
+        let solution = self.solution.clone();
+        let progress = self.progress.clone();
+        let index = self.selected;
+        thread::spawn(move || {
+            let monitor = Arc::new(RunMonitor::default());
+            progress.started(index, &monitor);
+            let test = &judge.tests()[index];
+            let state = match judge.judge_monitored(&solution, test, &monitor) {
+                Ok(result) => TestState::Done(result),
+                Err(error) => TestState::Failed(error.to_string()),
+            };
+            progress.set(index, state);
+        });
+    }
+
+    fn update_detail(&mut self) {
+        if !self.has_selection() {
+            self.detail = None;
+            return;
+        }
+        let (version, outputs) = {
+            let slots = self.progress.lock();
+            let slot = &slots[self.selected];
+            let outputs = match &slot.state {
+                TestState::Done(result) => result.outputs.clone(),
+                _ => None,
+            };
+            (slot.version, outputs)
+        };
+        if self
+            .detail
+            .as_ref()
+            .is_some_and(|detail| detail.index == self.selected && detail.version == version)
+        {
+            return;
+        }
+        let test = &self.judge.tests()[self.selected];
+        let missing = "no output".to_string();
+        self.detail = Some(Detail {
+            index: self.selected,
+            version,
+            input: preview_file(&test.input),
+            expected: match (&outputs, &test.output) {
+                (
+                    Some(TestOutputs {
+                        expected: Some(expected),
+                        ..
+                    }),
+                    _,
+                ) => preview(expected),
+                (_, Some(path)) => preview_file(path),
+                _ => missing.clone(),
+            },
+            actual: outputs
+                .as_ref()
+                .map(|outputs| preview(&outputs.output))
+                .unwrap_or_else(|| missing.clone()),
+            diff: outputs
+                .as_ref()
+                .map(diff_text)
+                .unwrap_or_else(|| Text::raw(missing)),
+        });
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let area = frame.area();
+        let visible = self.visible();
+        let columns = (area.width / CELL_WIDTH).max(1) as usize;
+        let rows = visible.len().div_ceil(columns).max(1) as u16;
+        let grid_height = (rows + 2).min(area.height * 2 / 5).max(3);
+        let [grid, summary, detail, help] = Layout::vertical([
+            Constraint::Length(grid_height),
+            Constraint::Length(1),
+            Constraint::Min(0),
+            Constraint::Length(1),
+        ])
+        .areas(area);
+        self.draw_grid(frame, grid, &visible, columns);
+        self.draw_summary(frame, summary);
+        self.update_detail();
+        self.draw_detail(frame, detail);
+        frame.render_widget(
+            Paragraph::new("←↑↓→ select  r run again  f filter  PgUp/PgDn scroll  q quit")
+                .style(Style::default().fg(Color::DarkGray)),
+            help,
+        );
+    }
+
+    fn draw_grid(&self, frame: &mut Frame, area: Rect, visible: &[usize], columns: usize) {
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .title(format!(" Tests ({}) ", self.filter.name()));
+        let inner = block.inner(area);
+        frame.render_widget(block, area);
+        let slots = self.progress.lock();
+        let selected_row = visible
+            .iter()
+            .position(|index| *index == self.selected)
+            .map_or(0, |position| position / columns);
+        // Scrolls so that the row of the selected test is visible.
+        let first_row = (selected_row + 1).saturating_sub(inner.height as usize);
+        let lines: Vec<Line> = visible
+            .chunks(columns)
+            .skip(first_row)
+            .take(inner.height as usize)
+            .map(|row| {
+                let spans = row.iter().flat_map(|index| {
+                    let mut style = Style::default()
+                        .fg(Color::Black)
+                        .bg(state_color(&slots[*index].state));
+                    if *index == self.selected {
+                        style = style.add_modifier(Modifier::BOLD | Modifier::REVERSED);
+                    }
+                    [
+                        Span::styled(
+                            format!("{:^width$}", index + 1, width = CELL_WIDTH as usize - 1),
+                            style,
+                        ),
+                        Span::raw(" "),
+                    ]
+                });
+                Line::from(spans.collect::<Vec<_>>())
+            })
+            .collect();
+        frame.render_widget(Paragraph::new(lines), inner);
+    }
+
+    fn draw_summary(&self, frame: &mut Frame, area: Rect) {
+        let score = self
+            .scoring
+            .map(|scoring| scoring.score(&self.progress.results()));
+        let slots = self.progress.lock();
+        let count = |filter: Filter| {
+            slots
+                .iter()
+                .filter(|slot| filter.matches(&slot.state))
+                .count()
+        };
+        let running = slots
+            .iter()
+            .filter(|slot| matches!(slot.state, TestState::Running(_)))
+            .count();
+        let pending = slots
+            .iter()
+            .filter(|slot| matches!(slot.state, TestState::Pending))
+            .count();
+        let mut spans = vec![
+            Span::styled(
+                format!("{} accepted", count(Filter::Accepted)),
+                Style::default().fg(Color::Green),
+            ),
+            Span::raw("  "),
+            Span::styled(
+                format!("{} failed", count(Filter::Failed)),
+                Style::default().fg(Color::Red),
+            ),
+            Span::raw("  "),
+            Span::styled(
+                format!("{} running", running),
+                Style::default().fg(Color::Yellow),
+            ),
+            Span::raw("  "),
+            Span::styled(
+                format!("{} pending", pending),
+                Style::default().fg(Color::DarkGray),
+            ),
+        ];
+        if let Some(score) = score {
+            spans.push(Span::raw("  "));
+            spans.push(Span::styled(
+                format!(
+                    "score {} of {}",
+                    format_points(score.points),
+                    format_points(score.max_points)
+                ),
+                Style::default().add_modifier(Modifier::BOLD),
+            ));
+        }
+        if let Some(error) = &self.error {
+            spans.push(Span::raw("  "));
+            spans.push(Span::styled(
+                format!("error: {}", error),
+                Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+            ));
+        }
+        frame.render_widget(Paragraph::new(Line::from(spans)), area);
+    }
+
+    fn draw_detail(&self, frame: &mut Frame, area: Rect) {
+        let [info, columns] =
+            Layout::vertical([Constraint::Length(2), Constraint::Min(0)]).areas(area);
+        if !self.has_selection() {
+            let message = if self.judge.tests().is_empty() {
+                "no tests"
+            } else {
+                "no test matches the filter"
+            };
+            frame.render_widget(
+                Paragraph::new(message).style(Style::default().fg(Color::DarkGray)),
+                info,
+            );
+            return;
+        }
+        let test = &self.judge.tests()[self.selected];
+        let slots = self.progress.lock();
+        let state = &slots[self.selected].state;
+        let color = state_color(state);
+        let status = match state {
+            TestState::Pending => Line::styled("pending", Style::default().fg(color)),
+            TestState::Running(monitor) => Line::styled(
+                format!(
+                    "running  {}  {}",
+                    format_time(monitor.elapsed().unwrap_or_default()),
+                    format_memory(monitor.memory())
+                ),
+                Style::default().fg(color),
+            ),
+            TestState::Done(result) => Line::styled(
+                format!(
+                    "{}  {}  {}",
+                    result.verdict,
+                    format_time(result.time),
+                    format_memory(result.memory)
+                ),
+                Style::default().fg(color),
+            ),
+            TestState::Failed(error) => {
+                Line::styled(format!("error: {}", error), Style::default().fg(color))
+            }
+        };
+        drop(slots);
+        frame.render_widget(
+            Paragraph::new(vec![
+                Line::styled(
+                    format!("{} {}", self.selected + 1, test.name.display()),
+                    Style::default().add_modifier(Modifier::BOLD),
+                ),
+                status,
+            ]),
+            info,
+        );
+        let detail = match &self.detail {
+            Some(detail) => detail,
+            None => return,
+        };
+        let areas = Layout::horizontal([Constraint::Ratio(1, 4); 4]).split(columns);
+        let panes = [
+            ("Input", Text::raw(detail.input.clone())),
+            ("Expected", Text::raw(detail.expected.clone())),
+            ("Actual", Text::raw(detail.actual.clone())),
+            ("Diff", detail.diff.clone()),
+        ];
+        for ((title, text), area) in panes.into_iter().zip(areas.iter()) {
+            frame.render_widget(
+                Paragraph::new(text)
+                    .scroll((self.scroll, 0))
+                    .block(Block::default().borders(Borders::ALL).title(title)),
+                *area,
+            );
+        }
+    }
+
+    fn event_loop(&mut self, terminal: &mut DefaultTerminal) -> io::Result<()> {
+        loop {
+            self.collect_run();
+            terminal.draw(|frame| self.draw(frame))?;
+            if !event::poll(REFRESH_INTERVAL)? {
+                continue;
+            }
+            let key = match event::read()? {
+                Event::Key(key) if key.kind == KeyEventKind::Press => key,
+                _ => continue,
+            };
+            let columns = (terminal.size()?.width / CELL_WIDTH).max(1) as usize;
+            match key.code {
+                KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+                KeyCode::Left | KeyCode::Char('h') => self.move_selection(-1, columns),
+                KeyCode::Right | KeyCode::Char('l') => self.move_selection(1, columns),
+                KeyCode::Up | KeyCode::Char('k') => self.move_selection(-2, columns),
+                KeyCode::Down | KeyCode::Char('j') => self.move_selection(2, columns),
+                KeyCode::PageUp => self.scroll = self.scroll.saturating_sub(10),
+                KeyCode::PageDown => self.scroll = self.scroll.saturating_add(10),
+                KeyCode::Char('r') => self.rerun(),
+                KeyCode::Char('f') => {
+                    self.filter = self.filter.next();
+                    self.move_selection(0, columns);
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Judges `solution` on all tests of `judge` while showing the dashboard,
+/// until the user quits. Returns the latest result of every test, `None` for
+/// tests that were not judged.
+pub fn run(
+    judge: &Arc<Judge>,
+    solution: &Arc<Runner>,
+    jobs: usize,
+    scoring: Option<&Scoring>,
+) -> Result<Vec<Option<TestResult>>, Error> {
+    let progress = Progress::new(judge.tests().len());
+    let run = {
+        let judge = judge.clone();
+        let solution = solution.clone();
+        let progress = progress.clone();
+        thread::spawn(move || judge.judge_all(&solution, jobs, &progress))
+    };
+    let mut dashboard = Dashboard {
+        judge: judge.clone(),
+        scoring,
+        solution: solution.clone(),
+        progress,
+        run: Some(run),
+        error: None,
+        filter: Filter::All,
+        selected: 0,
+        scroll: 0,
+        detail: None,
+    };
+    let mut terminal = ratatui::init();
+    let result = dashboard.event_loop(&mut terminal);
+    ratatui::restore();
+    dashboard.progress.cancel();
+    result?;
+    dashboard.collect_run();
+    if let Some(error) = dashboard.error {
+        return Err(error.into());
+    }
+    Ok(dashboard.progress.results())
+}
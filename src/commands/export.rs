@@ -0,0 +1,58 @@
+use clap::Parser;
+use console::style;
+use iobot::archive;
+use iobot::config::Config;
+use iobot::error::Error;
+use iobot::export::{export, Format, Package};
+use std::path::{Path, PathBuf};
+
+#[derive(Parser)]
+pub struct Params {
+    /// Target judge: polygon, cms, domjudge or kattis. Packages with a verifier
+    /// can only be exported to polygon, as the other judges call checkers
+    /// differently
+    #[clap(short, long)]
+    pub format: Format,
+    /// Generated package, a directory or a .zip or .tar.gz archive
+    pub package: PathBuf,
+    /// Target directory, or archive if it ends with .zip, .tar.gz or .tgz
+    pub target: PathBuf,
+}
+
+pub fn run(params: Params) -> Result<(), Error> {
+    let dir = archive::package_dir(&params.package)?;
+    let config = Config::load(&dir)?;
+    let mut package = Package::load(&config, &dir)?;
+    if dir != params.package {
+        if let Some(name) = params.package.file_name() {
+            package.name = archive::unpacked_path(Path::new(name))
+                .to_string_lossy()
+                .into_owned();
+        }
+    }
+    let warnings = export(&package, params.format, &params.target)?;
+    println!(
+        "{}",
+        style(format!(
+            "Exported {} tests to {}",
+            package.tests.len(),
+            params.target.display()
+        ))
+        .green()
+    );
+    for warning in warnings {
+        println!("{}", style(format!("warning: {}", warning)).yellow());
+    }
+    if package.checker.is_some() {
+        println!(
+            "{}",
+            style(format!(
+                "The verifier was copied as the checker, partial scores printed by it are not \
+                 supported by {}",
+                params.format
+            ))
+            .yellow()
+        );
+    }
+    Ok(())
+}
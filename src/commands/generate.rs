@@ -1,75 +1,492 @@
-use crate::commands::get_theme;
-use crate::config::{Config, FilesInput, GenerableConfig, Input, JustVerifier, OutputFiles};
-use crate::generator::{copy_or_generate_input, generate_outputs};
+use crate::commands::{get_theme, print_error, program_sources};
 use clap::Parser;
 use console::style;
 use dialoguer::Confirm;
-use std::fs::DirEntry;
-use std::path::PathBuf;
+use iobot::archive::{self, ArchiveFormat};
+use iobot::config::{
+    Config, Files, FilesInput, GenerableConfig, Input, InputRef, JustVerifier, ListedInput,
+    Options, OutputFiles, Program, TestList, CONFIG_FILE_NAME,
+};
+use iobot::error::Error;
+use iobot::generator::{
+    copy_inputs, generate_inputs, generate_outputs, generate_script_inputs, write_listed_inputs,
+    write_listed_outputs, GenerateOutputsError, Generator, GeneratorRun, ProgramCopier,
+};
+use iobot::provenance::{ProgramProvenance, Provenance};
+use iobot::runner::{take_runner, ProgramRole, RunnerCache};
+use iobot::samples::{load_samples, write_samples};
+use iobot::stats::InputStats;
+use iobot::utils::{format_size, list_config_files, natural_sort_key, FilesType};
+use iobot::watch::Watcher;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use std::{fs, io};
 
 #[derive(Parser)]
 pub struct Params {
     pub source: PathBuf,
     pub generated: PathBuf,
+    /// Number of programs run in parallel
+    #[clap(short, long)]
+    pub jobs: Option<usize>,
+    /// Also write the generated package to a .zip or .tar.gz archive
+    #[clap(long)]
+    pub archive: Option<PathBuf>,
+    /// Generate again when the source directory or programs change
+    #[clap(short, long)]
+    pub watch: bool,
 }
 
-pub fn run(params: Params) {
+/// Files changed since the previous generation.
+enum Changes {
+    All,
+    Paths(Vec<PathBuf>),
+}
+
+impl Changes {
+    /// Whether `path` or a file inside of it changed.
+    fn touches(&self, path: &Path) -> bool {
+        match self {
+            Changes::All => true,
+            Changes::Paths(paths) => {
+                let path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+                paths.iter().any(|changed| changed.starts_with(&path))
+            }
+        }
+    }
+}
+
+/// Results of the previous generation reused in watch mode.
+#[derive(Default)]
+struct State {
+    runners: RunnerCache,
+    inputs: Option<Files>,
+    /// Generator runs that wrote `inputs`.
+    runs: Vec<GeneratorRun>,
+    outputs: Option<Files>,
+}
+
+pub fn run(params: Params) -> Result<(), Error> {
     let theme = get_theme();
 
     if params.source.is_file() {
-        panic!("Source path should be a directory")
+        return Err(Error::Usage(
+            "Source path should be a directory".to_string(),
+        ));
     }
     if params.generated.is_file() {
-        panic!("Generated path should be a directory")
+        return Err(Error::Usage(
+            "Generated path should be a directory".to_string(),
+        ));
+    }
+    if let Some(archive) = &params.archive {
+        if ArchiveFormat::from_path(archive).is_none() {
+            return Err(Error::Usage(
+                "Archive path should end with .zip, .tar.gz or .tgz".to_string(),
+            ));
+        }
     }
-    fs::create_dir_all(&params.generated).unwrap();
-    let dir_contents: Vec<io::Result<DirEntry>> = params.generated.read_dir().unwrap().collect();
+    fs::create_dir_all(&params.generated)?;
+    let dir_contents = params
+        .generated
+        .read_dir()?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<io::Result<Vec<PathBuf>>>()?;
     if !dir_contents.is_empty() {
         let empty_dir = Confirm::with_theme(&theme)
             .with_prompt("Generated directory is not empty. Do you want to remove its contents?")
-            .interact()
-            .unwrap();
+            .interact()?;
         if !empty_dir {
-            return;
+            return Ok(());
+        }
+        trash::delete_all(dir_contents).map_err(|error| io::Error::other(error.to_string()))?;
+    }
+
+    let mut state = State::default();
+    if !params.watch {
+        return generate(&params, &mut state, &Changes::All);
+    }
+    let mut watcher = Watcher::new(std::slice::from_ref(&params.generated))?;
+    let mut changes = Changes::All;
+    loop {
+        if let Err(error) = generate(&params, &mut state, &changes) {
+            print_error(&error);
+        }
+        let programs = Config::load(&params.source)
+            .map(|config| program_sources(&config, &params.source))
+            .unwrap_or_default();
+        watcher.watch(&params.source, &programs)?;
+        println!(
+            "{}",
+            style("Watching for changes, press Ctrl+C to stop").dim()
+        );
+        changes = Changes::Paths(watcher.wait()?);
+    }
+}
+
+/// Removes the directory of files generated by a previous run, unless it is
+/// the generated directory itself.
+fn remove_generated(generated: &Path, files: &Option<Files>) -> io::Result<()> {
+    let dir = match files {
+        Some(files) => generated.join(&files.path),
+        None => return Ok(()),
+    };
+    if dir.is_dir() && dir.canonicalize()? != generated.canonicalize()? {
+        fs::remove_dir_all(dir)?;
+    }
+    Ok(())
+}
+
+/// Width of the longest bar of the input size histogram.
+const HISTOGRAM_WIDTH: usize = 40;
+/// Duplicate inputs listed before the rest are only counted.
+const LISTED_DUPLICATES: usize = 10;
+
+/// Prints the size distribution of the inputs in `generated` and warns about
+/// inputs identical to an earlier one.
+fn print_input_stats(generated: &Path, inputs: &Files) -> io::Result<()> {
+    let mut paths = list_config_files(inputs, generated, FilesType::Input)?;
+    // Inputs in test order, so that duplicates of generated inputs point to
+    // the lower index.
+    paths.sort_by_cached_key(|path| natural_sort_key(path));
+    let stats = InputStats::collect(&paths)?;
+    let (smallest, largest, median) = match (stats.smallest(), stats.largest(), stats.median()) {
+        (Some(smallest), Some(largest), Some(median)) => (smallest, largest, median),
+        _ => return Ok(()),
+    };
+    let name = |path: &Path| {
+        path.strip_prefix(generated)
+            .unwrap_or(path)
+            .display()
+            .to_string()
+    };
+    println!(
+        "{}",
+        style(format!(
+            "{} inputs, {} in total",
+            stats.sizes.len(),
+            format_size(stats.total())
+        ))
+        .bold()
+    );
+    println!(
+        "  smallest {} ({})",
+        format_size(smallest.1),
+        name(&smallest.0)
+    );
+    println!("  median   {}", format_size(median));
+    println!(
+        "  largest  {} ({})",
+        format_size(largest.1),
+        name(&largest.0)
+    );
+    let histogram = stats.histogram();
+    let most = histogram
+        .iter()
+        .map(|bucket| bucket.count)
+        .max()
+        .unwrap_or(1);
+    for bucket in &histogram {
+        println!(
+            "  <= {:>9} {:<width$} {}",
+            format_size(bucket.max_size),
+            "#".repeat((bucket.count * HISTOGRAM_WIDTH).div_ceil(most)),
+            bucket.count,
+            width = HISTOGRAM_WIDTH
+        );
+    }
+    if !stats.duplicates.is_empty() {
+        println!(
+            "{}",
+            style(format!(
+                "warning: {} inputs are identical to an earlier input",
+                stats.duplicates.len()
+            ))
+            .yellow()
+        );
+        for duplicate in stats.duplicates.iter().take(LISTED_DUPLICATES) {
+            println!(
+                "  {} is the same as {}",
+                name(&duplicate.path),
+                name(&duplicate.original)
+            );
+        }
+        if stats.duplicates.len() > LISTED_DUPLICATES {
+            println!("  and {} more", stats.duplicates.len() - LISTED_DUPLICATES);
         }
-        trash::delete_all(
-            dir_contents
-                .into_iter()
-                .map(|result| result.unwrap().path()),
-        )
-        .unwrap();
-    }
-    let source_config_path = params.source.join("iobot.yaml");
-    let config = Config::parse_bytes(&fs::read(source_config_path).unwrap())
-        .expect("Failed to parse config");
-    let config = config.to_generable().expect("Already generated");
-    let input_files_config =
-        copy_or_generate_input(&config.get_input(), &params.source, &params.generated).unwrap();
+    }
+    Ok(())
+}
+
+/// Copies the solutions listed in `options` to the generated package, like
+/// the verifier.
+fn copy_solutions(mut options: Options, copier: &mut ProgramCopier) -> io::Result<Options> {
+    for solution in &mut options.solutions {
+        solution.program = copier.copy(&solution.program)?;
+    }
+    Ok(options)
+}
+
+/// Writes the outputs listed in `list` in place of the previous outputs.
+/// Every test has to have one, unless `optional` is set and none of them has
+/// one. Returns `None` if no outputs were written.
+fn listed_outputs(
+    list: Option<&TestList>,
+    generated: &Path,
+    state: &mut State,
+    optional: bool,
+) -> Result<Option<Files>, Error> {
+    remove_generated(generated, &state.outputs.take())?;
+    let list = match list {
+        Some(list) if !optional || list.tests.iter().any(|test| test.output.is_some()) => list,
+        _ => return Ok(None),
+    };
+    let outputs = write_listed_outputs(list, generated, ".in", ".out")?;
+    state.outputs = Some(outputs.files.clone());
+    match outputs.missing.into_iter().next() {
+        Some(name) => Err(GenerateOutputsError::MissingOutput(name).into()),
+        None => Ok(Some(outputs.files)),
+    }
+}
+
+/// Runs the stages affected by `changes`: inputs are generated again when the
+/// config, input files or generator changed, and outputs when the inputs or
+/// model program changed. Programs whose sources did not change are not built
+/// again.
+fn generate(params: &Params, state: &mut State, changes: &Changes) -> Result<(), Error> {
+    let config = Config::load(&params.source)?;
+    let config = config.into_generable().ok_or_else(|| {
+        Error::Usage("Source config has nothing to generate, it is already generated".to_string())
+    })?;
+    let touches_program =
+        |path: Option<&Path>| path.is_some_and(|path| changes.touches(&params.source.join(path)));
+    let config_changed = changes.touches(&params.source.join(CONFIG_FILE_NAME));
+    let inputs_changed = config_changed
+        || match config.get_input() {
+            InputRef::Files(files) => changes.touches(&params.source.join(&files.path)),
+            InputRef::Generator(generator) => touches_program(generator.program.source_path()),
+            InputRef::Script(script) => script
+                .generators
+                .values()
+                .any(|program| touches_program(program.source_path())),
+            InputRef::Tests(list) => {
+                touches_program(
+                    list.generator
+                        .as_ref()
+                        .and_then(|program| program.source_path()),
+                ) || list.tests.iter().any(|test| match &test.input {
+                    ListedInput::File { file } => changes.touches(&params.source.join(file)),
+                    ListedInput::Inline { .. } | ListedInput::Generated { .. } => false,
+                })
+            }
+        };
+    let model_changed = match &config {
+        GenerableConfig::ModelProgram(config) => {
+            touches_program(config.model_program.source_path())
+        }
+        GenerableConfig::JustVerifier(_) | GenerableConfig::ListedTests(_) => false,
+    };
+
+    // Programs with the names of script generators.
+    let mut named_programs = vec![];
+    match config.get_input() {
+        InputRef::Generator(generator) => named_programs.push((
+            ProgramRole::Generator,
+            None,
+            generator.program.to_program()?,
+        )),
+        InputRef::Tests(TestList {
+            generator: Some(generator),
+            ..
+        }) => named_programs.push((ProgramRole::Generator, None, generator.to_program()?)),
+        InputRef::Script(script) => {
+            for (name, generator) in &script.generators {
+                named_programs.push((
+                    ProgramRole::Generator,
+                    Some(name.clone()),
+                    generator.to_program()?,
+                ));
+            }
+        }
+        InputRef::Files(_) | InputRef::Tests(_) => {}
+    }
+    if let GenerableConfig::ModelProgram(config) = &config {
+        named_programs.push((
+            ProgramRole::ModelProgram,
+            None,
+            config.model_program.to_program()?,
+        ));
+    }
+    // The verifier is not used for generating, building it only reports
+    // compilation errors early.
+    if let Some(verifier) = config.verifier() {
+        named_programs.push((ProgramRole::Verifier, None, verifier.to_program()?));
+    }
+    let programs: Vec<(ProgramRole, Program)> = named_programs
+        .iter()
+        .map(|(role, _, program)| (*role, program.clone()))
+        .collect();
+    let jobs = config.options().job_count(params.jobs, false);
+    if let Changes::Paths(paths) = changes {
+        state.runners.invalidate(paths, &params.source);
+    }
+    let mut runners = state
+        .runners
+        .build_all(&programs, &params.source)
+        .map_err(|(role, error)| Error::Compile(role, error))?;
+    let program_provenance = named_programs
+        .into_iter()
+        .map(|(role, name, program)| ProgramProvenance::new(role, name, &program, &params.source))
+        .collect::<io::Result<Vec<_>>>()?;
+
+    let input_files_config = match state.inputs.clone().filter(|_| !inputs_changed) {
+        Some(inputs) => inputs,
+        None => {
+            remove_generated(&params.generated, &state.inputs.take())?;
+            remove_generated(&params.generated, &state.outputs.take())?;
+            let (inputs, runs) = match config.get_input() {
+                InputRef::Files(files) => (
+                    copy_inputs(files, &params.source, &params.generated)?,
+                    vec![],
+                ),
+                InputRef::Generator(input) => {
+                    let generator = take_runner(&mut runners, ProgramRole::Generator)
+                        .expect("generator was built");
+                    let generated = generate_inputs(
+                        Arc::new(Generator::new(generator)),
+                        input,
+                        &params.generated,
+                        ".in",
+                        jobs,
+                        config.options().duplicate_inputs.unwrap_or_default(),
+                    )?;
+                    if generated.regenerated > 0 {
+                        println!("Regenerated {} duplicate inputs", generated.regenerated);
+                    }
+                    (generated.files, generated.runs)
+                }
+                InputRef::Script(script) => {
+                    let mut generators = BTreeMap::new();
+                    for name in script.generators.keys() {
+                        let generator = take_runner(&mut runners, ProgramRole::Generator)
+                            .expect("generators were built");
+                        generators.insert(name.clone(), Generator::new(generator));
+                    }
+                    let generated =
+                        generate_script_inputs(script, generators, &params.generated, ".in", jobs)?;
+                    (generated.files, generated.runs)
+                }
+                InputRef::Tests(list) => {
+                    let generator = take_runner(&mut runners, ProgramRole::Generator);
+                    let generated = write_listed_inputs(
+                        list,
+                        generator.map(Generator::new).as_ref(),
+                        &params.source,
+                        &params.generated,
+                        ".in",
+                    )?;
+                    (generated.files, generated.runs)
+                }
+            };
+            print_input_stats(&params.generated, &inputs)?;
+            state.inputs = Some(inputs.clone());
+            state.runs = runs;
+            inputs
+        }
+    };
+    let list = match config.get_input() {
+        InputRef::Tests(list) => Some(list.clone()),
+        InputRef::Files(_) | InputRef::Generator(_) | InputRef::Script(_) => None,
+    };
+    let mut copier = ProgramCopier::new(&params.source, &params.generated)?;
     let generated_config = match config {
         GenerableConfig::ModelProgram(config) => {
-            let output_files_config = generate_outputs(
-                &config.model_program.to_program().unwrap(),
-                &input_files_config,
-                &params.source,
-                &params.generated,
-                ".out",
-            )
-            .unwrap();
+            let output_files_config = match state.outputs.clone().filter(|_| !model_changed) {
+                Some(outputs) => outputs,
+                None => {
+                    remove_generated(&params.generated, &state.outputs.take())?;
+                    // Outputs listed in the config take precedence over the
+                    // model program.
+                    let listed = list
+                        .map(|list| write_listed_outputs(&list, &params.generated, ".in", ".out"))
+                        .transpose()?;
+                    let model_runner = take_runner(&mut runners, ProgramRole::ModelProgram)
+                        .expect("model program was built");
+                    let outputs = generate_outputs(
+                        Arc::new(model_runner),
+                        &input_files_config,
+                        &params.generated,
+                        ".out",
+                        jobs,
+                        listed.as_ref().map_or(&[], |listed| &listed.inputs),
+                    )?;
+                    state.outputs = Some(outputs.clone());
+                    outputs
+                }
+            };
             Config::OutputFiles(OutputFiles {
                 input: FilesInput::Files(input_files_config),
                 output_files: output_files_config,
-                verifier: config.verifier,
+                model_program: Some(copier.copy(&config.model_program)?),
+                verifier: config
+                    .verifier
+                    .map(|verifier| copier.copy(&verifier))
+                    .transpose()?,
+                options: copy_solutions(config.options, &mut copier)?,
             })
         }
-        GenerableConfig::JustVerifier(config) => Config::JustVerifier(JustVerifier {
-            input: Input::Files(input_files_config),
-            verifier: config.verifier,
+        GenerableConfig::JustVerifier(config) => {
+            let verifier = copier.copy(&config.verifier)?;
+            let options = copy_solutions(config.options, &mut copier)?;
+            // Listed outputs are used if every test has one.
+            match listed_outputs(list.as_ref(), &params.generated, state, true)? {
+                Some(output_files) => Config::OutputFiles(OutputFiles {
+                    input: FilesInput::Files(input_files_config),
+                    output_files,
+                    model_program: None,
+                    verifier: Some(verifier),
+                    options,
+                }),
+                None => Config::JustVerifier(JustVerifier {
+                    input: Input::Files(input_files_config),
+                    verifier,
+                    options,
+                }),
+            }
+        }
+        GenerableConfig::ListedTests(config) => Config::OutputFiles(OutputFiles {
+            input: FilesInput::Files(input_files_config),
+            output_files: listed_outputs(list.as_ref(), &params.generated, state, false)?
+                .expect("listed tests have outputs"),
+            model_program: None,
+            verifier: config
+                .verifier
+                .map(|verifier| copier.copy(&verifier))
+                .transpose()?,
+            options: copy_solutions(config.options, &mut copier)?,
         }),
     };
-    let generated_config_path = params.generated.join("./iobot.yaml");
-    let yaml = serde_yaml::to_string(&generated_config).unwrap();
-    fs::write(&generated_config_path, &yaml).unwrap();
-    println!("{}", style(format!("Finished generating")).green());
+    let yaml = generated_config.save(&params.generated)?;
+    Provenance::new(&params.source, program_provenance, state.runs.clone())?
+        .save(&params.generated)?;
+    let samples = load_samples(&generated_config, &params.generated)?;
+    if !samples.is_empty() {
+        let dir = write_samples(&samples, &params.generated)?;
+        println!(
+            "Wrote {} samples and statement snippets to {}",
+            samples.len(),
+            dir.display()
+        );
+    }
+    println!("{}", style("Finished generating").green());
     println!("{}", yaml);
+    if let Some(archive) = &params.archive {
+        archive::pack(&params.generated, archive)?;
+        println!(
+            "{}",
+            style(format!("Archived the package to {}", archive.display())).green()
+        );
+    }
+    Ok(())
 }
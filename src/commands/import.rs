@@ -0,0 +1,50 @@
+use clap::Parser;
+use console::style;
+use iobot::config::InputRef;
+use iobot::error::Error;
+use iobot::export::Format;
+use iobot::import::import;
+use std::path::PathBuf;
+
+#[derive(Parser)]
+pub struct Params {
+    /// Source judge: polygon, kattis or domjudge
+    #[clap(short, long)]
+    pub format: Format,
+    /// Package directory, or a .zip or .tar.gz archive
+    pub source: PathBuf,
+    /// Target directory, must be empty or not exist
+    pub target: PathBuf,
+}
+
+pub fn run(params: Params) -> Result<(), Error> {
+    if !params.source.exists() {
+        return Err(Error::Usage(format!(
+            "Source {} does not exist",
+            params.source.display()
+        )));
+    }
+    if params.target.exists() && params.target.read_dir()?.next().is_some() {
+        return Err(Error::Usage(format!(
+            "Target directory {} is not empty",
+            params.target.display()
+        )));
+    }
+    let imported = import(params.format, &params.source, &params.target)?;
+    for warning in &imported.warnings {
+        println!("{}", style(format!("warning: {}", warning)).yellow());
+    }
+    let message = match imported.config.get_input() {
+        InputRef::Files(_) => format!(
+            "Imported {} tests to {}",
+            imported.test_count,
+            params.target.display()
+        ),
+        InputRef::Generator(_) | InputRef::Tests(_) | InputRef::Script(_) => format!(
+            "Imported the package to {}, run `iobot generate` to generate its tests",
+            params.target.display()
+        ),
+    };
+    println!("{}", style(message).green());
+    Ok(())
+}
@@ -2,10 +2,12 @@ use crate::commands::get_theme;
 use clap::Parser;
 use console::style;
 use dialoguer::{Confirm, Input, Select};
+use iobot::config::{ConfigError, CONFIG_FILE_NAME};
+use iobot::error::Error;
 use maplit::btreemap;
 use serde_yaml::to_value;
 use std::collections::BTreeMap;
-use std::fs;
+use std::{fs, io};
 
 enum InputSelection {
     Files,
@@ -17,19 +19,18 @@ enum OutputSelection {
     ModelProgram,
 }
 
-fn prompt_input<'a>() -> (InputSelection, BTreeMap<&'a str, String>) {
+fn prompt_input<'a>() -> io::Result<(InputSelection, BTreeMap<&'a str, String>)> {
     let theme = get_theme();
 
     let selection = Select::with_theme(&theme)
         .with_prompt("Pick input type")
         .default(0)
         .items(&["Input files", "Generator script"])
-        .interact()
-        .unwrap();
+        .interact()?;
     let selection = match selection {
         0 => InputSelection::Files,
         1 => InputSelection::Generator,
-        _ => panic!(),
+        _ => unreachable!(),
     };
 
     let value = match selection {
@@ -37,8 +38,7 @@ fn prompt_input<'a>() -> (InputSelection, BTreeMap<&'a str, String>) {
             let path = Input::with_theme(&theme)
                 .with_prompt("Enter input files path")
                 .with_initial_text("./in/")
-                .interact_text()
-                .unwrap();
+                .interact_text()?;
             btreemap! {
                 "type" => "files".to_string(),
                 "path" => path,
@@ -48,83 +48,86 @@ fn prompt_input<'a>() -> (InputSelection, BTreeMap<&'a str, String>) {
             let program_path = Input::with_theme(&theme)
                 .with_prompt("Enter generator program path")
                 .with_initial_text("./")
-                .interact_text()
-                .unwrap();
+                .interact_text()?;
             btreemap! {
                 "type" => "generator".to_string(),
                 "program" => program_path,
             }
         }
     };
-    (selection, value)
+    Ok((selection, value))
 }
 
-fn prompt_model_program() -> String {
+fn prompt_model_program() -> io::Result<String> {
     Input::with_theme(&get_theme())
         .with_prompt("Enter model program path")
         .with_initial_text("./")
         .interact_text()
-        .unwrap()
 }
 
-fn prompt_verifier() -> String {
+fn prompt_verifier() -> io::Result<String> {
     Input::with_theme(&get_theme())
         .with_prompt("Enter verifier program path")
         .with_initial_text("./")
         .interact_text()
-        .unwrap()
 }
 
-fn prompt_output_files<'a>() -> BTreeMap<&'a str, String> {
+fn prompt_output_files<'a>() -> io::Result<BTreeMap<&'a str, String>> {
     let path = Input::with_theme(&get_theme())
         .with_prompt("Enter output files path")
         .with_initial_text("./out/")
-        .interact_text()
-        .unwrap();
-    btreemap! {
+        .interact_text()?;
+    Ok(btreemap! {
         "path" => path,
-    }
+    })
 }
 
 #[derive(Parser)]
 pub struct Params;
 
-pub fn run() {
+pub fn run() -> Result<(), Error> {
     let theme = get_theme();
 
-    let (input_selection, input_value) = prompt_input();
+    let (input_selection, input_value) = prompt_input()?;
 
     let mut result = btreemap! {
-        "input" => to_value(&input_value).unwrap(),
+        "input" => to_value(&input_value).map_err(ConfigError::Serialize)?,
     };
 
     let use_verifier_script = Confirm::with_theme(&theme)
         .with_prompt("Use a verifier script?")
-        .interact()
-        .unwrap();
+        .interact()?;
 
     if use_verifier_script {
-        result.insert("verifier", to_value(&prompt_verifier()).unwrap());
+        result.insert(
+            "verifier",
+            to_value(prompt_verifier()?).map_err(ConfigError::Serialize)?,
+        );
         match input_selection {
             InputSelection::Files => {
                 let output_selection = Select::with_theme(&theme)
                     .with_prompt("Pick output type (supplied to verifier)")
                     .default(0)
                     .items(&["None", "Output files", "Model program"])
-                    .interact()
-                    .unwrap();
+                    .interact()?;
                 let output_selection = match output_selection {
                     0 => None,
                     1 => Some(OutputSelection::Files),
                     2 => Some(OutputSelection::ModelProgram),
-                    _ => panic!(),
+                    _ => unreachable!(),
                 };
                 match output_selection {
                     Some(OutputSelection::Files) => {
-                        result.insert("output_files", to_value(&prompt_output_files()).unwrap());
+                        result.insert(
+                            "output_files",
+                            to_value(prompt_output_files()?).map_err(ConfigError::Serialize)?,
+                        );
                     }
                     Some(OutputSelection::ModelProgram) => {
-                        result.insert("modelProgram", to_value(&prompt_model_program()).unwrap());
+                        result.insert(
+                            "modelProgram",
+                            to_value(prompt_model_program()?).map_err(ConfigError::Serialize)?,
+                        );
                     }
                     None => {}
                 }
@@ -132,10 +135,12 @@ pub fn run() {
             InputSelection::Generator => {
                 let use_model_program = Confirm::with_theme(&theme)
                     .with_prompt("Use a model program (output supplied to verifier)?")
-                    .interact()
-                    .unwrap();
+                    .interact()?;
                 if use_model_program {
-                    result.insert("modelProgram", to_value(&prompt_model_program()).unwrap());
+                    result.insert(
+                        "modelProgram",
+                        to_value(prompt_model_program()?).map_err(ConfigError::Serialize)?,
+                    );
                 }
             }
         };
@@ -146,33 +151,36 @@ pub fn run() {
                     .with_prompt("Pick output type")
                     .default(0)
                     .items(&["Output files", "Model program"])
-                    .interact()
-                    .unwrap();
+                    .interact()?;
                 let output_selection = match output_selection {
                     0 => OutputSelection::Files,
                     1 => OutputSelection::ModelProgram,
-                    _ => panic!(),
+                    _ => unreachable!(),
                 };
                 match output_selection {
-                    OutputSelection::Files => {
-                        result.insert("output_files", to_value(&prompt_output_files()).unwrap())
-                    }
-                    OutputSelection::ModelProgram => {
-                        result.insert("modelProgram", to_value(&prompt_model_program()).unwrap())
-                    }
+                    OutputSelection::Files => result.insert(
+                        "output_files",
+                        to_value(prompt_output_files()?).map_err(ConfigError::Serialize)?,
+                    ),
+                    OutputSelection::ModelProgram => result.insert(
+                        "modelProgram",
+                        to_value(prompt_model_program()?).map_err(ConfigError::Serialize)?,
+                    ),
                 }
             }
-            InputSelection::Generator => {
-                result.insert("modelProgram", to_value(&prompt_model_program()).unwrap())
-            }
+            InputSelection::Generator => result.insert(
+                "modelProgram",
+                to_value(prompt_model_program()?).map_err(ConfigError::Serialize)?,
+            ),
         };
     };
 
-    let yaml = serde_yaml::to_string(&result).unwrap();
-    fs::write("iobot.yaml", &yaml).expect("Unable to write file");
+    let yaml = serde_yaml::to_string(&result).map_err(ConfigError::Serialize)?;
+    fs::write(CONFIG_FILE_NAME, &yaml).map_err(ConfigError::IO)?;
     println!(
         "{}",
-        style(format!("Saved to file {}", style("iobot.yaml").bold())).green()
+        style(format!("Saved to file {}", style(CONFIG_FILE_NAME).bold())).green()
     );
     println!("{}", yaml);
+    Ok(())
 }
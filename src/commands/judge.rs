@@ -0,0 +1,153 @@
+use clap::Parser;
+use console::{style, StyledObject};
+use indicatif::ProgressBar;
+use iobot::archive;
+use iobot::config::Config;
+use iobot::error::Error;
+use iobot::judge::{Judge, Verdict};
+use iobot::score::{format_points, Scoring};
+use iobot::submissions::{
+    find_submissions, judge_submission, to_csv, to_json, SubmissionResult, SubmissionVerdict,
+};
+use iobot::utils::format_time;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+#[derive(Parser)]
+pub struct Params {
+    /// Generated package, a directory or a .zip or .tar.gz archive
+    pub tests: PathBuf,
+    /// Directory with a file per submission, the language of a submission
+    /// is found from its extension
+    pub submissions: PathBuf,
+    /// Write the scoreboard to this .csv or .json file
+    #[clap(short, long)]
+    pub output: Option<PathBuf>,
+    /// Number of tests run in parallel, sequential by default when a time
+    /// limit is set
+    #[clap(short, long)]
+    pub jobs: Option<usize>,
+}
+
+enum Format {
+    Csv,
+    Json,
+}
+
+fn format(path: &Path) -> Result<Format, Error> {
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some("csv") => Ok(Format::Csv),
+        Some("json") => Ok(Format::Json),
+        _ => Err(Error::Usage(format!(
+            "Cannot write the scoreboard to {}, expected a .csv or .json file",
+            path.display()
+        ))),
+    }
+}
+
+pub fn run(params: Params) -> Result<(), Error> {
+    let format = params.output.as_deref().map(format).transpose()?;
+    let package = archive::package_dir(&params.tests)?;
+    let config = Config::load(&package)?;
+    let (submissions, unknown) = find_submissions(&params.submissions, config.options())?;
+    for path in &unknown {
+        println!(
+            "{}",
+            style(format!(
+                "Skipping {}, its language is unknown",
+                path.display()
+            ))
+            .yellow()
+        );
+    }
+    if submissions.is_empty() {
+        return Err(Error::Usage(format!(
+            "No submissions found in {}",
+            params.submissions.display()
+        )));
+    }
+    let judge = Arc::new(Judge::load(&config, &package)?);
+    let scoring = Scoring::new(&config.options().groups, judge.tests())?;
+    let jobs = config
+        .options()
+        .job_count(params.jobs, config.options().time_limit.is_some());
+    let bar = ProgressBar::new((judge.tests().len() * submissions.len()) as u64);
+    bar.tick();
+    let mut results = vec![];
+    for submission in &submissions {
+        bar.println(format!("Judging {}", submission.name));
+        let result = judge_submission(&judge, scoring.as_ref(), submission, &package, jobs, &bar)?;
+        if result.verdict == SubmissionVerdict::CompilationError {
+            bar.inc(judge.tests().len() as u64);
+        }
+        results.push(result);
+    }
+    bar.finish_and_clear();
+    print_scoreboard(&results);
+    if let (Some(path), Some(format)) = (&params.output, format) {
+        let scoreboard = match format {
+            Format::Csv => to_csv(&results),
+            Format::Json => to_json(&results),
+        };
+        fs::write(path, scoreboard)?;
+        println!("Wrote the scoreboard to {}", path.display());
+    }
+    Ok(())
+}
+
+fn verdict_code(verdict: SubmissionVerdict) -> StyledObject<&'static str> {
+    let code = style(verdict.code());
+    match verdict {
+        SubmissionVerdict::CompilationError => code.yellow(),
+        SubmissionVerdict::Judged(Verdict::Accepted) => code.green(),
+        SubmissionVerdict::Judged(Verdict::PartiallyAccepted) => code.cyan(),
+        SubmissionVerdict::Judged(Verdict::RuntimeError(_)) => code.magenta(),
+        SubmissionVerdict::Judged(Verdict::TimeLimitExceeded) => code.blue(),
+        SubmissionVerdict::Judged(_) => code.red(),
+    }
+}
+
+fn print_scoreboard(results: &[SubmissionResult]) {
+    let name_width = results
+        .iter()
+        .map(|result| result.submission.chars().count())
+        .max()
+        .unwrap_or(0)
+        .max("submission".len());
+    let language_width = results
+        .iter()
+        .map(|result| result.language.chars().count())
+        .max()
+        .unwrap_or(0)
+        .max("language".len());
+    println!(
+        "{}",
+        style(format!(
+            "{:<name_width$}  {:<language_width$}  verdict  {:>9}  {:>9}",
+            "submission",
+            "language",
+            "score",
+            "max time",
+            name_width = name_width,
+            language_width = language_width
+        ))
+        .bold()
+    );
+    for result in results {
+        println!(
+            "{:<name_width$}  {:<language_width$}  {:<7}  {:>9}  {:>9}",
+            result.submission,
+            result.language,
+            verdict_code(result.verdict),
+            format!(
+                "{}/{}",
+                format_points(result.score),
+                format_points(result.max_score)
+            ),
+            result.max_time.map(format_time).unwrap_or_default(),
+            name_width = name_width,
+            language_width = language_width
+        );
+    }
+}
@@ -1,9 +1,50 @@
+use console::style;
 use dialoguer::theme::ColorfulTheme;
+use iobot::config::{Config, InputRef};
+use iobot::error::Error;
+use iobot::remote;
+use std::path::{Path, PathBuf};
 
+pub mod calibrate;
+pub mod check_package;
+pub mod dashboard;
+pub mod export;
 pub mod generate;
+pub mod import;
 pub mod init;
+pub mod judge;
+pub mod serve;
 pub mod test;
 
 pub fn get_theme() -> ColorfulTheme {
     ColorfulTheme::default()
 }
+
+pub fn print_error(error: &Error) {
+    eprintln!("{} {}", style("error:").red().bold(), error);
+}
+
+/// Local source files of the programs in `config`, for watch mode.
+pub fn program_sources(config: &Config, dir: &Path) -> Vec<PathBuf> {
+    let mut programs = vec![config.model_program(), config.verifier()];
+    match config.get_input() {
+        InputRef::Generator(generator) => programs.push(Some(&generator.program)),
+        InputRef::Tests(list) => programs.push(list.generator.as_ref()),
+        InputRef::Script(script) => programs.extend(script.generators.values().map(Some)),
+        InputRef::Files(_) => {}
+    }
+    programs.extend(
+        config
+            .options()
+            .solutions
+            .iter()
+            .map(|solution| Some(&solution.program)),
+    );
+    programs
+        .into_iter()
+        .flatten()
+        .filter_map(|program| program.source_path())
+        .filter(|path| !remote::is_url(path))
+        .map(|path| dir.join(path))
+        .collect()
+}
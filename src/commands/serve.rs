@@ -0,0 +1,72 @@
+use clap::Parser;
+use iobot::archive;
+use iobot::config::Config;
+use iobot::error::Error;
+use iobot::judge::Judge;
+use iobot::score::Scoring;
+use iobot::server::{serve, Problem, DEFAULT_ADDRESS};
+use std::net::ToSocketAddrs;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+#[derive(Parser)]
+pub struct Params {
+    /// Generated packages to judge, each a directory or a .zip or .tar.gz
+    /// archive named after its problem
+    #[clap(required = true)]
+    pub packages: Vec<PathBuf>,
+    /// Address to listen on
+    #[clap(short, long, default_value = DEFAULT_ADDRESS)]
+    pub address: String,
+    /// Allow listening on an address reachable from other machines, whose
+    /// users can then run any program on this one
+    #[clap(long)]
+    pub allow_remote: bool,
+    /// Number of tests run in parallel, sequential by default when a time
+    /// limit is set
+    #[clap(short, long)]
+    pub jobs: Option<usize>,
+}
+
+pub fn run(params: Params) -> Result<(), Error> {
+    // Submissions run unsandboxed, so only local users may send them unless
+    // told otherwise. Addresses that do not resolve fail when binding.
+    let remote = params
+        .address
+        .to_socket_addrs()
+        .is_ok_and(|mut addresses| addresses.any(|address| !address.ip().is_loopback()));
+    if remote && !params.allow_remote {
+        return Err(Error::Usage(format!(
+            "{} is reachable from other machines, pass --allow-remote to run their submissions",
+            params.address
+        )));
+    }
+    let mut problems: Vec<Problem> = vec![];
+    for path in &params.packages {
+        let name = archive::unpacked_path(&path.canonicalize()?)
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        if problems.iter().any(|problem| problem.name == name) {
+            return Err(Error::Usage(format!("Several packages are named {}", name)));
+        }
+        let package = archive::package_dir(path)?;
+        let config = Config::load(&package)?;
+        let judge = Judge::load(&config, &package)?;
+        let scoring = Scoring::new(&config.options().groups, judge.tests())?;
+        println!("Loaded problem {} with {} tests", name, judge.tests().len());
+        problems.push(Problem {
+            name,
+            jobs: config
+                .options()
+                .job_count(params.jobs, config.options().time_limit.is_some()),
+            options: config.options().clone(),
+            judge: Arc::new(judge),
+            scoring,
+            package,
+        });
+    }
+    println!("Serving on http://{}, press Ctrl+C to stop", params.address);
+    serve(problems, &params.address)?;
+    Ok(())
+}
@@ -1,23 +1,411 @@
+use crate::commands::{dashboard, print_error, program_sources};
 use clap::Parser;
-use std::path::PathBuf;
+use console::{style, StyledObject};
+use indicatif::ProgressBar;
+use iobot::archive;
+use iobot::config::{Config, Expectation, Program, ProgramOrShorthand};
+use iobot::error::Error;
+use iobot::judge::{list_tests, meets_expectation, FailFast, Judge, TestCase, TestResult, Verdict};
+use iobot::report::Report;
+use iobot::runner::{describe, ProgramRole, Runner, RunnerCache};
+use iobot::score::{format_points, Score, Scoring};
+use iobot::watch::Watcher;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
 #[derive(Parser)]
 pub struct Params {
+    /// Generated package, a directory or a .zip or .tar.gz archive
     pub tests: PathBuf,
-    pub program: PathBuf,
-}
-
-// pub fn run(params: Params) {
-//     let source_config_path = params.source.join("iobot.yaml");
-//     let config =
-//         parse_config(&fs::read(source_config_path).unwrap()).expect("Failed to parse config");
-//     println!("{:#?}", config);
-//     match config {
-//         Config::OutputFiles(_) => {}
-//         Config::ModelProgram(config) => {
-//             let model_runner =
-//                 make_runner(config.model_program.to_program().unwrap(), &params.source).unwrap();
-//         }
-//         Config::JustVerifier(_) => {}
-//     }
-// }
+    /// Solutions to test, the `solutions` of the package config if none are
+    /// given
+    pub programs: Vec<PathBuf>,
+    /// Number of tests run in parallel, sequential by default when a time
+    /// limit is set
+    #[clap(short, long)]
+    pub jobs: Option<usize>,
+    /// Test again when the program or the package change
+    #[clap(short, long)]
+    pub watch: bool,
+    /// Show a full-screen dashboard with the verdicts and outputs of the tests
+    #[clap(long)]
+    pub tui: bool,
+    /// Stop at the first test that is not accepted, even partially accepted,
+    /// skipping the remaining tests
+    #[clap(long)]
+    pub fail_fast: bool,
+    /// Skip the remaining tests of a group after one of its tests is not
+    /// accepted
+    #[clap(long)]
+    pub group_fail_fast: bool,
+    /// Write a self-contained HTML report of the results to this file
+    #[clap(long)]
+    pub report: Option<PathBuf>,
+}
+
+pub fn run(params: Params) -> Result<(), Error> {
+    let package = archive::package_dir(&params.tests)?;
+    let programs = params
+        .programs
+        .iter()
+        .map(|program| {
+            program.canonicalize().map_err(|error| {
+                Error::Usage(format!(
+                    "Cannot find program {}: {}",
+                    program.display(),
+                    error
+                ))
+            })
+        })
+        .collect::<Result<Vec<PathBuf>, Error>>()?;
+    if params.watch && params.tui {
+        return Err(Error::Usage(
+            "The dashboard cannot be used while watching".to_string(),
+        ));
+    }
+    if programs.len() != 1 && (params.tui || params.report.is_some()) {
+        return Err(Error::Usage(
+            "The dashboard and report need a single solution".to_string(),
+        ));
+    }
+    let mut runners = RunnerCache::default();
+    if !params.watch {
+        return test(&params, &package, &programs, &mut runners);
+    }
+    if package != params.tests {
+        return Err(Error::Usage(
+            "Watching needs a package directory, not an archive".to_string(),
+        ));
+    }
+    let mut watcher = Watcher::new(&[])?;
+    loop {
+        if let Err(error) = test(&params, &package, &programs, &mut runners) {
+            print_error(&error);
+        }
+        let mut files = programs.clone();
+        if let Ok(config) = Config::load(&package) {
+            files.extend(program_sources(&config, &package));
+        }
+        watcher.watch(&package, &files)?;
+        println!(
+            "{}",
+            style("Watching for changes, press Ctrl+C to stop").dim()
+        );
+        runners.invalidate(&watcher.wait()?, &package);
+    }
+}
+
+/// A solution to test with the outcome it should get.
+struct Solution {
+    name: String,
+    program: Program,
+    expected: Expectation,
+}
+
+/// The solutions given on the command line, expected to be accepted, or the
+/// solutions of the package config.
+fn solutions(config: &Config, programs: &[PathBuf]) -> Result<Vec<Solution>, Error> {
+    if !programs.is_empty() {
+        return programs
+            .iter()
+            .map(|path| {
+                Ok(Solution {
+                    name: path.display().to_string(),
+                    program: ProgramOrShorthand::Shorthand(path.clone()).to_program()?,
+                    expected: Expectation::Accepted,
+                })
+            })
+            .collect();
+    }
+    let solutions = &config.options().solutions;
+    if solutions.is_empty() {
+        return Err(Error::Usage(
+            "No solution given and the package config has no solutions".to_string(),
+        ));
+    }
+    solutions
+        .iter()
+        .map(|solution| {
+            let program = solution.program.to_program()?;
+            Ok(Solution {
+                name: describe(&program),
+                program,
+                expected: solution.expected,
+            })
+        })
+        .collect()
+}
+
+fn test(
+    params: &Params,
+    package: &Path,
+    programs: &[PathBuf],
+    runners: &mut RunnerCache,
+) -> Result<(), Error> {
+    let config = Config::load(package)?;
+    let solutions = solutions(&config, programs)?;
+    let tests = list_tests(&config, package)?;
+    let mut programs: Vec<(ProgramRole, Program)> = solutions
+        .iter()
+        .map(|solution| (ProgramRole::Solution, solution.program.clone()))
+        .collect();
+    programs.extend(Judge::programs(&config)?);
+    let mut runners = runners
+        .build_all(&programs, package)
+        .map_err(|(role, error)| Error::Compile(role, error))?;
+    let solution_runners: Vec<Arc<Runner>> = runners
+        .drain(..solutions.len())
+        .map(|(_, runner)| Arc::new(runner))
+        .collect();
+    let time_limit = config.options().time_limit();
+    let judge = Arc::new(
+        Judge::new(tests, runners)
+            .with_time_limit(time_limit)
+            .with_outputs_kept(params.tui || params.report.is_some())
+            .with_fail_fast(if params.fail_fast {
+                FailFast::Run
+            } else if params.group_fail_fast {
+                FailFast::Group
+            } else {
+                FailFast::Never
+            }),
+    );
+    let jobs = config
+        .options()
+        .job_count(params.jobs, config.options().time_limit.is_some());
+    match solution_runners.as_slice() {
+        [solution] if !params.programs.is_empty() => {
+            test_solution(params, &config, &judge, solution, jobs)
+        }
+        _ => compare_solutions(&judge, &solutions, &solution_runners, jobs),
+    }
+}
+
+/// Tests a single solution given on the command line.
+fn test_solution(
+    params: &Params,
+    config: &Config,
+    judge: &Arc<Judge>,
+    solution: &Arc<Runner>,
+    jobs: usize,
+) -> Result<(), Error> {
+    let scoring = Scoring::new(&config.options().groups, judge.tests())?;
+    let tests = judge.tests();
+    let results: Vec<Option<TestResult>> = if params.tui {
+        dashboard::run(judge, solution, jobs, scoring.as_ref())?
+    } else {
+        let bar = ProgressBar::new(tests.len() as u64);
+        bar.tick();
+        let results = judge.judge_all(solution, jobs, &bar)?;
+        bar.finish();
+        results.into_iter().map(Some).collect()
+    };
+    let score = scoring.map(|scoring| scoring.score(&results));
+    if let Some(path) = &params.report {
+        let report = Report {
+            solution: &params.programs[0],
+            package: &params.tests,
+            tests,
+            results: &results,
+            score: score.as_ref(),
+        };
+        fs::write(path, report.to_html())?;
+        println!("Wrote the report to {}", path.display());
+    }
+
+    // Tests left unjudged when the dashboard is closed early count as failed.
+    let failed: Vec<(&TestCase, &Option<TestResult>)> = tests
+        .iter()
+        .zip(&results)
+        .filter(|(_, result)| {
+            !result
+                .as_ref()
+                .is_some_and(|result| result.verdict.is_accepted())
+        })
+        .collect();
+    let mut skipped = 0;
+    for (test, result) in &failed {
+        let verdict = match result {
+            Some(result) if result.verdict == Verdict::Skipped => {
+                skipped += 1;
+                continue;
+            }
+            Some(result) => style(result.verdict.to_string()).red(),
+            None => style("not judged".to_string()).yellow(),
+        };
+        println!("{} {}", style(test.name.display()).bold(), verdict);
+    }
+    if skipped > 0 {
+        println!(
+            "{}",
+            style(format!("{} tests skipped after a failure", skipped)).yellow()
+        );
+    }
+    if let Some(score) = &score {
+        print_score(score);
+    }
+    if !failed.is_empty() {
+        return Err(Error::TestsFailed {
+            failed: failed.len(),
+            total: tests.len(),
+        });
+    }
+    println!(
+        "{}",
+        style(format!("All {} tests passed", tests.len())).green()
+    );
+    Ok(())
+}
+
+/// Short name of `verdict` for the comparison matrix.
+fn verdict_code(verdict: Verdict) -> StyledObject<&'static str> {
+    match verdict {
+        Verdict::Accepted => style("AC").green(),
+        Verdict::PartiallyAccepted => style("PA").cyan(),
+        Verdict::WrongAnswer => style("WA").red(),
+        Verdict::RuntimeError(_) => style("RE").magenta(),
+        Verdict::TimeLimitExceeded => style("TL").blue(),
+        Verdict::Skipped => style("SK").dim(),
+    }
+}
+
+/// Describes the outcome of a solution, e.g. `wrong answer on 2 tests`.
+fn describe_outcome(verdicts: &[Verdict]) -> String {
+    let mut counts: Vec<(String, usize)> = vec![];
+    for verdict in verdicts {
+        let name = match verdict {
+            Verdict::Accepted | Verdict::Skipped => continue,
+            // Exit codes are only shown per test.
+            Verdict::RuntimeError(_) => "runtime error".to_string(),
+            verdict => verdict.to_string(),
+        };
+        match counts.iter_mut().find(|(counted, _)| *counted == name) {
+            Some((_, count)) => *count += 1,
+            None => counts.push((name, 1)),
+        }
+    }
+    if counts.is_empty() {
+        return "accepted".to_string();
+    }
+    counts
+        .iter()
+        .map(|(name, count)| match count {
+            1 => format!("{} on 1 test", name),
+            count => format!("{} on {} tests", name, count),
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Judges all `solutions` and prints a matrix of their verdicts on every
+/// test, flagging the solutions that do not get their expected outcome.
+fn compare_solutions(
+    judge: &Arc<Judge>,
+    solutions: &[Solution],
+    runners: &[Arc<Runner>],
+    jobs: usize,
+) -> Result<(), Error> {
+    let tests = judge.tests();
+    let bar = ProgressBar::new((tests.len() * runners.len()) as u64);
+    bar.tick();
+    let mut verdicts: Vec<Vec<Verdict>> = vec![];
+    for runner in runners {
+        let results = judge.judge_all(runner, jobs, &bar)?;
+        verdicts.push(results.iter().map(|result| result.verdict).collect());
+    }
+    bar.finish();
+
+    let name_width = tests
+        .iter()
+        .map(|test| test.name.display().to_string().chars().count())
+        .max()
+        .unwrap_or(0)
+        .max("test".len());
+    let header: String = (1..=solutions.len())
+        .map(|index| format!(" {:>3}", index))
+        .collect();
+    println!(
+        "{}",
+        style(format!("{:<width$}{}", "test", header, width = name_width)).bold()
+    );
+    for (index, test) in tests.iter().enumerate() {
+        let row: String = verdicts
+            .iter()
+            .map(|verdicts| format!("  {}", verdict_code(verdicts[index])))
+            .collect();
+        println!(
+            "{:<width$}{}",
+            test.name.display().to_string(),
+            row,
+            width = name_width
+        );
+    }
+    println!();
+    let mut unexpected = 0;
+    for (index, (solution, verdicts)) in solutions.iter().zip(&verdicts).enumerate() {
+        let outcome = describe_outcome(verdicts);
+        let status = if meets_expectation(solution.expected, verdicts) {
+            style(outcome).green()
+        } else {
+            unexpected += 1;
+            style(format!("{}, expected {}", outcome, solution.expected)).red()
+        };
+        println!(
+            "{:>3} {} {}",
+            index + 1,
+            style(&solution.name).bold(),
+            status
+        );
+    }
+    if unexpected > 0 {
+        return Err(Error::UnexpectedOutcomes {
+            unexpected,
+            total: solutions.len(),
+        });
+    }
+    println!(
+        "{}",
+        style(format!(
+            "All {} solutions got their expected outcome",
+            solutions.len()
+        ))
+        .green()
+    );
+    Ok(())
+}
+
+fn print_score(score: &Score) {
+    println!(
+        "{}",
+        style(format!(
+            "Score {} of {}",
+            format_points(score.points),
+            format_points(score.max_points)
+        ))
+        .bold()
+    );
+    for group in &score.groups {
+        let points = format!(
+            "{} of {}",
+            format_points(group.points),
+            format_points(group.max_points)
+        );
+        let points = if group.points >= group.max_points {
+            style(points).green()
+        } else {
+            style(points).red()
+        };
+        let note = match &group.failed_dependency {
+            Some(dependency) => format!(", needs {}", dependency),
+            None => String::new(),
+        };
+        println!(
+            "  {} {} ({}{})",
+            style(&group.name).bold(),
+            points,
+            group.policy,
+            note
+        );
+    }
+}
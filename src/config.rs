@@ -1,14 +1,49 @@
-use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use crate::utils::get_thread_count;
+use crate::{archive, remote};
+use serde::{Deserialize, Deserializer, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use std::{cmp, error, fmt, fs, io};
+
+/// Name of the config file in source and generated directories.
+pub const CONFIG_FILE_NAME: &str = "iobot.yaml";
 
 #[derive(Clone, Serialize, Deserialize, Debug)]
 pub struct Files {
+    /// A directory, an archive, a directory inside an archive or a URL of one
+    /// of the latter two.
     pub path: PathBuf,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub extensions: Option<Vec<String>>,
+    /// SHA-256 hash of the archive at a `path` URL.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub checksum: Option<String>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+impl Files {
+    /// Local directory with the files, with a relative `path` resolved
+    /// against `base`. Archives are extracted and URLs fetched first.
+    pub fn dir(&self, base: &Path) -> io::Result<PathBuf> {
+        match self.path.to_str() {
+            Some(url) if remote::is_url(&self.path) => {
+                Ok(remote::fetch_files(url, self.checksum.as_deref())?)
+            }
+            _ => archive::resolve(base, &self.path),
+        }
+    }
+
+    /// Relative path of a local directory to copy the files to, with archive
+    /// extensions removed and URLs replaced by the archive name.
+    pub fn unpacked_path(&self) -> PathBuf {
+        match self.path.to_str().and_then(remote::unpacked_path) {
+            Some(path) if remote::is_url(&self.path) => path,
+            _ => archive::unpacked_path(&self.path),
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
 #[serde(untagged)]
 pub enum ProgramOrShorthand {
     Shorthand(PathBuf),
@@ -16,6 +51,22 @@ pub enum ProgramOrShorthand {
 }
 
 impl ProgramOrShorthand {
+    /// Path of the source file of the program, if the program has one.
+    pub fn source_path(&self) -> Option<&Path> {
+        match self {
+            ProgramOrShorthand::Shorthand(path) => Some(path),
+            ProgramOrShorthand::Value(program) => program.source_path(),
+        }
+    }
+
+    /// Local path of the source file, fetched first if it is a URL.
+    pub fn local_source_path(&self) -> Result<Option<PathBuf>, remote::FetchError> {
+        match self {
+            ProgramOrShorthand::Shorthand(path) => remote::local_path(path, None).map(Some),
+            ProgramOrShorthand::Value(program) => program.local_source_path(),
+        }
+    }
+
     pub fn to_program(&self) -> Result<Program, UnknownExtensionError> {
         match self {
             ProgramOrShorthand::Value(program) => Ok(program.clone()),
@@ -27,9 +78,11 @@ impl ProgramOrShorthand {
                         "cpp" => Ok(Program::GPP {
                             path: shorthand.clone(),
                             compiler_args: None,
+                            checksum: None,
                         }),
                         "py" => Ok(Program::Python {
                             path: shorthand.clone(),
+                            checksum: None,
                         }),
                         _ => Err(UnknownExtensionError),
                     },
@@ -42,23 +95,37 @@ impl ProgramOrShorthand {
 #[derive(Debug, Clone)]
 pub struct UnknownExtensionError;
 
-#[derive(Clone, Serialize, Deserialize, Debug)]
+impl fmt::Display for UnknownExtensionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "cannot infer the program type from its file extension")
+    }
+}
+
+impl error::Error for UnknownExtensionError {}
+
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
 pub struct Command {
     pub command: String,
     pub args: Option<Vec<String>>,
 }
 
-#[derive(Clone, Serialize, Deserialize, Debug)]
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
 #[serde(tag = "mode", rename_all = "camelCase")]
+#[allow(clippy::upper_case_acronyms)]
 pub enum Program {
     #[serde(rename = "g++")]
     GPP {
         path: PathBuf,
         #[serde(rename = "compilerArgs")]
         compiler_args: Option<Vec<String>>,
+        /// SHA-256 hash of the source at a `path` URL.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        checksum: Option<String>,
     },
     Python {
         path: PathBuf,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        checksum: Option<String>,
     },
     Command {
         run: Command,
@@ -70,18 +137,184 @@ pub enum Program {
     },
 }
 
+impl Program {
+    /// Path of the source file of the program, if the program has one.
+    pub fn source_path(&self) -> Option<&Path> {
+        match self {
+            Program::GPP { path, .. } | Program::Python { path, .. } => Some(path),
+            Program::Command { .. } | Program::Compiled { .. } => None,
+        }
+    }
+
+    /// Local path of the source file, fetched first if it is a URL.
+    pub fn local_source_path(&self) -> Result<Option<PathBuf>, remote::FetchError> {
+        match self {
+            Program::GPP { path, checksum, .. } | Program::Python { path, checksum } => {
+                remote::local_path(path, checksum.as_deref()).map(Some)
+            }
+            Program::Command { .. } | Program::Compiled { .. } => Ok(None),
+        }
+    }
+}
+
+/// A group of generated tests.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct GeneratorGroup {
+    pub name: String,
+    pub count: u64,
+}
+
+/// Inputs written by a generator program, see [`crate::naming`] for the test
+/// names.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct GeneratorInput {
+    pub program: ProgramOrShorthand,
+    /// Number of tests without groups, 100 by default.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub count: Option<u64>,
+    /// Template of the test names.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// Groups of tests generated one after another. The generator gets the
+    /// group name as its second argument.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub groups: Vec<GeneratorGroup>,
+}
+
+/// Where the input of a listed test comes from.
+#[derive(Clone, Serialize, Debug)]
+#[serde(untagged)]
+pub enum ListedInput {
+    /// The input text itself.
+    Inline { input: String },
+    /// A file relative to the config directory.
+    File { file: PathBuf },
+    /// The output of the generator of the list run with these arguments.
+    Generated { args: Vec<String> },
+}
+
+/// Deserializes program arguments, which may be written as numbers.
+fn deserialize_args<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<String>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Arg {
+        String(String),
+        Integer(i64),
+        Float(f64),
+    }
+    Ok(Vec::<Arg>::deserialize(deserializer)?
+        .into_iter()
+        .map(|arg| match arg {
+            Arg::String(arg) => arg,
+            Arg::Integer(arg) => arg.to_string(),
+            Arg::Float(arg) => arg.to_string(),
+        })
+        .collect())
+}
+
+/// A test written out in the config.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+#[serde(try_from = "RawListedTest")]
+pub struct ListedTest {
+    /// Name of the input file without the extension, like `edge/max`. Tests
+    /// are named by their index in the list by default.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(flatten)]
+    pub input: ListedInput,
+    /// The expected output, generated by the model program if missing.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub output: Option<String>,
+}
+
+/// A listed test as written, checked to have exactly one input source when
+/// converted, so that a wrong field gets a clearer error than an untagged
+/// enum would give.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawListedTest {
+    name: Option<String>,
+    input: Option<String>,
+    file: Option<PathBuf>,
+    #[serde(default, deserialize_with = "deserialize_optional_args")]
+    args: Option<Vec<String>>,
+    output: Option<String>,
+}
+
+fn deserialize_optional_args<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<Vec<String>>, D::Error> {
+    deserialize_args(deserializer).map(Some)
+}
+
+impl TryFrom<RawListedTest> for ListedTest {
+    type Error = String;
+
+    fn try_from(test: RawListedTest) -> Result<Self, Self::Error> {
+        let input = match (test.input, test.file, test.args) {
+            (Some(input), None, None) => ListedInput::Inline { input },
+            (None, Some(file), None) => ListedInput::File { file },
+            (None, None, Some(args)) => ListedInput::Generated { args },
+            (None, None, None) => return Err("a listed test needs input, file or args".to_string()),
+            _ => return Err("a listed test can only have one of input, file and args".to_string()),
+        };
+        Ok(ListedTest {
+            name: test.name,
+            input,
+            output: test.output,
+        })
+    }
+}
+
+/// Tests listed one by one.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct TestList {
+    /// Generator for the tests given by arguments.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub generator: Option<ProgramOrShorthand>,
+    pub tests: Vec<ListedTest>,
+}
+
+/// Inputs written by several generators as told by a script, see
+/// [`crate::script`].
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct ScriptInput {
+    /// Generator programs by the name used in the script.
+    pub generators: BTreeMap<String, ProgramOrShorthand>,
+    pub script: String,
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 #[serde(tag = "type", rename_all = "camelCase")]
 pub enum Input {
     Files(Files),
-    Generator { program: ProgramOrShorthand },
+    Generator(GeneratorInput),
+    Tests(TestList),
+    Script(ScriptInput),
 }
 
 impl Input {
-    pub fn as_input_ref(&self) -> InputRef {
+    pub fn as_input_ref(&self) -> InputRef<'_> {
         match self {
             Input::Files(files) => InputRef::Files(files),
-            Input::Generator { program } => InputRef::Generator { program },
+            Input::Generator(generator) => InputRef::Generator(generator),
+            Input::Tests(list) => InputRef::Tests(list),
+            Input::Script(script) => InputRef::Script(script),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum TestListInput {
+    Tests(TestList),
+}
+
+impl TestListInput {
+    pub fn as_list(&self) -> &TestList {
+        match self {
+            TestListInput::Tests(list) => list,
         }
     }
 }
@@ -99,7 +332,7 @@ impl FilesInput {
         }
     }
 
-    pub fn as_input_ref(&self) -> InputRef {
+    pub fn as_input_ref(&self) -> InputRef<'_> {
         match self {
             FilesInput::Files(files) => InputRef::Files(files),
         }
@@ -108,7 +341,227 @@ impl FilesInput {
 
 pub enum InputRef<'a> {
     Files(&'a Files),
-    Generator { program: &'a ProgramOrShorthand },
+    Generator(&'a GeneratorInput),
+    Tests(&'a TestList),
+    Script(&'a ScriptInput),
+}
+
+/// How the test scores of a group are combined into the group score.
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum ScoringPolicy {
+    /// All points if every test is fully accepted, none otherwise.
+    #[default]
+    AllOrNothing,
+    /// Points times the lowest test score.
+    Min,
+    /// Points for every test, times the test score.
+    Sum,
+    /// Points times the average test score.
+    Average,
+}
+
+impl fmt::Display for ScoringPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScoringPolicy::AllOrNothing => write!(f, "all or nothing"),
+            ScoringPolicy::Min => write!(f, "min"),
+            ScoringPolicy::Sum => write!(f, "sum"),
+            ScoringPolicy::Average => write!(f, "average"),
+        }
+    }
+}
+
+/// Scoring of a group of tests, the tests in a directory of the input files.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct GroupScoring {
+    /// Points of the group, or of each of its tests with the `sum` policy.
+    pub points: f64,
+    #[serde(default)]
+    pub policy: ScoringPolicy,
+    /// Groups that have to be fully accepted for this group to score.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub depends_on: Vec<String>,
+}
+
+/// Outcome a solution is expected to get on the tests of a package.
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Expectation {
+    /// Accepted on every test.
+    #[default]
+    Accepted,
+    /// Wrong answer on some test and accepted on the others.
+    WrongAnswer,
+    /// Time limit exceeded on some test and accepted on the others.
+    TimeLimit,
+    /// Runtime error on some test and accepted on the others.
+    RuntimeError,
+    /// Not accepted on some test, in any way.
+    Rejected,
+}
+
+impl fmt::Display for Expectation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Expectation::Accepted => write!(f, "accepted"),
+            Expectation::WrongAnswer => write!(f, "wrong answer"),
+            Expectation::TimeLimit => write!(f, "time limit exceeded"),
+            Expectation::RuntimeError => write!(f, "runtime error"),
+            Expectation::Rejected => write!(f, "rejected"),
+        }
+    }
+}
+
+/// A solution of the problem, tested by `iobot test` when no solution is
+/// given on the command line.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Solution {
+    pub program: ProgramOrShorthand,
+    #[serde(default)]
+    pub expected: Expectation,
+}
+
+/// How to compile and run submissions in a language, e.g. for judging
+/// contest submissions. `{source}` in the commands is replaced by the path of
+/// the submission and `{target}` by the path of the compiled program.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Language {
+    pub name: String,
+    /// Extensions of the source files, without the dot.
+    pub extensions: Vec<String>,
+    /// Compiler command, interpreted languages have none.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub compile: Option<Command>,
+    /// Extension of the compiled program, with the dot.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub target_extension: String,
+    pub run: Command,
+}
+
+fn replace_source(command: &Command, source: &Path) -> Command {
+    let source = source.to_string_lossy();
+    let replace = |text: &String| text.replace("{source}", &source);
+    Command {
+        command: replace(&command.command),
+        args: command
+            .args
+            .as_ref()
+            .map(|args| args.iter().map(replace).collect()),
+    }
+}
+
+impl Language {
+    /// Whether `source` has one of the extensions of the language.
+    pub fn matches(&self, source: &Path) -> bool {
+        source
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| self.extensions.iter().any(|known| known == ext))
+    }
+
+    /// The program compiling and running `source` in this language.
+    pub fn program(&self, source: &Path) -> Program {
+        let run = replace_source(&self.run, source);
+        match &self.compile {
+            Some(compile) => Program::Compiled {
+                compile: replace_source(compile, source),
+                extension: self.target_extension.clone(),
+                run,
+            },
+            None => Program::Command { run },
+        }
+    }
+}
+
+/// What `iobot generate` does with generated inputs identical to an earlier
+/// input.
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum DuplicateInputs {
+    /// Keep them and print a warning.
+    #[default]
+    Warn,
+    /// Run the generator again with the attempt number, starting from 1, as
+    /// its last argument, after the test index and group, until the input is
+    /// new or the attempts run out.
+    Regenerate,
+}
+
+/// Settings shared by all config types.
+#[derive(Clone, Serialize, Deserialize, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct Options {
+    /// Number of programs run in parallel.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub jobs: Option<usize>,
+    /// Time limit of a solution run in milliseconds.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub time_limit: Option<u64>,
+    /// Memory limit of a solution run in MiB. It is not enforced by iobot,
+    /// only passed on to other judges when exporting.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub memory_limit: Option<u64>,
+    /// Scoring of the test groups by group name. Tests of groups without
+    /// scoring and tests outside of groups do not score.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub groups: BTreeMap<String, GroupScoring>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub solutions: Vec<Solution>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub duplicate_inputs: Option<DuplicateInputs>,
+    /// Names of the tests shown in the statement, relative to the input
+    /// directory and with or without the input extension.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub samples: Vec<String>,
+    /// Languages of submissions, tried before the built-in C++ and Python
+    /// shorthands.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub languages: Vec<Language>,
+}
+
+impl Options {
+    pub fn time_limit(&self) -> Option<Duration> {
+        self.time_limit.map(Duration::from_millis)
+    }
+
+    /// Number of parallel jobs, taken from `jobs` given on the command line or
+    /// the `jobs` key. Timed runs, e.g. judged against the time limit, are
+    /// sequential by default, as parallel runs distort the measured times.
+    pub fn job_count(&self, jobs: Option<usize>, timed: bool) -> usize {
+        match jobs.or(self.jobs) {
+            Some(jobs) => cmp::max(jobs, 1),
+            None if timed => 1,
+            None => get_thread_count(),
+        }
+    }
+
+    /// Name of the language of the submission at `source`, found from its
+    /// extension, with the program running it.
+    pub fn submission_language(
+        &self,
+        source: &Path,
+    ) -> Result<(String, Program), UnknownExtensionError> {
+        if let Some(language) = self
+            .languages
+            .iter()
+            .find(|language| language.matches(source))
+        {
+            return Ok((language.name.clone(), language.program(source)));
+        }
+        let program = ProgramOrShorthand::Shorthand(source.to_path_buf()).to_program()?;
+        let name = match program {
+            Program::GPP { .. } => "C++",
+            Program::Python { .. } => "Python",
+            Program::Command { .. } | Program::Compiled { .. } => {
+                unreachable!("shorthands are g++ or Python programs")
+            }
+        };
+        Ok((name.to_string(), program))
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -118,6 +571,8 @@ pub struct ModelProgram {
     pub model_program: ProgramOrShorthand,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub verifier: Option<ProgramOrShorthand>,
+    #[serde(flatten)]
+    pub options: Options,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -125,8 +580,13 @@ pub struct ModelProgram {
 pub struct OutputFiles {
     pub input: FilesInput,
     pub output_files: Files,
+    /// Program that produced the outputs, only run to check them.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model_program: Option<ProgramOrShorthand>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub verifier: Option<ProgramOrShorthand>,
+    #[serde(flatten)]
+    pub options: Options,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -134,63 +594,337 @@ pub struct OutputFiles {
 pub struct JustVerifier {
     pub input: Input,
     pub verifier: ProgramOrShorthand,
+    #[serde(flatten)]
+    pub options: Options,
+}
+
+/// Tests listed with their expected outputs, so that the whole problem fits
+/// in the config.
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ListedTests {
+    pub input: TestListInput,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub verifier: Option<ProgramOrShorthand>,
+    #[serde(flatten)]
+    pub options: Options,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
 #[serde(untagged)]
 pub enum Config {
-    ModelProgram(ModelProgram),
+    // Output files come first, as a config with output files may also name
+    // its model program.
     OutputFiles(OutputFiles),
+    ModelProgram(ModelProgram),
     JustVerifier(JustVerifier),
-}
-
-pub struct JustVerifierGenerator {
-    pub input: ProgramOrShorthand,
-    pub verifier: ProgramOrShorthand,
+    ListedTests(ListedTests),
 }
 
 pub enum GenerableConfig {
     ModelProgram(ModelProgram),
-    JustVerifier(JustVerifierGenerator),
+    /// A verifier config with generated or listed inputs.
+    JustVerifier(JustVerifier),
+    ListedTests(ListedTests),
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    IO(io::Error),
+    Parse(serde_yaml::Error),
+    Serialize(serde_yaml::Error),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::IO(error) => write!(f, "cannot read {}: {}", CONFIG_FILE_NAME, error),
+            ConfigError::Parse(error) => write!(f, "invalid {}: {}", CONFIG_FILE_NAME, error),
+            ConfigError::Serialize(error) => {
+                write!(f, "cannot serialize {}: {}", CONFIG_FILE_NAME, error)
+            }
+        }
+    }
+}
+
+impl error::Error for ConfigError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            ConfigError::IO(error) => Some(error),
+            ConfigError::Parse(error) | ConfigError::Serialize(error) => Some(error),
+        }
+    }
 }
 
 impl Config {
-    pub fn parse_bytes(file: &Vec<u8>) -> serde_yaml::Result<Config> {
-        serde_yaml::from_slice(&file)
+    pub fn parse_bytes(file: &[u8]) -> serde_yaml::Result<Config> {
+        /// The input alone, parsed again for a clearer error when no variant
+        /// of the untagged config matches.
+        #[derive(Deserialize)]
+        struct JustInput {
+            #[allow(dead_code)]
+            input: Input,
+        }
+        serde_yaml::from_slice(file).map_err(|error| {
+            match serde_yaml::from_slice::<JustInput>(file) {
+                Err(input_error) => input_error,
+                Ok(_) => error,
+            }
+        })
     }
 
-    pub fn get_input(&self) -> InputRef {
+    /// Reads and parses the `iobot.yaml` file in `dir`.
+    pub fn load(dir: &Path) -> Result<Config, ConfigError> {
+        let bytes = fs::read(dir.join(CONFIG_FILE_NAME)).map_err(ConfigError::IO)?;
+        Config::parse_bytes(&bytes).map_err(ConfigError::Parse)
+    }
+
+    /// Sets the top-level `timeLimit` of the `iobot.yaml` file in `dir` to
+    /// `time_limit` milliseconds. Only the `timeLimit` line is replaced or
+    /// appended, so comments, key order and shorthands are kept. Returns
+    /// `false` without writing if the edited file would not have that time
+    /// limit, e.g. for a config in flow style.
+    pub fn set_time_limit(dir: &Path, time_limit: u64) -> Result<bool, ConfigError> {
+        let path = dir.join(CONFIG_FILE_NAME);
+        let yaml = fs::read_to_string(&path).map_err(ConfigError::IO)?;
+        let mut found = false;
+        let mut edited: String = yaml
+            .split_inclusive('\n')
+            .map(|line| match line.strip_prefix("timeLimit:") {
+                Some(rest) if !found => {
+                    found = true;
+                    let comment = rest.find(" #").map_or("", |start| {
+                        rest[rest[..start].trim_end().len()..].trim_end()
+                    });
+                    let end = &line[line.trim_end_matches(['\r', '\n']).len()..];
+                    format!("timeLimit: {}{}{}", time_limit, comment, end)
+                }
+                _ => line.to_string(),
+            })
+            .collect();
+        if !found {
+            if !edited.is_empty() && !edited.ends_with('\n') {
+                edited.push('\n');
+            }
+            edited.push_str(&format!("timeLimit: {}\n", time_limit));
+        }
+        match Config::parse_bytes(edited.as_bytes()) {
+            Ok(config) if config.options().time_limit == Some(time_limit) => {
+                fs::write(path, edited).map_err(ConfigError::IO)?;
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    /// Writes the config to the `iobot.yaml` file in `dir` and returns the
+    /// written YAML.
+    pub fn save(&self, dir: &Path) -> Result<String, ConfigError> {
+        let yaml = serde_yaml::to_string(self).map_err(ConfigError::Serialize)?;
+        fs::write(dir.join(CONFIG_FILE_NAME), &yaml).map_err(ConfigError::IO)?;
+        Ok(yaml)
+    }
+
+    pub fn get_input(&self) -> InputRef<'_> {
         match self {
             Config::ModelProgram(config) => config.input.as_input_ref(),
             Config::OutputFiles(config) => config.input.as_input_ref(),
             Config::JustVerifier(config) => config.input.as_input_ref(),
+            Config::ListedTests(config) => InputRef::Tests(config.input.as_list()),
+        }
+    }
+
+    pub fn options(&self) -> &Options {
+        match self {
+            Config::ModelProgram(config) => &config.options,
+            Config::OutputFiles(config) => &config.options,
+            Config::JustVerifier(config) => &config.options,
+            Config::ListedTests(config) => &config.options,
+        }
+    }
+
+    /// The model program of the config. With output files, it only shows how
+    /// the outputs were produced and is not needed to judge.
+    pub fn model_program(&self) -> Option<&ProgramOrShorthand> {
+        match self {
+            Config::ModelProgram(config) => Some(&config.model_program),
+            Config::OutputFiles(config) => config.model_program.as_ref(),
+            Config::JustVerifier(_) | Config::ListedTests(_) => None,
+        }
+    }
+
+    pub fn verifier(&self) -> Option<&ProgramOrShorthand> {
+        match self {
+            Config::ModelProgram(config) => config.verifier.as_ref(),
+            Config::OutputFiles(config) => config.verifier.as_ref(),
+            Config::JustVerifier(config) => Some(&config.verifier),
+            Config::ListedTests(config) => config.verifier.as_ref(),
         }
     }
 
-    pub fn to_generable(self) -> Option<GenerableConfig> {
+    /// Returns `None` if the config has neither generated or listed inputs nor
+    /// a model program, so there is nothing to generate.
+    pub fn into_generable(self) -> Option<GenerableConfig> {
         match self {
             Config::ModelProgram(config) => Some(GenerableConfig::ModelProgram(config)),
             Config::OutputFiles(_) => None,
             Config::JustVerifier(config) => match config.input {
                 Input::Files(_) => None,
-                Input::Generator { program } => {
-                    Some(GenerableConfig::JustVerifier(JustVerifierGenerator {
-                        input: program,
-                        verifier: config.verifier,
-                    }))
+                Input::Generator(_) | Input::Tests(_) | Input::Script(_) => {
+                    Some(GenerableConfig::JustVerifier(config))
                 }
             },
+            Config::ListedTests(config) => Some(GenerableConfig::ListedTests(config)),
         }
     }
 }
 
 impl GenerableConfig {
-    pub fn get_input(&self) -> InputRef {
+    pub fn get_input(&self) -> InputRef<'_> {
         match self {
             GenerableConfig::ModelProgram(config) => config.input.as_input_ref(),
-            GenerableConfig::JustVerifier(config) => InputRef::Generator {
-                program: &config.input,
-            },
+            GenerableConfig::JustVerifier(config) => config.input.as_input_ref(),
+            GenerableConfig::ListedTests(config) => InputRef::Tests(config.input.as_list()),
+        }
+    }
+
+    pub fn options(&self) -> &Options {
+        match self {
+            GenerableConfig::ModelProgram(config) => &config.options,
+            GenerableConfig::JustVerifier(config) => &config.options,
+            GenerableConfig::ListedTests(config) => &config.options,
         }
     }
+
+    pub fn verifier(&self) -> Option<&ProgramOrShorthand> {
+        match self {
+            GenerableConfig::ModelProgram(config) => config.verifier.as_ref(),
+            GenerableConfig::JustVerifier(config) => Some(&config.verifier),
+            GenerableConfig::ListedTests(config) => config.verifier.as_ref(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_dir;
+
+    const HEAD: &str = "input:\n  type: files\n  path: in\nmodelProgram: model.cpp\n";
+
+    /// Sets the time limit of the config `yaml` to 2000 ms, returns whether it
+    /// was written and the resulting file.
+    fn set_time_limit(yaml: &str) -> (bool, String) {
+        let dir = test_dir();
+        fs::write(dir.join(CONFIG_FILE_NAME), yaml).unwrap();
+        let written = Config::set_time_limit(&dir, 2000).unwrap();
+        let yaml = fs::read_to_string(dir.join(CONFIG_FILE_NAME)).unwrap();
+        (written, yaml)
+    }
+
+    #[test]
+    fn set_time_limit_replaces_the_line() {
+        assert_eq!(
+            set_time_limit(&format!("timeLimit: 1000\n{}jobs: 2\n", HEAD)),
+            (true, format!("timeLimit: 2000\n{}jobs: 2\n", HEAD))
+        );
+    }
+
+    #[test]
+    fn set_time_limit_appends_a_missing_key() {
+        let yaml = format!("# Sums two numbers\n{}", HEAD.trim_end());
+        assert_eq!(
+            set_time_limit(&yaml),
+            (true, format!("{}\ntimeLimit: 2000\n", yaml))
+        );
+    }
+
+    #[test]
+    fn set_time_limit_keeps_the_comment() {
+        assert_eq!(
+            set_time_limit(&format!("{}timeLimit: 1000   # ms\n", HEAD)),
+            (true, format!("{}timeLimit: 2000   # ms\n", HEAD))
+        );
+    }
+
+    #[test]
+    fn set_time_limit_ignores_nested_keys() {
+        let yaml = format!(
+            "{}groups:\n  small:\n    points: 10\n    timeLimit: 500\n",
+            HEAD
+        );
+        assert_eq!(
+            set_time_limit(&yaml),
+            (true, format!("{}timeLimit: 2000\n", yaml))
+        );
+    }
+
+    #[test]
+    fn set_time_limit_refuses_flow_style() {
+        let yaml = "{input: {type: files, path: in}, modelProgram: model.cpp, timeLimit: 1000}\n";
+        assert_eq!(set_time_limit(yaml), (false, yaml.to_string()));
+    }
+
+    fn parse_error(yaml: &str) -> String {
+        Config::parse_bytes(yaml.as_bytes())
+            .unwrap_err()
+            .to_string()
+    }
+
+    #[test]
+    fn parses_listed_tests() {
+        let config = Config::parse_bytes(
+            b"input:\n  type: tests\n  tests:\n  - input: \"1 2\"\n    output: \"3\"\n  \
+              - name: big\n    file: big.in\n  - args: [10, x, 0.5]\n",
+        )
+        .unwrap();
+        let list = match config.get_input() {
+            InputRef::Tests(list) => list,
+            _ => panic!("not a test list"),
+        };
+        assert!(matches!(&list.tests[0].input, ListedInput::Inline { input } if input == "1 2"));
+        assert_eq!(list.tests[0].output.as_deref(), Some("3"));
+        assert_eq!(list.tests[1].name.as_deref(), Some("big"));
+        assert!(
+            matches!(&list.tests[1].input, ListedInput::File { file } if file == Path::new("big.in"))
+        );
+        assert!(
+            matches!(&list.tests[2].input, ListedInput::Generated { args } if args == &["10", "x", "0.5"])
+        );
+    }
+
+    #[test]
+    fn listed_test_errors_name_the_field() {
+        let error = parse_error("input:\n  type: tests\n  tests:\n  - input: 5\n");
+        assert!(
+            error.contains("invalid type: integer `5`, expected a string"),
+            "{}",
+            error
+        );
+        let error = parse_error("input:\n  type: tests\n  tests:\n  - output: \"3\"\n");
+        assert!(error.contains("needs input, file or args"), "{}", error);
+        let error = parse_error("input:\n  type: tests\n  tests:\n  - input: a\n    file: a.in\n");
+        assert!(
+            error.contains("only have one of input, file and args"),
+            "{}",
+            error
+        );
+        let error = parse_error("input:\n  type: tests\n  tests:\n  - inputs: a\n");
+        assert!(error.contains("unknown field `inputs`"), "{}", error);
+    }
+
+    #[test]
+    fn job_count_is_sequential_for_timed_runs() {
+        let options = Options {
+            jobs: Some(3),
+            ..Options::default()
+        };
+        assert_eq!(options.job_count(None, true), 3);
+        assert_eq!(options.job_count(Some(2), true), 2);
+        assert_eq!(options.job_count(Some(0), false), 1);
+        let options = Options::default();
+        assert_eq!(options.job_count(None, true), 1);
+        assert_eq!(options.job_count(None, false), get_thread_count());
+        assert_eq!(options.job_count(Some(5), true), 5);
+    }
 }
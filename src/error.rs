@@ -0,0 +1,337 @@
+use crate::config::{ConfigError, UnknownExtensionError};
+use crate::export::ExportError;
+use crate::generator::{GenerateInputsError, GenerateOutputsError};
+use crate::import::ImportError;
+use crate::judge::JudgeError;
+use crate::runner::{CompileError, ProgramRole, RunError};
+use crate::samples::SamplesError;
+use crate::score::ScoringError;
+use crate::server::ServeError;
+use std::{error, fmt, io};
+
+/// Process exit codes, one per error category.
+pub mod exit_code {
+    /// Some tests were not accepted.
+    pub const TESTS_FAILED: i32 = 1;
+    /// Invalid command line arguments.
+    pub const USAGE: i32 = 2;
+    /// Missing or invalid `iobot.yaml`.
+    pub const CONFIG: i32 = 3;
+    /// A program failed to compile.
+    pub const COMPILE: i32 = 4;
+    /// A generator, model program or verifier failed while running.
+    pub const RUN: i32 = 5;
+    /// Reading or writing files failed.
+    pub const IO: i32 = 6;
+}
+
+/// Any error that ends an iobot command.
+#[derive(Debug)]
+pub enum Error {
+    Usage(String),
+    IO(io::Error),
+    Config(ConfigError),
+    UnknownExtension(UnknownExtensionError),
+    Compile(ProgramRole, CompileError),
+    Run(RunError),
+    GenerateInputs(GenerateInputsError),
+    GenerateOutputs(GenerateOutputsError),
+    Judge(JudgeError),
+    Export(ExportError),
+    Import(ImportError),
+    Watch(notify::Error),
+    Scoring(ScoringError),
+    Samples(SamplesError),
+    Serve(ServeError),
+    TestsFailed {
+        failed: usize,
+        total: usize,
+    },
+    /// Solutions did not get the outcome declared in the config.
+    UnexpectedOutcomes {
+        unexpected: usize,
+        total: usize,
+    },
+    /// Tests whose reference output disagrees with the model program or the
+    /// verifier.
+    InconsistentTests {
+        inconsistent: usize,
+        total: usize,
+    },
+}
+
+impl Error {
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Error::Usage(_) => exit_code::USAGE,
+            Error::IO(_) => exit_code::IO,
+            Error::Config(_) | Error::UnknownExtension(_) | Error::Scoring(_) => exit_code::CONFIG,
+            Error::Compile(..) => exit_code::COMPILE,
+            Error::Run(_) => exit_code::RUN,
+            Error::GenerateInputs(error) => match error {
+                GenerateInputsError::IO(_)
+                | GenerateInputsError::StripPrefix(_)
+                | GenerateInputsError::ListedFile { .. } => exit_code::IO,
+                GenerateInputsError::Run { .. } | GenerateInputsError::Panicked => exit_code::RUN,
+                GenerateInputsError::Naming(_)
+                | GenerateInputsError::NoGenerator(_)
+                | GenerateInputsError::Script(_) => exit_code::CONFIG,
+            },
+            Error::GenerateOutputs(error) => match error {
+                GenerateOutputsError::IO(_) | GenerateOutputsError::StripPrefix(_) => exit_code::IO,
+                GenerateOutputsError::Run(_) | GenerateOutputsError::Panicked => exit_code::RUN,
+                GenerateOutputsError::Naming(_) | GenerateOutputsError::MissingOutput(_) => {
+                    exit_code::CONFIG
+                }
+            },
+            Error::Export(ExportError::IO(_)) => exit_code::IO,
+            Error::Export(ExportError::Yaml(_))
+            | Error::Export(ExportError::NotGenerated)
+            | Error::Export(ExportError::Verifier(_)) => exit_code::CONFIG,
+            Error::Export(ExportError::Judge(error)) => Error::judge_exit_code(error),
+            Error::Judge(error) => Error::judge_exit_code(error),
+            Error::Watch(_) => exit_code::IO,
+            Error::Import(error) => match error {
+                ImportError::IO(_) => exit_code::IO,
+                ImportError::UnsupportedFormat(_) => exit_code::USAGE,
+                ImportError::Xml(_)
+                | ImportError::Yaml(_)
+                | ImportError::Config(_)
+                | ImportError::Invalid(_) => exit_code::CONFIG,
+            },
+            Error::Samples(error) => match error {
+                SamplesError::IO(_) => exit_code::IO,
+                SamplesError::Judge(error) => Error::judge_exit_code(error),
+                SamplesError::UnknownSample(_) => exit_code::CONFIG,
+            },
+            Error::Serve(_) => exit_code::IO,
+            Error::TestsFailed { .. }
+            | Error::UnexpectedOutcomes { .. }
+            | Error::InconsistentTests { .. } => exit_code::TESTS_FAILED,
+        }
+    }
+
+    fn judge_exit_code(error: &JudgeError) -> i32 {
+        match error {
+            JudgeError::IO(_) | JudgeError::StripPrefix(_) => exit_code::IO,
+            JudgeError::NotGenerated
+            | JudgeError::MissingOutput(_)
+            | JudgeError::UnknownExtension(_) => exit_code::CONFIG,
+            JudgeError::Compile(..) => exit_code::COMPILE,
+            JudgeError::Model(_)
+            | JudgeError::Verifier(_)
+            | JudgeError::Solution(_)
+            | JudgeError::Panicked => exit_code::RUN,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Usage(message) => write!(f, "{}", message),
+            Error::IO(error) => write!(f, "{}", error),
+            Error::Config(error) => write!(f, "{}", error),
+            Error::UnknownExtension(error) => write!(f, "{}", error),
+            Error::Compile(role, error) => write!(f, "cannot build the {}: {}", role, error),
+            Error::Run(error) => write!(f, "{}", error),
+            Error::GenerateInputs(error) => write!(f, "{}", error),
+            Error::GenerateOutputs(error) => write!(f, "{}", error),
+            Error::Judge(error) => write!(f, "{}", error),
+            Error::Export(error) => write!(f, "{}", error),
+            Error::Import(error) => write!(f, "{}", error),
+            Error::Watch(error) => write!(f, "cannot watch files: {}", error),
+            Error::Scoring(error) => write!(f, "invalid scoring: {}", error),
+            Error::Samples(error) => write!(f, "{}", error),
+            Error::Serve(error) => write!(f, "{}", error),
+            Error::TestsFailed { failed, total } => {
+                write!(f, "{} of {} tests failed", failed, total)
+            }
+            Error::UnexpectedOutcomes { unexpected, total } => write!(
+                f,
+                "{} of {} solutions did not get their expected outcome",
+                unexpected, total
+            ),
+            Error::InconsistentTests {
+                inconsistent,
+                total,
+            } => write!(f, "{} of {} tests are inconsistent", inconsistent, total),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Error::Usage(_)
+            | Error::TestsFailed { .. }
+            | Error::UnexpectedOutcomes { .. }
+            | Error::InconsistentTests { .. } => None,
+            Error::IO(error) => Some(error),
+            Error::Config(error) => Some(error),
+            Error::UnknownExtension(error) => Some(error),
+            Error::Compile(_, error) => Some(error),
+            Error::Run(error) => Some(error),
+            Error::GenerateInputs(error) => Some(error),
+            Error::GenerateOutputs(error) => Some(error),
+            Error::Judge(error) => Some(error),
+            Error::Export(error) => Some(error),
+            Error::Import(error) => Some(error),
+            Error::Watch(error) => Some(error),
+            Error::Scoring(error) => Some(error),
+            Error::Samples(error) => Some(error),
+            Error::Serve(error) => Some(error),
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(error: io::Error) -> Self {
+        Error::IO(error)
+    }
+}
+
+impl From<ConfigError> for Error {
+    fn from(error: ConfigError) -> Self {
+        Error::Config(error)
+    }
+}
+
+impl From<UnknownExtensionError> for Error {
+    fn from(error: UnknownExtensionError) -> Self {
+        Error::UnknownExtension(error)
+    }
+}
+
+impl From<RunError> for Error {
+    fn from(error: RunError) -> Self {
+        Error::Run(error)
+    }
+}
+
+impl From<GenerateInputsError> for Error {
+    fn from(error: GenerateInputsError) -> Self {
+        Error::GenerateInputs(error)
+    }
+}
+
+impl From<GenerateOutputsError> for Error {
+    fn from(error: GenerateOutputsError) -> Self {
+        Error::GenerateOutputs(error)
+    }
+}
+
+impl From<JudgeError> for Error {
+    fn from(error: JudgeError) -> Self {
+        Error::Judge(error)
+    }
+}
+
+impl From<ExportError> for Error {
+    fn from(error: ExportError) -> Self {
+        Error::Export(error)
+    }
+}
+
+impl From<ImportError> for Error {
+    fn from(error: ImportError) -> Self {
+        Error::Import(error)
+    }
+}
+
+impl From<notify::Error> for Error {
+    fn from(error: notify::Error) -> Self {
+        Error::Watch(error)
+    }
+}
+
+impl From<ScoringError> for Error {
+    fn from(error: ScoringError) -> Self {
+        Error::Scoring(error)
+    }
+}
+
+impl From<SamplesError> for Error {
+    fn from(error: SamplesError) -> Self {
+        Error::Samples(error)
+    }
+}
+
+impl From<ServeError> for Error {
+    fn from(error: ServeError) -> Self {
+        Error::Serve(error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exit_codes_follow_the_category() {
+        let cases = [
+            (Error::Usage("No tests".to_string()), exit_code::USAGE),
+            (
+                Error::Compile(ProgramRole::ModelProgram, CompileError::Extension),
+                exit_code::COMPILE,
+            ),
+            (Error::Run(RunError::TimeLimitExceeded), exit_code::RUN),
+            (
+                Error::GenerateInputs(GenerateInputsError::Panicked),
+                exit_code::RUN,
+            ),
+            (
+                Error::GenerateInputs(GenerateInputsError::NoGenerator("1".into())),
+                exit_code::CONFIG,
+            ),
+            (
+                Error::GenerateInputs(GenerateInputsError::IO(io::ErrorKind::NotFound.into())),
+                exit_code::IO,
+            ),
+            (Error::Judge(JudgeError::NotGenerated), exit_code::CONFIG),
+            (
+                Error::Judge(JudgeError::Solution(RunError::Unsuccessful(Some(1)))),
+                exit_code::RUN,
+            ),
+            (
+                Error::Judge(JudgeError::Compile(
+                    ProgramRole::Verifier,
+                    CompileError::Extension,
+                )),
+                exit_code::COMPILE,
+            ),
+            (
+                Error::Samples(SamplesError::UnknownSample("1".to_string())),
+                exit_code::CONFIG,
+            ),
+            (
+                Error::TestsFailed {
+                    failed: 1,
+                    total: 2,
+                },
+                exit_code::TESTS_FAILED,
+            ),
+        ];
+        for (error, code) in cases {
+            assert_eq!(error.exit_code(), code, "{}", error);
+        }
+    }
+
+    #[test]
+    fn messages_name_the_failure() {
+        assert_eq!(
+            Error::Compile(ProgramRole::Verifier, CompileError::Extension).to_string(),
+            "cannot build the verifier: target extension must start with a dot"
+        );
+        assert_eq!(
+            Error::TestsFailed {
+                failed: 3,
+                total: 10
+            }
+            .to_string(),
+            "3 of 10 tests failed"
+        );
+        let error = Error::Run(RunError::IO(io::ErrorKind::NotFound.into()));
+        assert!(error::Error::source(&error).is_some());
+    }
+}
@@ -0,0 +1,115 @@
+use super::{ExportError, Package, Target};
+use crate::config::ScoringPolicy;
+use crate::judge::TestCase;
+use crate::score::format_points;
+use serde::Serialize;
+
+/// `task.yaml` of the Italian task format imported by CMS.
+#[derive(Serialize)]
+struct Task<'a> {
+    name: &'a str,
+    title: &'a str,
+    /// In seconds.
+    time_limit: f64,
+    /// In MiB.
+    memory_limit: u64,
+    n_input: usize,
+    infile: &'a str,
+    outfile: &'a str,
+    public_testcases: &'a str,
+}
+
+/// Total score split between the subtasks of packages without scoring.
+const TOTAL_POINTS: u64 = 100;
+
+/// Subtasks of `group` with their points from the scoring config, zero for
+/// groups without scoring. CMS gives the points of a subtask only if all of
+/// its tests are accepted, so the tests of groups scored per test become
+/// subtasks of their own, which gives the same score as tests are either
+/// accepted or not.
+fn subtasks<'a>(
+    package: &Package,
+    group: Option<&str>,
+    tests: &[&'a TestCase],
+) -> Vec<(f64, Vec<&'a TestCase>)> {
+    let scoring = match group.and_then(|group| package.scoring.get(group)) {
+        Some(scoring) => scoring,
+        None => return vec![(0.0, tests.to_vec())],
+    };
+    let test_points = match scoring.policy {
+        ScoringPolicy::AllOrNothing | ScoringPolicy::Min => {
+            return vec![(scoring.points, tests.to_vec())]
+        }
+        ScoringPolicy::Sum => scoring.points,
+        ScoringPolicy::Average => scoring.points / tests.len() as f64,
+    };
+    tests
+        .iter()
+        .map(|test| (test_points, vec![*test]))
+        .collect()
+}
+
+/// Writes tests as `input/input0.txt` and `output/output0.txt`, the limits to
+/// `task.yaml` and the groups as subtasks of `gen/GEN`. Returns warnings for
+/// the scoring that CMS cannot represent.
+pub(super) fn export(package: &Package, target: &mut Target) -> Result<Vec<String>, ExportError> {
+    let groups = package.groups();
+    let mut warnings = vec![];
+    let mut gen = String::new();
+    let mut index = 0;
+    for (group_index, (group, tests)) in groups.iter().enumerate() {
+        let subtasks = if package.scoring.is_empty() {
+            // Points are split evenly, the last subtask gets the remainder.
+            let points = TOTAL_POINTS / groups.len() as u64
+                + if group_index + 1 == groups.len() {
+                    TOTAL_POINTS % groups.len() as u64
+                } else {
+                    0
+                };
+            vec![(points as f64, tests.clone())]
+        } else {
+            subtasks(package, group.as_deref(), tests)
+        };
+        if let Some(scoring) = group.as_ref().and_then(|group| package.scoring.get(group)) {
+            if !scoring.depends_on.is_empty() {
+                warnings.push(format!(
+                    "CMS subtasks cannot depend on each other, group {} scores without its dependencies",
+                    group.as_deref().unwrap_or_default()
+                ));
+            }
+        }
+        for (points, tests) in subtasks {
+            if package.has_groups() {
+                gen.push_str(&format!("#ST: {}\n", format_points(points)));
+                if let Some(group) = group {
+                    gen.push_str(&format!("# {}\n", group));
+                }
+            }
+            for test in tests {
+                target.copy(&format!("input/input{}.txt", index), &test.input)?;
+                target.write(
+                    &format!("output/output{}.txt", index),
+                    &package.answer(test)?,
+                )?;
+                gen.push_str(&format!("{}\n", test.name.display()));
+                index += 1;
+            }
+        }
+    }
+    if package.has_groups() {
+        target.write("gen/GEN", gen.as_bytes())?;
+    }
+    let task = Task {
+        name: &package.name,
+        title: &package.name,
+        time_limit: package.time_limit.as_secs_f64(),
+        memory_limit: package.memory_limit,
+        n_input: package.tests.len(),
+        infile: "",
+        outfile: "",
+        public_testcases: "",
+    };
+    let yaml = serde_yaml::to_string(&task)?;
+    target.write("task.yaml", yaml.as_bytes())?;
+    Ok(warnings)
+}
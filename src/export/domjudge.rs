@@ -0,0 +1,20 @@
+use super::kattis::{problem_yaml, scoring_warnings};
+use super::{flat_name, ExportError, Package, Target};
+
+/// Writes tests as `data/secret/<name>.in` and `.ans`, the memory limit to
+/// `problem.yaml` and the time limit to `domjudge-problem.ini`. The package is
+/// otherwise in the Kattis format, but DOMjudge keeps all tests in one
+/// directory, so groups become prefixes of the test names.
+pub(super) fn export(package: &Package, target: &mut Target) -> Result<Vec<String>, ExportError> {
+    for test in &package.tests {
+        let name = format!("data/secret/{}", flat_name(test));
+        target.copy(&format!("{}.in", name), &test.input)?;
+        target.write(&format!("{}.ans", name), &package.answer(test)?)?;
+    }
+    target.write("problem.yaml", problem_yaml(package)?.as_bytes())?;
+    target.write(
+        "domjudge-problem.ini",
+        format!("timelimit = '{}'\n", package.time_limit.as_secs_f64()).as_bytes(),
+    )?;
+    Ok(scoring_warnings(package))
+}
@@ -0,0 +1,51 @@
+use super::{ExportError, Package, Target};
+use serde::Serialize;
+
+#[derive(Serialize)]
+struct Limits {
+    /// In MiB.
+    memory: u64,
+}
+
+#[derive(Serialize)]
+struct Problem<'a> {
+    name: &'a str,
+    limits: Limits,
+}
+
+/// Contents of `problem.yaml`.
+pub(super) fn problem_yaml(package: &Package) -> Result<String, ExportError> {
+    let problem = Problem {
+        name: &package.name,
+        limits: Limits {
+            memory: package.memory_limit,
+        },
+    };
+    Ok(serde_yaml::to_string(&problem)?)
+}
+
+/// Warns that the scoring of the groups is not exported, as the packages are
+/// judged pass or fail.
+pub(super) fn scoring_warnings(package: &Package) -> Vec<String> {
+    if package.scoring.is_empty() {
+        return vec![];
+    }
+    vec!["the scoring of the groups is not exported, the problem is pass or fail".to_string()]
+}
+
+/// Writes tests as `data/secret/<group>/<name>.in` and `.ans`, the memory
+/// limit to `problem.yaml` and the time limit to `.timelimit`.
+pub(super) fn export(package: &Package, target: &mut Target) -> Result<Vec<String>, ExportError> {
+    for test in &package.tests {
+        let name = &test.stem;
+        let name = format!("data/secret/{}", name.to_string_lossy().replace('\\', "/"));
+        target.copy(&format!("{}.in", name), &test.input)?;
+        target.write(&format!("{}.ans", name), &package.answer(test)?)?;
+    }
+    target.write("problem.yaml", problem_yaml(package)?.as_bytes())?;
+    target.write(
+        ".timelimit",
+        format!("{}\n", package.time_limit.as_secs_f64()).as_bytes(),
+    )?;
+    Ok(scoring_warnings(package))
+}
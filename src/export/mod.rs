@@ -0,0 +1,471 @@
+use crate::archive::Target;
+use crate::config::{Config, GroupScoring};
+use crate::judge::{list_tests, JudgeError, TestCase};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
+use std::{error, fmt, fs, io};
+
+mod cms;
+mod domjudge;
+mod kattis;
+mod polygon;
+
+/// Used when the config has no `timeLimit`, as every judge requires one.
+pub const DEFAULT_TIME_LIMIT: Duration = Duration::from_secs(1);
+/// Used when the config has no `memoryLimit`, in MiB.
+pub const DEFAULT_MEMORY_LIMIT: u64 = 256;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    Polygon,
+    Cms,
+    DomJudge,
+    Kattis,
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Format::Polygon => write!(f, "polygon"),
+            Format::Cms => write!(f, "cms"),
+            Format::DomJudge => write!(f, "domjudge"),
+            Format::Kattis => write!(f, "kattis"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct UnknownFormatError(pub String);
+
+impl fmt::Display for UnknownFormatError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "unknown format {}, expected polygon, cms, domjudge or kattis",
+            self.0
+        )
+    }
+}
+
+impl error::Error for UnknownFormatError {}
+
+impl FromStr for Format {
+    type Err = UnknownFormatError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "polygon" => Ok(Format::Polygon),
+            "cms" => Ok(Format::Cms),
+            "domjudge" => Ok(Format::DomJudge),
+            "kattis" => Ok(Format::Kattis),
+            _ => Err(UnknownFormatError(s.to_string())),
+        }
+    }
+}
+
+impl Format {
+    /// Why an iobot verifier cannot be used as the checker of `self`, `None`
+    /// if the checker protocol of the judge is compatible: Polygon calls the
+    /// checker with the input, output and answer paths like iobot does and
+    /// treats a non-zero exit code as a rejection.
+    fn checker_incompatibility(&self) -> Option<&'static str> {
+        match self {
+            Format::Polygon => None,
+            Format::Cms => Some(
+                "CMS calls checkers with the input, answer and output paths in that order and \
+                 reads the score from their standard output",
+            ),
+            Format::DomJudge | Format::Kattis => Some(
+                "output validators read the output from standard input, get the answer path \
+                 and a feedback directory as arguments and accept with exit code 42",
+            ),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ExportError {
+    IO(io::Error),
+    Yaml(serde_yaml::Error),
+    Judge(JudgeError),
+    /// The package has a model program instead of output files.
+    NotGenerated,
+    /// The verifier of the package cannot be exported, for the given reason.
+    Verifier(String),
+}
+
+impl fmt::Display for ExportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExportError::IO(error) => write!(f, "cannot export the package: {}", error),
+            ExportError::Yaml(error) => write!(f, "cannot serialize the package: {}", error),
+            ExportError::Judge(error) => write!(f, "{}", error),
+            ExportError::NotGenerated => {
+                write!(
+                    f,
+                    "package outputs are not generated, run `iobot generate` first"
+                )
+            }
+            ExportError::Verifier(reason) => write!(f, "cannot export the verifier: {}", reason),
+        }
+    }
+}
+
+impl error::Error for ExportError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            ExportError::IO(error) => Some(error),
+            ExportError::Yaml(error) => Some(error),
+            ExportError::Judge(error) => Some(error),
+            ExportError::NotGenerated | ExportError::Verifier(_) => None,
+        }
+    }
+}
+
+impl From<io::Error> for ExportError {
+    fn from(error: io::Error) -> Self {
+        ExportError::IO(error)
+    }
+}
+
+impl From<serde_yaml::Error> for ExportError {
+    fn from(error: serde_yaml::Error) -> Self {
+        ExportError::Yaml(error)
+    }
+}
+
+impl From<JudgeError> for ExportError {
+    fn from(error: JudgeError) -> Self {
+        ExportError::Judge(error)
+    }
+}
+
+/// The tests and metadata of a generated package.
+pub struct Package {
+    pub name: String,
+    pub tests: Vec<TestCase>,
+    /// Source file of the verifier.
+    pub checker: Option<PathBuf>,
+    pub time_limit: Duration,
+    /// Memory limit in MiB.
+    pub memory_limit: u64,
+    /// Scoring of the groups, see [`crate::config::Options::groups`].
+    pub scoring: BTreeMap<String, GroupScoring>,
+}
+
+impl Package {
+    pub fn load(config: &Config, dir: &Path) -> Result<Package, ExportError> {
+        if let Config::ModelProgram(_) = config {
+            return Err(ExportError::NotGenerated);
+        }
+        let name = dir
+            .canonicalize()?
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|| "problem".to_string());
+        let checker = match config.verifier() {
+            Some(verifier) => match verifier.local_source_path().map_err(io::Error::from)? {
+                Some(path) => Some(dir.join(path)),
+                None => {
+                    return Err(ExportError::Verifier(
+                        "only verifiers with a source file can be exported".to_string(),
+                    ))
+                }
+            },
+            None => None,
+        };
+        Ok(Package {
+            name,
+            tests: list_tests(config, dir)?,
+            checker,
+            time_limit: config.options().time_limit().unwrap_or(DEFAULT_TIME_LIMIT),
+            memory_limit: config
+                .options()
+                .memory_limit
+                .unwrap_or(DEFAULT_MEMORY_LIMIT),
+            scoring: config.options().groups.clone(),
+        })
+    }
+
+    /// Expected output of `test`, empty for packages with just a verifier.
+    fn answer(&self, test: &TestCase) -> io::Result<Vec<u8>> {
+        match &test.output {
+            Some(output) => fs::read(output),
+            None => Ok(vec![]),
+        }
+    }
+
+    /// Tests split into groups, in the order of the first test of each group.
+    fn groups(&self) -> Vec<(Option<String>, Vec<&TestCase>)> {
+        let mut groups: Vec<(Option<String>, Vec<&TestCase>)> = vec![];
+        for test in &self.tests {
+            let group = test.group();
+            match groups.iter_mut().find(|(name, _)| *name == group) {
+                Some((_, tests)) => tests.push(test),
+                None => groups.push((group, vec![test])),
+            }
+        }
+        groups
+    }
+
+    fn has_groups(&self) -> bool {
+        self.tests.iter().any(|test| test.group().is_some())
+    }
+
+    /// File name of the checker in the exported package, keeping the extension
+    /// of its source.
+    fn checker_name(&self, stem: &str) -> Option<String> {
+        let checker = self.checker.as_ref()?;
+        Some(match checker.extension() {
+            Some(ext) => format!("{}.{}", stem, ext.to_string_lossy()),
+            None => stem.to_string(),
+        })
+    }
+}
+
+/// Converts `package` to `format`, written to an archive if `target` has the
+/// `.zip`, `.tar.gz` or `.tgz` extension and to a directory otherwise. Packages
+/// with a verifier can only be exported to judges whose checkers are called
+/// like iobot verifiers. Returns warnings for the parts of the package that
+/// `format` cannot represent.
+pub fn export(
+    package: &Package,
+    format: Format,
+    target: &Path,
+) -> Result<Vec<String>, ExportError> {
+    if let (Some(_), Some(reason)) = (&package.checker, format.checker_incompatibility()) {
+        return Err(ExportError::Verifier(format!(
+            "{}, so the verifier does not work as a {} checker",
+            reason, format
+        )));
+    }
+    let mut target = Target::create(target)?;
+    let warnings = match format {
+        Format::Polygon => {
+            polygon::export(package, &mut target)?;
+            vec![]
+        }
+        Format::Cms => cms::export(package, &mut target)?,
+        Format::DomJudge => domjudge::export(package, &mut target)?,
+        Format::Kattis => kattis::export(package, &mut target)?,
+    };
+    target.finish()?;
+    Ok(warnings)
+}
+
+/// Name of `test` without the input extension and with directories joined by
+/// `-`, for judges that keep all tests in one directory.
+fn flat_name(test: &TestCase) -> String {
+    test.stem
+        .components()
+        .map(|component| component.as_os_str().to_string_lossy().into_owned())
+        .collect::<Vec<String>>()
+        .join("-")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ScoringPolicy;
+    use crate::judge::write_tests;
+    use crate::utils::{relative_files, test_dir};
+
+    /// A package with the groups `small`, scored per test, and `large`, which
+    /// depends on it.
+    fn package() -> Package {
+        let tests = write_tests(
+            &test_dir(),
+            &[
+                ("small/1", "1 2\n", "3\n"),
+                ("small/2", "2 2\n", "4\n"),
+                ("large/1", "10 20\n", "30\n"),
+            ],
+        );
+        let scoring = BTreeMap::from([
+            (
+                "small".to_string(),
+                GroupScoring {
+                    points: 10.0,
+                    policy: ScoringPolicy::Sum,
+                    depends_on: vec![],
+                },
+            ),
+            (
+                "large".to_string(),
+                GroupScoring {
+                    points: 30.0,
+                    policy: ScoringPolicy::AllOrNothing,
+                    depends_on: vec!["small".to_string()],
+                },
+            ),
+        ]);
+        Package {
+            name: "sum".to_string(),
+            tests,
+            checker: None,
+            time_limit: Duration::from_millis(1500),
+            memory_limit: 128,
+            scoring,
+        }
+    }
+
+    /// Exports `package` to a new directory, returns it with the warnings.
+    fn export_to_dir(package: &Package, format: Format) -> (PathBuf, Vec<String>) {
+        let target = test_dir().join("export");
+        let warnings = export(package, format, &target).unwrap();
+        (target, warnings)
+    }
+
+    fn read(dir: &Path, name: &str) -> String {
+        fs::read_to_string(dir.join(name)).unwrap()
+    }
+
+    #[test]
+    fn refuses_verifiers_for_other_judges() {
+        let mut package = package();
+        package.checker = Some(PathBuf::from("verifier.cpp"));
+        for format in [Format::Cms, Format::DomJudge, Format::Kattis] {
+            let target = test_dir().join("export");
+            assert!(matches!(
+                export(&package, format, &target),
+                Err(ExportError::Verifier(_))
+            ));
+            assert!(!target.exists());
+        }
+    }
+
+    #[test]
+    fn exports_polygon() {
+        let (dir, warnings) = export_to_dir(&package(), Format::Polygon);
+        assert!(warnings.is_empty());
+        assert_eq!(
+            relative_files(&dir),
+            [
+                "problem.xml",
+                "tests/01",
+                "tests/01.a",
+                "tests/02",
+                "tests/02.a",
+                "tests/03",
+                "tests/03.a"
+            ]
+        );
+        assert_eq!(read(&dir, "tests/03"), "10 20\n");
+        assert_eq!(read(&dir, "tests/03.a"), "30\n");
+        assert_eq!(
+            read(&dir, "problem.xml"),
+            r#"<?xml version="1.0" encoding="utf-8" standalone="no"?>
+<problem short-name="sum">
+  <judging input-file="" output-file="">
+    <testset name="tests">
+      <time-limit>1500</time-limit>
+      <memory-limit>134217728</memory-limit>
+      <test-count>3</test-count>
+      <input-path-pattern>tests/%02d</input-path-pattern>
+      <answer-path-pattern>tests/%02d.a</answer-path-pattern>
+      <tests>
+        <test method="manual" group="small" points="10"/>
+        <test method="manual" group="small" points="10"/>
+        <test method="manual" group="large"/>
+      </tests>
+      <groups>
+        <group name="small" points="20" points-policy="each-test"/>
+        <group name="large" points="30" points-policy="complete-group">
+          <dependencies>
+            <dependency group="small"/>
+          </dependencies>
+        </group>
+      </groups>
+    </testset>
+  </judging>
+  <assets>
+    <checker name="std::wcmp.cpp" type="testlib"/>
+  </assets>
+</problem>
+"#
+        );
+    }
+
+    #[test]
+    fn exports_cms() {
+        let (dir, warnings) = export_to_dir(&package(), Format::Cms);
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(
+            relative_files(&dir),
+            [
+                "gen/GEN",
+                "input/input0.txt",
+                "input/input1.txt",
+                "input/input2.txt",
+                "output/output0.txt",
+                "output/output1.txt",
+                "output/output2.txt",
+                "task.yaml"
+            ]
+        );
+        assert_eq!(read(&dir, "input/input2.txt"), "10 20\n");
+        assert_eq!(read(&dir, "output/output2.txt"), "30\n");
+        assert_eq!(
+            read(&dir, "task.yaml"),
+            "---\nname: sum\ntitle: sum\ntime_limit: 1.5\nmemory_limit: 128\nn_input: 3\n\
+             infile: \"\"\noutfile: \"\"\npublic_testcases: \"\"\n"
+        );
+        // The tests of `small` are scored separately, as subtasks of their own.
+        assert_eq!(
+            read(&dir, "gen/GEN"),
+            "#ST: 10\n# small\nsmall/1.in\n#ST: 10\n# small\nsmall/2.in\n#ST: 30\n# large\nlarge/1.in\n"
+        );
+    }
+
+    #[test]
+    fn exports_kattis() {
+        let (dir, warnings) = export_to_dir(&package(), Format::Kattis);
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(
+            relative_files(&dir),
+            [
+                ".timelimit",
+                "data/secret/large/1.ans",
+                "data/secret/large/1.in",
+                "data/secret/small/1.ans",
+                "data/secret/small/1.in",
+                "data/secret/small/2.ans",
+                "data/secret/small/2.in",
+                "problem.yaml"
+            ]
+        );
+        assert_eq!(read(&dir, "data/secret/large/1.ans"), "30\n");
+        assert_eq!(
+            read(&dir, "problem.yaml"),
+            "---\nname: sum\nlimits:\n  memory: 128\n"
+        );
+        assert_eq!(read(&dir, ".timelimit"), "1.5\n");
+    }
+
+    #[test]
+    fn exports_domjudge() {
+        let (dir, warnings) = export_to_dir(&package(), Format::DomJudge);
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(
+            relative_files(&dir),
+            [
+                "data/secret/large-1.ans",
+                "data/secret/large-1.in",
+                "data/secret/small-1.ans",
+                "data/secret/small-1.in",
+                "data/secret/small-2.ans",
+                "data/secret/small-2.in",
+                "domjudge-problem.ini",
+                "problem.yaml"
+            ]
+        );
+        assert_eq!(read(&dir, "data/secret/small-2.in"), "2 2\n");
+        assert_eq!(
+            read(&dir, "problem.yaml"),
+            "---\nname: sum\nlimits:\n  memory: 128\n"
+        );
+        assert_eq!(read(&dir, "domjudge-problem.ini"), "timelimit = '1.5'\n");
+    }
+}
@@ -0,0 +1,154 @@
+use super::{ExportError, Package, Target};
+use crate::config::{GroupScoring, ScoringPolicy};
+use crate::score::format_points;
+use std::cmp;
+use std::fmt::Write;
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn source_type(checker_name: &str) -> Option<&'static str> {
+    match checker_name.rsplit('.').next()? {
+        "cpp" => Some("cpp.g++17"),
+        "py" => Some("python.3"),
+        _ => None,
+    }
+}
+
+/// Points of every test of a group with `scoring` and `test_count` tests,
+/// `None` for groups scored as a whole. Polygon sums the points of the tests,
+/// which gives the `average` policy for tests that are either accepted or not.
+fn test_points(scoring: &GroupScoring, test_count: usize) -> Option<f64> {
+    match scoring.policy {
+        ScoringPolicy::Sum => Some(scoring.points),
+        ScoringPolicy::Average => Some(scoring.points / test_count as f64),
+        // The lowest score of tests that are either accepted or not is all or
+        // nothing.
+        ScoringPolicy::AllOrNothing | ScoringPolicy::Min => None,
+    }
+}
+
+/// `<group>` element of `group` with its points and dependencies from the
+/// scoring config.
+fn group_xml(package: &Package, group: &str, test_count: usize) -> String {
+    let scoring = match package.scoring.get(group) {
+        Some(scoring) => scoring,
+        None => return format!("        <group name=\"{}\"/>\n", escape(group)),
+    };
+    let (points, policy) = match test_points(scoring, test_count) {
+        Some(points) => (points * test_count as f64, "each-test"),
+        None => (scoring.points, "complete-group"),
+    };
+    let mut xml = format!(
+        "        <group name=\"{}\" points=\"{}\" points-policy=\"{}\"",
+        escape(group),
+        format_points(points),
+        policy
+    );
+    if scoring.depends_on.is_empty() {
+        xml.push_str("/>\n");
+        return xml;
+    }
+    xml.push_str(">\n          <dependencies>\n");
+    for dependency in &scoring.depends_on {
+        writeln!(
+            xml,
+            "            <dependency group=\"{}\"/>",
+            escape(dependency)
+        )
+        .expect("writing to a string cannot fail");
+    }
+    xml.push_str("          </dependencies>\n        </group>\n");
+    xml
+}
+
+/// Writes tests as `tests/01` and `tests/01.a`, the checker to `files/` and
+/// the limits and groups to `problem.xml`, with the points and dependencies of
+/// the groups that have scoring.
+pub(super) fn export(package: &Package, target: &mut Target) -> Result<(), ExportError> {
+    let width = cmp::max(2, package.tests.len().to_string().len());
+    let groups = package.groups();
+    let mut tests_xml = String::new();
+    for (index, test) in package.tests.iter().enumerate() {
+        let name = format!("tests/{:0width$}", index + 1, width = width);
+        target.copy(&name, &test.input)?;
+        target.write(&(name + ".a"), &package.answer(test)?)?;
+        let group = test.group();
+        let points = group.as_ref().and_then(|group| {
+            let scoring = package.scoring.get(group)?;
+            let (_, tests) = groups
+                .iter()
+                .find(|(name, _)| name.as_ref() == Some(group))?;
+            test_points(scoring, tests.len())
+        });
+        let mut attributes = String::new();
+        if let Some(group) = &group {
+            write!(attributes, " group=\"{}\"", escape(group))
+                .expect("writing to a string cannot fail");
+        }
+        if let Some(points) = points {
+            write!(attributes, " points=\"{}\"", format_points(points))
+                .expect("writing to a string cannot fail");
+        }
+        writeln!(tests_xml, "        <test method=\"manual\"{}/>", attributes)
+            .expect("writing to a string cannot fail");
+    }
+    let mut groups_xml = String::new();
+    if package.has_groups() {
+        groups_xml.push_str("      <groups>\n");
+        for (group, tests) in &groups {
+            if let Some(group) = group {
+                groups_xml.push_str(&group_xml(package, group, tests.len()));
+            }
+        }
+        groups_xml.push_str("      </groups>\n");
+    }
+    let checker_xml = match (&package.checker, package.checker_name("check")) {
+        (Some(checker), Some(checker_name)) => {
+            let path = format!("files/{}", checker_name);
+            target.copy(&path, checker)?;
+            let source_type = source_type(&checker_name)
+                .map(|source_type| format!(" type=\"{}\"", source_type))
+                .unwrap_or_default();
+            format!(
+                "    <checker type=\"testlib\">\n      <source path=\"{}\"{}/>\n    </checker>\n",
+                escape(&path),
+                source_type
+            )
+        }
+        _ => "    <checker name=\"std::wcmp.cpp\" type=\"testlib\"/>\n".to_string(),
+    };
+    let xml = format!(
+        r#"<?xml version="1.0" encoding="utf-8" standalone="no"?>
+<problem short-name="{name}">
+  <judging input-file="" output-file="">
+    <testset name="tests">
+      <time-limit>{time_limit}</time-limit>
+      <memory-limit>{memory_limit}</memory-limit>
+      <test-count>{test_count}</test-count>
+      <input-path-pattern>tests/%0{width}d</input-path-pattern>
+      <answer-path-pattern>tests/%0{width}d.a</answer-path-pattern>
+      <tests>
+{tests}      </tests>
+{groups}    </testset>
+  </judging>
+  <assets>
+{checker}  </assets>
+</problem>
+"#,
+        name = escape(&package.name),
+        time_limit = package.time_limit.as_millis(),
+        memory_limit = package.memory_limit * 1024 * 1024,
+        test_count = package.tests.len(),
+        width = width,
+        tests = tests_xml,
+        groups = groups_xml,
+        checker = checker_xml,
+    );
+    target.write("problem.xml", xml.as_bytes())?;
+    Ok(())
+}
@@ -1,49 +1,186 @@
 use crate::config;
-use crate::config::{Files, UnknownExtensionError};
+use crate::config::{
+    DuplicateInputs, Files, GeneratorInput, ListedInput, ProgramOrShorthand, ScriptInput, TestList,
+};
+use crate::naming::{listed_test_names, plan_tests, GeneratedTest, NamingError};
+use crate::remote;
+use crate::remote::sha256_hex;
 use crate::runner::{CompileError, RunError, RunResult, Runner};
-use crate::utils::{get_thread_count, list_config_files, FilesType};
+use crate::script::{parse_script, ScriptError};
+use crate::utils::{
+    config_extensions, list_config_files, matching_extension, run_parallel, strip_extension,
+    with_suffix, FilesType, PoolError,
+};
 use indicatif::ProgressBar;
-use std::path::{Path, PathBuf, StripPrefixError};
-use std::sync::{Arc, Mutex};
-use std::{fs, io};
-use threadpool::ThreadPool;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::path::{Component, Path, PathBuf, StripPrefixError};
+use std::sync::Arc;
+use std::{error, fmt, fs, io};
 
+/// A generator program, called with the test index as its first argument and
+/// the name of the test group as the second one if the tests are grouped.
 pub struct Generator(Runner);
 
 impl Generator {
+    pub fn new(runner: Runner) -> Generator {
+        Generator(runner)
+    }
+
     pub fn build(program: &config::Program, config_dir: &Path) -> Result<Generator, CompileError> {
         Ok(Generator(Runner::build(program, config_dir)?))
     }
 
-    pub fn run(&self, index: u64) -> Result<RunResult, RunError> {
-        self.0.run_without_input(vec![index.to_string()])
+    pub fn run(&self, test: &GeneratedTest) -> Result<RunResult, RunError> {
+        self.0.run_without_input(Generator::args(test))
+    }
+
+    /// Runs the generator again for a test whose input was a duplicate, with
+    /// the attempt number, starting from 1, as the last argument.
+    pub fn rerun(&self, test: &GeneratedTest, attempt: u32) -> Result<RunResult, RunError> {
+        let mut args = Generator::args(test);
+        args.push(attempt.to_string());
+        self.0.run_without_input(args)
+    }
+
+    /// Runs the generator with `args` given in the config or a script.
+    pub fn run_with_args(&self, args: Vec<String>) -> Result<RunResult, RunError> {
+        self.0.run_without_input(args)
+    }
+
+    fn args(test: &GeneratedTest) -> Vec<String> {
+        let mut args = vec![test.index.to_string()];
+        args.extend(test.group.clone());
+        args
     }
 }
 
-fn copy_inputs(
+/// A generator run that wrote an input, recorded so that it can be repeated.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct GeneratorRun {
+    /// Name of the test, without the input extension.
+    pub test: PathBuf,
+    /// Name of the generator in a script.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub generator: Option<String>,
+    pub args: Vec<String>,
+}
+
+/// Inputs written by a generator.
+#[derive(Debug)]
+pub struct GeneratedInputs {
+    pub files: config::Files,
+    /// Number of duplicate inputs that were generated again.
+    pub regenerated: usize,
+    /// The generator runs that wrote the inputs, in test order.
+    pub runs: Vec<GeneratorRun>,
+}
+
+impl GeneratedInputs {
+    fn new(files: config::Files, runs: Vec<GeneratorRun>) -> GeneratedInputs {
+        GeneratedInputs {
+            files,
+            regenerated: 0,
+            runs,
+        }
+    }
+}
+
+/// Generator runs tried for a duplicate input before keeping it.
+const REGENERATE_ATTEMPTS: u32 = 10;
+
+/// Copies the input files listed in `files` from `source` to `generated`,
+/// keeping their relative paths. Files read from an archive are copied to a
+/// directory named after it, which the returned config points to.
+pub fn copy_inputs(
     files: &config::Files,
     source: &Path,
     generated: &Path,
-) -> io::Result<config::Files> {
+) -> Result<config::Files, GenerateInputsError> {
+    let dir = files.dir(source)?;
+    let unpacked = files.unpacked_path();
     let file_paths: Vec<PathBuf> = list_config_files(files, source, FilesType::Input)?;
     let bar = ProgressBar::new(file_paths.len() as u64);
     bar.tick();
     for path in file_paths {
-        let target_path = generated.join(path.strip_prefix(source).unwrap()); // TODO: Handle error
-        fs::create_dir_all(&target_path.parent().unwrap())?;
+        let target_path = generated.join(&unpacked).join(path.strip_prefix(&dir)?);
+        if let Some(parent) = target_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
         fs::copy(path, &target_path)?;
         bar.inc(1);
     }
     bar.finish();
-    Ok(files.clone())
+    Ok(config::Files {
+        path: unpacked,
+        extensions: files.extensions.clone(),
+        checksum: None,
+    })
 }
 
 #[derive(Debug)]
 pub enum GenerateInputsError {
     IO(io::Error),
-    Run { index: u64, error: RunError },
-    GeneratorCompile(CompileError),
-    GeneratorUnknownExtension(UnknownExtensionError),
+    Run {
+        name: PathBuf,
+        error: RunError,
+    },
+    StripPrefix(StripPrefixError),
+    Naming(NamingError),
+    /// A listed test is generated by arguments, but the list has no generator.
+    NoGenerator(PathBuf),
+    /// The input file of a listed test cannot be copied.
+    ListedFile {
+        name: PathBuf,
+        file: PathBuf,
+        error: io::Error,
+    },
+    Script(ScriptError),
+    /// A worker thread panicked.
+    Panicked,
+}
+
+impl fmt::Display for GenerateInputsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GenerateInputsError::IO(error) => write!(f, "cannot write inputs: {}", error),
+            GenerateInputsError::Run { name, error } => {
+                write!(f, "generator failed for test {}: {}", name.display(), error)
+            }
+            GenerateInputsError::StripPrefix(error) => {
+                write!(f, "input file outside of the source directory: {}", error)
+            }
+            GenerateInputsError::Naming(error) => write!(f, "{}", error),
+            GenerateInputsError::NoGenerator(name) => write!(
+                f,
+                "test {} is generated by arguments, but the test list has no generator",
+                name.display()
+            ),
+            GenerateInputsError::ListedFile { name, file, error } => write!(
+                f,
+                "cannot copy input file {} of test {}: {}",
+                file.display(),
+                name.display(),
+                error
+            ),
+            GenerateInputsError::Script(error) => write!(f, "invalid generation {}", error),
+            GenerateInputsError::Panicked => write!(f, "input generation thread panicked"),
+        }
+    }
+}
+
+impl error::Error for GenerateInputsError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            GenerateInputsError::IO(error) => Some(error),
+            GenerateInputsError::Run { error, .. } => Some(error),
+            GenerateInputsError::StripPrefix(error) => Some(error),
+            GenerateInputsError::Naming(error) => Some(error),
+            GenerateInputsError::ListedFile { error, .. } => Some(error),
+            GenerateInputsError::Script(error) => Some(error),
+            GenerateInputsError::NoGenerator(_) | GenerateInputsError::Panicked => None,
+        }
+    }
 }
 
 impl From<io::Error> for GenerateInputsError {
@@ -52,90 +189,302 @@ impl From<io::Error> for GenerateInputsError {
     }
 }
 
-impl From<CompileError> for GenerateInputsError {
-    fn from(error: CompileError) -> Self {
-        GenerateInputsError::GeneratorCompile(error)
+impl From<StripPrefixError> for GenerateInputsError {
+    fn from(error: StripPrefixError) -> Self {
+        GenerateInputsError::StripPrefix(error)
+    }
+}
+
+impl From<ScriptError> for GenerateInputsError {
+    fn from(error: ScriptError) -> Self {
+        GenerateInputsError::Script(error)
     }
 }
 
-impl From<UnknownExtensionError> for GenerateInputsError {
-    fn from(error: UnknownExtensionError) -> Self {
-        GenerateInputsError::GeneratorUnknownExtension(error)
+impl From<NamingError> for GenerateInputsError {
+    fn from(error: NamingError) -> Self {
+        GenerateInputsError::Naming(error)
     }
 }
 
+fn input_file(input_path: &Path, test: &GeneratedTest, ext: &str) -> PathBuf {
+    input_path.join(with_suffix(&test.name, ext))
+}
+
 fn generate_input(
     generator: &Generator,
-    input_path: &Path,
+    path: &Path,
     bar: &ProgressBar,
-    ext: &str,
-    i: u64,
+    test: &GeneratedTest,
 ) -> Result<(), GenerateInputsError> {
     let result = generator
-        .run(i)
-        .map_err(|error| GenerateInputsError::Run { error, index: i })?;
-    fs::write(input_path.join(i.to_string() + ext), result.output)?;
+        .run(test)
+        .map_err(|error| GenerateInputsError::Run {
+            name: test.name.clone(),
+            error,
+        })?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, result.output)?;
     bar.inc(1);
     Ok(())
 }
 
-fn generate_inputs(
-    program: &config::Program,
-    source: &Path,
-    generated: &Path,
+/// Runs the generator again for the inputs identical to an earlier input, in
+/// test order, and returns the number of inputs that were replaced. The
+/// attempt number is added to the `runs` of the replaced inputs.
+fn regenerate_duplicates(
+    generator: &Generator,
+    input_path: &Path,
     ext: &str,
-) -> Result<config::Files, GenerateInputsError> {
-    let generator = Arc::new(Generator::build(program, &source)?);
+    tests: &[GeneratedTest],
+    runs: &mut [GeneratorRun],
+) -> Result<usize, GenerateInputsError> {
+    let mut hashes = HashSet::new();
+    let mut regenerated = 0;
+    for (test, run) in tests.iter().zip(runs) {
+        let path = input_file(input_path, test, ext);
+        if hashes.insert(sha256_hex(&fs::read(&path)?)) {
+            continue;
+        }
+        for attempt in 1..=REGENERATE_ATTEMPTS {
+            let result =
+                generator
+                    .rerun(test, attempt)
+                    .map_err(|error| GenerateInputsError::Run {
+                        name: test.name.clone(),
+                        error,
+                    })?;
+            if hashes.insert(sha256_hex(&result.output)) {
+                fs::write(&path, result.output)?;
+                run.args.push(attempt.to_string());
+                regenerated += 1;
+                break;
+            }
+        }
+    }
+    Ok(regenerated)
+}
 
-    let count = 100;
-    let bar = Arc::new(ProgressBar::new(count));
+/// Runs `generator` for every test of `input` on `jobs` threads and writes the
+/// inputs, named as described in [`crate::naming`] with extension `ext`, to the
+/// `in/` directory of `generated`. With [`DuplicateInputs::Regenerate`],
+/// inputs identical to an earlier input are generated again.
+pub fn generate_inputs(
+    generator: Arc<Generator>,
+    input: &GeneratorInput,
+    generated: &Path,
+    ext: &str,
+    jobs: usize,
+    duplicates: DuplicateInputs,
+) -> Result<GeneratedInputs, GenerateInputsError> {
+    let tests = plan_tests(input)?;
+    let bar = Arc::new(ProgressBar::new(tests.len() as u64));
     bar.tick();
     let input_path_relative = PathBuf::from("in/");
     let input_path = generated.join(&input_path_relative);
     fs::create_dir_all(input_path.as_path())?;
 
-    let error = Arc::new(Mutex::new(None));
-    let pool = ThreadPool::new(get_thread_count());
-    for i in 0..count {
-        let bar = bar.clone();
-        let generator = generator.clone();
-        let input_path = input_path.clone();
-        let error = error.clone();
-        let ext = ext.to_string();
-        pool.execute(move || {
-            if error.lock().unwrap().is_some() {
-                return;
+    let jobs_tests = tests
+        .iter()
+        .map(|test| (input_file(&input_path, test, ext), test.clone()))
+        .collect();
+    let job_generator = generator.clone();
+    let job_bar = bar.clone();
+    run_parallel(jobs_tests, jobs, move |(path, test)| {
+        generate_input(&job_generator, &path, &job_bar, &test)
+    })
+    .map_err(|error| match error {
+        PoolError::Job(error) => error,
+        PoolError::Panicked => GenerateInputsError::Panicked,
+    })?;
+    bar.finish();
+    let mut runs: Vec<GeneratorRun> = tests
+        .iter()
+        .map(|test| GeneratorRun {
+            test: test.name.clone(),
+            generator: None,
+            args: Generator::args(test),
+        })
+        .collect();
+    let regenerated = match duplicates {
+        DuplicateInputs::Warn => 0,
+        DuplicateInputs::Regenerate => {
+            regenerate_duplicates(&generator, &input_path, ext, &tests, &mut runs)?
+        }
+    };
+
+    Ok(GeneratedInputs {
+        files: config::Files {
+            path: input_path_relative,
+            extensions: Some(vec![ext.to_string()]),
+            checksum: None,
+        },
+        regenerated,
+        runs,
+    })
+}
+
+/// Writes the inputs of the tests in `list`, named as described in
+/// [`crate::naming`] with extension `ext`, to the `in/` directory of
+/// `generated`. Input files are relative to `source`.
+pub fn write_listed_inputs(
+    list: &TestList,
+    generator: Option<&Generator>,
+    source: &Path,
+    generated: &Path,
+    ext: &str,
+) -> Result<GeneratedInputs, GenerateInputsError> {
+    let names = listed_test_names(list)?;
+    let input_path_relative = PathBuf::from("in/");
+    let input_path = generated.join(&input_path_relative);
+    fs::create_dir_all(&input_path)?;
+    let mut runs = vec![];
+    for (test, name) in list.tests.iter().zip(names) {
+        let path = input_path.join(with_suffix(&name, ext));
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        match &test.input {
+            ListedInput::Inline { input } => fs::write(&path, input)?,
+            ListedInput::File { file } => {
+                let file = source.join(file);
+                fs::copy(&file, &path).map_err(|error| GenerateInputsError::ListedFile {
+                    name: name.clone(),
+                    file,
+                    error,
+                })?;
             }
-            if let Err(err) = generate_input(&generator, &input_path, &bar, &ext, i) {
-                *error.lock().unwrap() = Some(err);
+            ListedInput::Generated { args } => {
+                let generator = generator.ok_or(GenerateInputsError::NoGenerator(name.clone()))?;
+                let result = generator.run_with_args(args.clone()).map_err(|error| {
+                    GenerateInputsError::Run {
+                        name: name.clone(),
+                        error,
+                    }
+                })?;
+                fs::write(&path, result.output)?;
+                runs.push(GeneratorRun {
+                    test: name,
+                    generator: None,
+                    args: args.clone(),
+                });
             }
-        })
-    }
-    pool.join();
-    if pool.panic_count() > 0 {
-        panic!()
-    }
-    if let Some(error) = Arc::try_unwrap(error).unwrap().into_inner().unwrap() {
-        return Err(error);
+        }
     }
-    bar.finish();
-
-    Ok(config::Files {
+    let files = config::Files {
         path: input_path_relative,
         extensions: Some(vec![ext.to_string()]),
+        checksum: None,
+    };
+    Ok(GeneratedInputs::new(files, runs))
+}
+
+/// Runs the generators of `input`, built into `generators` by name, as told
+/// by its script on `jobs` threads and writes the inputs with extension `ext`
+/// to the `in/` directory of `generated`.
+pub fn generate_script_inputs(
+    input: &ScriptInput,
+    generators: BTreeMap<String, Generator>,
+    generated: &Path,
+    ext: &str,
+    jobs: usize,
+) -> Result<GeneratedInputs, GenerateInputsError> {
+    let tests = parse_script(&input.script, &input.generators)?;
+    let runs = tests
+        .iter()
+        .map(|test| GeneratorRun {
+            test: test.name.clone(),
+            generator: Some(test.generator.clone()),
+            args: test.args.clone(),
+        })
+        .collect();
+    let bar = Arc::new(ProgressBar::new(tests.len() as u64));
+    bar.tick();
+    let input_path_relative = PathBuf::from("in/");
+    let input_path = generated.join(&input_path_relative);
+    fs::create_dir_all(&input_path)?;
+
+    let generators = Arc::new(generators);
+    let job_bar = bar.clone();
+    let job_ext = ext.to_string();
+    run_parallel(tests, jobs, move |test| {
+        let generator = &generators[&test.generator];
+        let result =
+            generator
+                .run_with_args(test.args)
+                .map_err(|error| GenerateInputsError::Run {
+                    name: test.name.clone(),
+                    error,
+                })?;
+        let path = input_path.join(with_suffix(&test.name, &job_ext));
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, result.output)?;
+        job_bar.inc(1);
+        Ok(())
     })
+    .map_err(|error| match error {
+        PoolError::Job(error) => error,
+        PoolError::Panicked => GenerateInputsError::Panicked,
+    })?;
+    bar.finish();
+    let files = config::Files {
+        path: input_path_relative,
+        extensions: Some(vec![ext.to_string()]),
+        checksum: None,
+    };
+    Ok(GeneratedInputs::new(files, runs))
 }
 
-pub fn copy_or_generate_input(
-    input: &config::InputRef,
-    source: &Path,
+/// Outputs written by [`write_listed_outputs`].
+pub struct ListedOutputs {
+    pub files: config::Files,
+    /// Input files of the tests whose outputs were written.
+    pub inputs: Vec<PathBuf>,
+    /// Names of the tests without an output in the list.
+    pub missing: Vec<PathBuf>,
+}
+
+/// Writes the expected outputs given in `list` to the `out/` directory of
+/// `generated`, for the inputs written by [`write_listed_inputs`] with
+/// extension `input_ext`.
+pub fn write_listed_outputs(
+    list: &TestList,
     generated: &Path,
-) -> Result<config::Files, GenerateInputsError> {
-    Ok(match input {
-        config::InputRef::Files(files) => copy_inputs(files, source, generated)?,
-        config::InputRef::Generator { program } => {
-            generate_inputs(&program.to_program()?, source, generated, ".in")?
+    input_ext: &str,
+    ext: &str,
+) -> Result<ListedOutputs, GenerateOutputsError> {
+    let names = listed_test_names(list).map_err(GenerateOutputsError::Naming)?;
+    let output_path_relative = PathBuf::from("out/");
+    let output_path = generated.join(&output_path_relative);
+    let mut inputs = vec![];
+    let mut missing = vec![];
+    for (test, name) in list.tests.iter().zip(names) {
+        let output = match &test.output {
+            Some(output) => output,
+            None => {
+                missing.push(name);
+                continue;
+            }
+        };
+        let path = output_path.join(with_suffix(&name, ext));
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
         }
+        fs::write(&path, output)?;
+        inputs.push(generated.join("in").join(with_suffix(&name, input_ext)));
+    }
+    Ok(ListedOutputs {
+        files: config::Files {
+            path: output_path_relative,
+            extensions: Some(vec![ext.to_string()]),
+            checksum: None,
+        },
+        inputs,
+        missing,
     })
 }
 
@@ -143,19 +492,48 @@ pub fn copy_or_generate_input(
 pub enum GenerateOutputsError {
     IO(io::Error),
     Run(RunError),
-    GeneratorCompile(CompileError),
     StripPrefix(StripPrefixError),
+    Naming(NamingError),
+    /// A test has no expected output and there is no model program.
+    MissingOutput(PathBuf),
+    /// A worker thread panicked.
+    Panicked,
 }
 
-impl From<io::Error> for GenerateOutputsError {
-    fn from(error: io::Error) -> Self {
-        GenerateOutputsError::IO(error)
+impl fmt::Display for GenerateOutputsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GenerateOutputsError::IO(error) => write!(f, "cannot write outputs: {}", error),
+            GenerateOutputsError::Run(error) => write!(f, "model program failed: {}", error),
+            GenerateOutputsError::StripPrefix(error) => {
+                write!(f, "input file outside of the input directory: {}", error)
+            }
+            GenerateOutputsError::Naming(error) => write!(f, "{}", error),
+            GenerateOutputsError::MissingOutput(name) => write!(
+                f,
+                "test {} has no expected output, list one or add a model program",
+                name.display()
+            ),
+            GenerateOutputsError::Panicked => write!(f, "output generation thread panicked"),
+        }
+    }
+}
+
+impl error::Error for GenerateOutputsError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            GenerateOutputsError::IO(error) => Some(error),
+            GenerateOutputsError::Run(error) => Some(error),
+            GenerateOutputsError::StripPrefix(error) => Some(error),
+            GenerateOutputsError::Naming(error) => Some(error),
+            GenerateOutputsError::MissingOutput(_) | GenerateOutputsError::Panicked => None,
+        }
     }
 }
 
-impl From<CompileError> for GenerateOutputsError {
-    fn from(error: CompileError) -> Self {
-        GenerateOutputsError::GeneratorCompile(error)
+impl From<io::Error> for GenerateOutputsError {
+    fn from(error: io::Error) -> Self {
+        GenerateOutputsError::IO(error)
     }
 }
 
@@ -178,55 +556,307 @@ pub fn generate_output(
     bar: &ProgressBar,
 ) -> Result<(), GenerateOutputsError> {
     let result = model_runner.run(fs::read(input_path)?, vec![])?;
-    fs::create_dir_all(output_path.parent().unwrap())?;
+    if let Some(parent) = output_path.parent() {
+        fs::create_dir_all(parent)?;
+    }
     fs::write(output_path, result.output)?;
     bar.inc(1);
     Ok(())
 }
 
+/// Runs the model program on every input listed in `input_config` on `jobs`
+/// threads and writes the outputs to the `out/` directory of `generated`, named
+/// after the inputs with their extension replaced by `ext`. Inputs in `skip`
+/// already have outputs and are not run.
 pub fn generate_outputs(
-    model_runner_program: &config::Program,
+    model_runner: Arc<Runner>,
     input_config: &config::Files,
-    source: &Path,
     generated: &Path,
     ext: &str,
+    jobs: usize,
+    skip: &[PathBuf],
 ) -> Result<config::Files, GenerateOutputsError> {
-    let model_runner = Arc::from(Runner::build(model_runner_program, source)?);
-    let input_files = list_config_files(input_config, generated, FilesType::Input)?;
-    let bar = ProgressBar::new(input_files.len() as u64);
+    let mut input_files = list_config_files(input_config, generated, FilesType::Input)?;
+    input_files.retain(|file| !skip.contains(file));
+    input_files.sort();
+    let input_extensions = config_extensions(input_config, FilesType::Input);
+    let bar = Arc::new(ProgressBar::new(input_files.len() as u64));
     bar.tick();
-    let error = Arc::new(Mutex::new(None));
-    let pool = ThreadPool::new(get_thread_count());
     let output_path_relative = PathBuf::from("out/");
     let output_path = generated.join(&output_path_relative);
+    let mut files = vec![];
     for input_file in input_files {
-        let output_file = output_path.join(
-            input_file
-                .strip_prefix(generated.join(&input_config.path))?
-                .with_extension(ext.strip_prefix(".").unwrap()),
+        // Only the input extension is replaced, names may contain other dots.
+        let name = input_file.strip_prefix(generated.join(&input_config.path))?;
+        let stem = strip_extension(
+            name,
+            matching_extension(name, &input_extensions).unwrap_or_default(),
         );
-        let model_runner = model_runner.clone();
-        let error = error.clone();
-        let bar = bar.clone();
-        pool.execute(move || {
-            if error.lock().unwrap().is_some() {
-                return;
-            }
-            if let Err(err) = generate_output(&model_runner, &input_file, &output_file, &bar) {
-                *error.lock().unwrap() = Some(err);
-            }
-        })
-    }
-    pool.join();
-    if pool.panic_count() > 0 {
-        panic!()
-    }
-    if let Some(error) = Arc::try_unwrap(error).unwrap().into_inner().unwrap() {
-        return Err(error);
+        let output_file = output_path.join(with_suffix(&stem, ext));
+        files.push((input_file, output_file));
     }
+    let job_bar = bar.clone();
+    run_parallel(files, jobs, move |(input_file, output_file)| {
+        generate_output(&model_runner, &input_file, &output_file, &job_bar)
+    })
+    .map_err(|error| match error {
+        PoolError::Job(error) => error,
+        PoolError::Panicked => GenerateOutputsError::Panicked,
+    })?;
     bar.finish();
     Ok(Files {
         path: output_path_relative,
         extensions: Some(vec![ext.to_string()]),
+        checksum: None,
     })
 }
+
+/// Directory of a generated package the program sources are copied to.
+pub const PROGRAMS_DIR: &str = "programs";
+
+/// Extensions of the headers copied along with a C++ source.
+const HEADER_EXTENSIONS: [&str; 4] = ["h", "hh", "hpp", "hxx"];
+
+/// Copies the source files of programs to the `programs/` directory of a
+/// generated package, so that the package does not depend on the source
+/// directory or network. Sources inside the source directory keep their
+/// relative path, others are copied under their file name. C++ sources are
+/// copied with the headers next to them.
+pub struct ProgramCopier<'a> {
+    source: &'a Path,
+    generated: &'a Path,
+    /// The file copied to every path in the package.
+    copied: BTreeMap<PathBuf, PathBuf>,
+}
+
+impl<'a> ProgramCopier<'a> {
+    /// Removes the programs copied to `generated` before.
+    pub fn new(source: &'a Path, generated: &'a Path) -> io::Result<ProgramCopier<'a>> {
+        let programs = generated.join(PROGRAMS_DIR);
+        if programs.exists() {
+            fs::remove_dir_all(programs)?;
+        }
+        Ok(ProgramCopier {
+            source,
+            generated,
+            copied: BTreeMap::new(),
+        })
+    }
+
+    /// Copies the source of `program` and returns the program with its path
+    /// in the package. Programs without a source file are returned unchanged.
+    pub fn copy(&mut self, program: &ProgramOrShorthand) -> io::Result<ProgramOrShorthand> {
+        Ok(match program {
+            ProgramOrShorthand::Shorthand(path) => {
+                let headers = path.extension().is_some_and(|ext| ext == "cpp");
+                ProgramOrShorthand::Shorthand(self.copy_source(path, &None, headers)?)
+            }
+            ProgramOrShorthand::Value(config::Program::GPP {
+                path,
+                compiler_args,
+                checksum,
+            }) => ProgramOrShorthand::Value(config::Program::GPP {
+                path: self.copy_source(path, checksum, true)?,
+                compiler_args: compiler_args.clone(),
+                checksum: None,
+            }),
+            ProgramOrShorthand::Value(config::Program::Python { path, checksum }) => {
+                ProgramOrShorthand::Value(config::Program::Python {
+                    path: self.copy_source(path, checksum, false)?,
+                    checksum: None,
+                })
+            }
+            ProgramOrShorthand::Value(program) => ProgramOrShorthand::Value(program.clone()),
+        })
+    }
+
+    fn copy_source(
+        &mut self,
+        path: &Path,
+        checksum: &Option<String>,
+        headers: bool,
+    ) -> io::Result<PathBuf> {
+        let file_name = match path.file_name() {
+            Some(file_name) => PathBuf::from(file_name),
+            None => return Ok(path.to_path_buf()),
+        };
+        let inside_source = !remote::is_url(path)
+            && path
+                .components()
+                .all(|component| matches!(component, Component::Normal(_)));
+        let target = Path::new(PROGRAMS_DIR).join(if inside_source { path } else { &file_name });
+        let local = self
+            .source
+            .join(remote::local_path(path, checksum.as_deref())?);
+        self.copy_file(&local, &target)?;
+        if headers && !remote::is_url(path) {
+            let dir = local.parent().unwrap_or(self.source);
+            for entry in fs::read_dir(dir)? {
+                let header = entry?.path();
+                let is_header = header
+                    .extension()
+                    .and_then(|ext| ext.to_str())
+                    .is_some_and(|ext| HEADER_EXTENSIONS.contains(&ext));
+                if let (true, Some(name)) = (is_header && header.is_file(), header.file_name()) {
+                    self.copy_file(&header, &target.with_file_name(name))?;
+                }
+            }
+        }
+        Ok(target)
+    }
+
+    /// Copies `file` to `target` in the package, failing if a different file
+    /// was already copied there.
+    fn copy_file(&mut self, file: &Path, target: &Path) -> io::Result<()> {
+        match self.copied.get(target) {
+            Some(copied) if copied == file => return Ok(()),
+            Some(copied) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::AlreadyExists,
+                    format!(
+                        "{} and {} would both be copied to {}",
+                        copied.display(),
+                        file.display(),
+                        target.display()
+                    ),
+                ))
+            }
+            None => {}
+        }
+        let path = self.generated.join(target);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::copy(file, path)?;
+        self.copied.insert(target.to_path_buf(), file.to_path_buf());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ListedTest;
+    use crate::utils::{relative_files, test_dir};
+
+    fn listed(name: Option<&str>, input: ListedInput) -> ListedTest {
+        ListedTest {
+            name: name.map(str::to_string),
+            input,
+            output: None,
+        }
+    }
+
+    #[test]
+    fn writes_listed_inputs() {
+        let source = test_dir();
+        fs::write(source.join("big.in"), "100\n").unwrap();
+        let generated = test_dir();
+        let list = TestList {
+            generator: None,
+            tests: vec![
+                listed(
+                    None,
+                    ListedInput::Inline {
+                        input: "1 2\n".to_string(),
+                    },
+                ),
+                listed(
+                    Some("edge/big"),
+                    ListedInput::File {
+                        file: PathBuf::from("big.in"),
+                    },
+                ),
+            ],
+        };
+        write_listed_inputs(&list, None, &source, &generated, ".in").unwrap();
+        assert_eq!(relative_files(&generated), ["in/0.in", "in/edge/big.in"]);
+        assert_eq!(
+            fs::read_to_string(generated.join("in/0.in")).unwrap(),
+            "1 2\n"
+        );
+        assert_eq!(
+            fs::read_to_string(generated.join("in/edge/big.in")).unwrap(),
+            "100\n"
+        );
+    }
+
+    #[test]
+    fn missing_listed_file_is_named() {
+        let source = test_dir();
+        let list = TestList {
+            generator: None,
+            tests: vec![listed(
+                Some("big"),
+                ListedInput::File {
+                    file: PathBuf::from("big.in"),
+                },
+            )],
+        };
+        let error = write_listed_inputs(&list, None, &source, &test_dir(), ".in").unwrap_err();
+        assert!(matches!(error, GenerateInputsError::ListedFile { .. }));
+        assert!(error.to_string().starts_with(&format!(
+            "cannot copy input file {} of test big:",
+            source.join("big.in").display()
+        )));
+    }
+
+    #[test]
+    fn generated_listed_input_needs_a_generator() {
+        let list = TestList {
+            generator: None,
+            tests: vec![listed(None, ListedInput::Generated { args: vec![] })],
+        };
+        let error = write_listed_inputs(&list, None, &test_dir(), &test_dir(), ".in").unwrap_err();
+        assert!(matches!(error, GenerateInputsError::NoGenerator(name) if name == Path::new("0")));
+    }
+
+    /// Generates 4 inputs with a generator printing the test index modulo 2,
+    /// or 100 plus the attempt number when run again.
+    fn generate_parity(duplicates: DuplicateInputs) -> (PathBuf, GeneratedInputs) {
+        let dir = test_dir();
+        fs::write(
+            dir.join("gen.py"),
+            "import sys\nargs = sys.argv[1:]\n\
+             print(int(args[0]) % 2 if len(args) == 1 else 100 + int(args[1]))\n",
+        )
+        .unwrap();
+        let program = config::Program::Python {
+            path: PathBuf::from("gen.py"),
+            checksum: None,
+        };
+        let generator = Arc::new(Generator::build(&program, &dir).unwrap());
+        let input = GeneratorInput {
+            program: ProgramOrShorthand::Value(program),
+            count: Some(4),
+            name: None,
+            groups: vec![],
+        };
+        let generated = test_dir();
+        let inputs = generate_inputs(generator, &input, &generated, ".in", 2, duplicates).unwrap();
+        (generated, inputs)
+    }
+
+    fn read_inputs(generated: &Path) -> Vec<String> {
+        (0..4)
+            .map(|index| fs::read_to_string(generated.join(format!("in/{}.in", index))).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn duplicate_inputs_are_kept_by_default() {
+        let (generated, inputs) = generate_parity(DuplicateInputs::Warn);
+        assert_eq!(inputs.regenerated, 0);
+        assert_eq!(read_inputs(&generated), ["0\n", "1\n", "0\n", "1\n"]);
+    }
+
+    #[test]
+    fn duplicate_inputs_are_regenerated() {
+        let (generated, inputs) = generate_parity(DuplicateInputs::Regenerate);
+        assert_eq!(inputs.regenerated, 2);
+        // The first attempt for test 3 repeats the new input of test 2.
+        assert_eq!(read_inputs(&generated), ["0\n", "1\n", "101\n", "102\n"]);
+        let args: Vec<&[String]> = inputs.runs.iter().map(|run| &run.args[..]).collect();
+        assert_eq!(args, [&["0"][..], &["1"], &["2", "1"], &["3", "2"]]);
+    }
+}
@@ -0,0 +1,131 @@
+use super::{ImportError, ImportedTest, Problem};
+use crate::utils::list_files;
+use serde::Deserialize;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+#[derive(Deserialize, Default)]
+struct Limits {
+    /// In MiB.
+    memory: Option<u64>,
+    /// In seconds, only in newer versions of the format.
+    time_limit: Option<f64>,
+}
+
+#[derive(Deserialize, Default)]
+struct ProblemYaml {
+    #[serde(default)]
+    limits: Limits,
+}
+
+fn relative(dir: &Path, path: &Path) -> PathBuf {
+    path.strip_prefix(dir)
+        .expect("listed files are in the package")
+        .to_path_buf()
+}
+
+fn seconds_to_millis(seconds: f64) -> u64 {
+    (seconds * 1000.0).round() as u64
+}
+
+/// Time limit from `.timelimit` or the `timelimit` key of
+/// `domjudge-problem.ini`, in seconds.
+fn read_time_limit(dir: &Path) -> Option<f64> {
+    if let Ok(text) = fs::read_to_string(dir.join(".timelimit")) {
+        return text.trim().parse().ok();
+    }
+    let ini = fs::read_to_string(dir.join("domjudge-problem.ini")).ok()?;
+    ini.lines().find_map(|line| {
+        let (key, value) = line.split_once('=')?;
+        if key.trim() != "timelimit" {
+            return None;
+        }
+        value
+            .trim()
+            .trim_matches(|c| c == '\'' || c == '"')
+            .parse()
+            .ok()
+    })
+}
+
+/// The only `.cpp` or `.py` file of the first output validator.
+fn find_validator(dir: &Path, problem: &mut Problem) -> Result<(), ImportError> {
+    let validators = ["output_validators", "output_validator"]
+        .iter()
+        .map(|name| dir.join(name))
+        .find(|path| path.is_dir());
+    let validators = match validators {
+        Some(validators) => validators,
+        None => return Ok(()),
+    };
+    let mut sources: Vec<PathBuf> = list_files(&validators)?
+        .into_iter()
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|ext| ext == "cpp" || ext == "py")
+        })
+        .collect();
+    sources.sort();
+    match sources.as_slice() {
+        [source] => {
+            let source = relative(dir, source);
+            problem.warnings.push(format!(
+                "the output validator {} reads the solution output from stdin, adapt it to the iobot verifier arguments",
+                source.display()
+            ));
+            problem.checker = Some(source);
+        }
+        _ => problem.warnings.push(
+            "cannot find a single .cpp or .py output validator, it was not imported".to_string(),
+        ),
+    }
+    Ok(())
+}
+
+/// Reads the tests in `data/sample` and `data/secret`, the limits and the
+/// output validator. Sample tests are put in the `sample` group and the
+/// directories in `data/secret` become groups.
+pub(super) fn read(dir: &Path) -> Result<Problem, ImportError> {
+    let data = dir.join("data");
+    if !data.is_dir() {
+        return Err(ImportError::Invalid(
+            "the package has no data directory".to_string(),
+        ));
+    }
+    let mut problem = Problem::default();
+    let mut inputs: Vec<PathBuf> = list_files(&data)?
+        .into_iter()
+        .filter(|path| path.extension().is_some_and(|ext| ext == "in"))
+        .collect();
+    inputs.sort();
+    for input in inputs {
+        let name = input
+            .strip_prefix(&data)
+            .expect("listed files are in the data directory")
+            .with_extension("");
+        let mut components = name.components();
+        let name = match components.next() {
+            Some(Component::Normal(first)) if first == "secret" => components.as_path(),
+            Some(Component::Normal(first)) if first == "sample" => name.as_path(),
+            _ => continue,
+        }
+        .to_path_buf();
+        let answer = input.with_extension("ans");
+        problem.tests.push(ImportedTest {
+            name,
+            input: relative(dir, &input),
+            answer: answer.is_file().then(|| relative(dir, &answer)),
+        });
+    }
+
+    let problem_yaml = match fs::read(dir.join("problem.yaml")) {
+        Ok(bytes) => serde_yaml::from_slice(&bytes)?,
+        Err(_) => ProblemYaml::default(),
+    };
+    problem.options.memory_limit = problem_yaml.limits.memory;
+    problem.options.time_limit = read_time_limit(dir)
+        .or(problem_yaml.limits.time_limit)
+        .map(seconds_to_millis);
+    find_validator(dir, &mut problem)?;
+    Ok(problem)
+}
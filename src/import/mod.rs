@@ -0,0 +1,479 @@
+use crate::archive;
+use crate::config::{
+    Config, ConfigError, Files, FilesInput, GeneratorInput, Input, InputRef, JustVerifier,
+    ModelProgram, Options, OutputFiles, ProgramOrShorthand,
+};
+use crate::export::Format;
+use crate::utils::with_suffix;
+use std::path::{Component, Path, PathBuf};
+use std::{error, fmt, fs, io};
+
+mod kattis;
+mod polygon;
+
+/// Extension of imported inputs.
+pub const INPUT_EXTENSION: &str = ".in";
+/// Extension of imported answers.
+pub const OUTPUT_EXTENSION: &str = ".out";
+
+#[derive(Debug)]
+pub enum ImportError {
+    IO(io::Error),
+    Xml(roxmltree::Error),
+    Yaml(serde_yaml::Error),
+    Config(ConfigError),
+    UnsupportedFormat(Format),
+    /// The package does not follow the layout of its format.
+    Invalid(String),
+}
+
+impl fmt::Display for ImportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ImportError::IO(error) => write!(f, "cannot import the package: {}", error),
+            ImportError::Xml(error) => write!(f, "invalid problem.xml: {}", error),
+            ImportError::Yaml(error) => write!(f, "invalid problem.yaml: {}", error),
+            ImportError::Config(error) => write!(f, "{}", error),
+            ImportError::UnsupportedFormat(format) => {
+                write!(
+                    f,
+                    "cannot import {} packages, expected polygon, kattis or domjudge",
+                    format
+                )
+            }
+            ImportError::Invalid(message) => write!(f, "invalid package: {}", message),
+        }
+    }
+}
+
+impl error::Error for ImportError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            ImportError::IO(error) => Some(error),
+            ImportError::Xml(error) => Some(error),
+            ImportError::Yaml(error) => Some(error),
+            ImportError::Config(error) => Some(error),
+            ImportError::UnsupportedFormat(_) | ImportError::Invalid(_) => None,
+        }
+    }
+}
+
+impl From<io::Error> for ImportError {
+    fn from(error: io::Error) -> Self {
+        ImportError::IO(error)
+    }
+}
+
+impl From<roxmltree::Error> for ImportError {
+    fn from(error: roxmltree::Error) -> Self {
+        ImportError::Xml(error)
+    }
+}
+
+impl From<serde_yaml::Error> for ImportError {
+    fn from(error: serde_yaml::Error) -> Self {
+        ImportError::Yaml(error)
+    }
+}
+
+impl From<ConfigError> for ImportError {
+    fn from(error: ConfigError) -> Self {
+        ImportError::Config(error)
+    }
+}
+
+/// A test read from a package. Paths are relative to the package root.
+struct ImportedTest {
+    /// Name of the test without an extension, directories become groups.
+    name: PathBuf,
+    input: PathBuf,
+    answer: Option<PathBuf>,
+}
+
+/// Contents of a package in one of the supported formats. Program paths are
+/// relative to the package root and kept the same in the imported package.
+#[derive(Default)]
+struct Problem {
+    tests: Vec<ImportedTest>,
+    /// Number of tests listed by the package whose inputs are not included,
+    /// such as generated Polygon tests.
+    missing_inputs: usize,
+    checker: Option<PathBuf>,
+    generator: Option<PathBuf>,
+    model: Option<PathBuf>,
+    options: Options,
+    warnings: Vec<String>,
+}
+
+/// Result of [`import`].
+pub struct Imported {
+    pub config: Config,
+    pub test_count: usize,
+    /// Parts of the package that could not be imported exactly.
+    pub warnings: Vec<String>,
+}
+
+/// Reads the package in `format` from the directory or archive `source`
+/// and writes it with an `iobot.yaml` to the directory `target`.
+pub fn import(format: Format, source: &Path, target: &Path) -> Result<Imported, ImportError> {
+    import_dir(format, &archive::package_dir(source)?, target)
+}
+
+fn import_dir(format: Format, source: &Path, target: &Path) -> Result<Imported, ImportError> {
+    let mut problem = match format {
+        Format::Polygon => polygon::read(source)?,
+        Format::Kattis | Format::DomJudge => kattis::read(source)?,
+        Format::Cms => return Err(ImportError::UnsupportedFormat(format)),
+    };
+    // Paths come from the package files, such as the groups and path patterns
+    // of problem.xml, and must not point outside of the source or target.
+    for test in &problem.tests {
+        check_inside(&test.name)?;
+        check_inside(&test.input)?;
+        if let Some(answer) = &test.answer {
+            check_inside(answer)?;
+        }
+    }
+    for program in [&problem.checker, &problem.generator, &problem.model]
+        .into_iter()
+        .flatten()
+    {
+        check_inside(program)?;
+    }
+    let config = to_config(&mut problem)?;
+    fs::create_dir_all(target)?;
+    let mut programs = vec![config.verifier(), config.model_program()];
+    let test_count = match config.get_input() {
+        InputRef::Files(_) => {
+            for test in &problem.tests {
+                copy_file(
+                    &source.join(&test.input),
+                    &target
+                        .join("in")
+                        .join(with_suffix(&test.name, INPUT_EXTENSION)),
+                )?;
+                if let (Config::OutputFiles(_), Some(answer)) = (&config, &test.answer) {
+                    copy_file(
+                        &source.join(answer),
+                        &target
+                            .join("out")
+                            .join(with_suffix(&test.name, OUTPUT_EXTENSION)),
+                    )?;
+                }
+            }
+            problem.tests.len()
+        }
+        InputRef::Generator(generator) => {
+            programs.push(Some(&generator.program));
+            0
+        }
+        // Imported packages never list their tests or use scripts.
+        InputRef::Tests(_) | InputRef::Script(_) => 0,
+    };
+    for program in programs.into_iter().flatten() {
+        let program = match program.source_path() {
+            Some(program) => program,
+            None => continue,
+        };
+        for file in program_files(source, program)? {
+            copy_file(&source.join(&file), &target.join(&file))?;
+        }
+    }
+    config.save(target)?;
+    Ok(Imported {
+        config,
+        test_count,
+        warnings: problem.warnings,
+    })
+}
+
+/// Rejects paths read from a package that are not relative to the package
+/// root or go up with `..`.
+fn check_inside(path: &Path) -> Result<(), ImportError> {
+    if path.as_os_str().is_empty()
+        || !path
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)))
+    {
+        return Err(ImportError::Invalid(format!(
+            "{} is not a path inside of the package",
+            path.display()
+        )));
+    }
+    Ok(())
+}
+
+/// Picks the config type matching what the package contains: output files if
+/// every test has an answer, kept with the main solution that produced them,
+/// otherwise the main solution as the model program or just the checker.
+fn to_config(problem: &mut Problem) -> Result<Config, ImportError> {
+    let verifier = problem.checker.clone().map(ProgramOrShorthand::Shorthand);
+    let model = problem.model.clone().map(ProgramOrShorthand::Shorthand);
+    let options = problem.options.clone();
+    if problem.tests.is_empty() || problem.missing_inputs > 0 {
+        if let (Some(generator), Some(model_program)) = (&problem.generator, &model) {
+            if !problem.tests.is_empty() {
+                problem.warnings.push(format!(
+                    "{} tests included in the package were dropped, as inputs are generated",
+                    problem.tests.len()
+                ));
+            }
+            return Ok(Config::ModelProgram(ModelProgram {
+                input: Input::Generator(GeneratorInput {
+                    program: ProgramOrShorthand::Shorthand(generator.clone()),
+                    count: None,
+                    name: None,
+                    groups: vec![],
+                }),
+                model_program: model_program.clone(),
+                verifier,
+                options,
+            }));
+        }
+        if problem.tests.is_empty() {
+            return Err(ImportError::Invalid("the package has no tests".to_string()));
+        }
+        problem.warnings.push(format!(
+            "{} tests without included inputs were skipped",
+            problem.missing_inputs
+        ));
+    }
+    let input = Files {
+        path: PathBuf::from("in"),
+        extensions: Some(vec![INPUT_EXTENSION.to_string()]),
+        checksum: None,
+    };
+    if problem.tests.iter().all(|test| test.answer.is_some()) {
+        return Ok(Config::OutputFiles(OutputFiles {
+            input: FilesInput::Files(input),
+            output_files: Files {
+                path: PathBuf::from("out"),
+                extensions: Some(vec![OUTPUT_EXTENSION.to_string()]),
+                checksum: None,
+            },
+            model_program: model,
+            verifier,
+            options,
+        }));
+    }
+    match (model, verifier) {
+        (Some(model_program), verifier) => Ok(Config::ModelProgram(ModelProgram {
+            input: Input::Files(input),
+            model_program,
+            verifier,
+            options,
+        })),
+        (None, Some(verifier)) => Ok(Config::JustVerifier(JustVerifier {
+            input: Input::Files(input),
+            verifier,
+            options,
+        })),
+        (None, None) => Err(ImportError::Invalid(
+            "some tests have no answers and there is no main solution or checker".to_string(),
+        )),
+    }
+}
+
+/// The source file of `program` and the headers next to it, such as
+/// `testlib.h`.
+fn program_files(source: &Path, program: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut files = vec![program.to_path_buf()];
+    let parent = program.parent().unwrap_or_else(|| Path::new(""));
+    for entry in source.join(parent).read_dir()? {
+        let path = entry?.path();
+        if path.is_file()
+            && path
+                .extension()
+                .is_some_and(|ext| ext == "h" || ext == "hpp")
+        {
+            files.push(parent.join(path.file_name().expect("read_dir returns file names")));
+        }
+    }
+    Ok(files)
+}
+
+fn copy_file(source: &Path, target: &Path) -> io::Result<()> {
+    if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::copy(source, target)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::{relative_files, test_dir};
+
+    fn write(dir: &Path, files: &[(&str, &str)]) {
+        for (path, contents) in files {
+            let path = dir.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, contents).unwrap();
+        }
+    }
+
+    fn polygon_xml(group: &str, checker: &str) -> String {
+        format!(
+            r#"<problem>
+  <judging>
+    <testset name="tests">
+      <time-limit>2000</time-limit>
+      <memory-limit>268435456</memory-limit>
+      <input-path-pattern>tests/%02d</input-path-pattern>
+      <answer-path-pattern>tests/%02d.a</answer-path-pattern>
+      <tests>
+        <test method="manual" sample="true"/>
+        <test method="manual" group="{}"/>
+      </tests>
+    </testset>
+  </judging>
+  <assets>
+    <checker type="testlib"><source path="{}" type="cpp.g++17"/></checker>
+    <solutions>
+      <solution tag="main"><source path="solutions/main.cpp" type="cpp.g++17"/></solution>
+    </solutions>
+  </assets>
+</problem>"#,
+            group, checker
+        )
+    }
+
+    fn polygon_package(group: &str, checker: &str) -> PathBuf {
+        let source = test_dir();
+        write(
+            &source,
+            &[
+                ("problem.xml", &polygon_xml(group, checker)),
+                ("tests/01", "1 2\n"),
+                ("tests/01.a", "3\n"),
+                ("tests/02", "2 2\n"),
+                ("tests/02.a", "4\n"),
+                ("files/check.cpp", "#include \"testlib.h\"\n"),
+                ("files/testlib.h", ""),
+                ("solutions/main.cpp", ""),
+            ],
+        );
+        source
+    }
+
+    #[test]
+    fn imports_a_polygon_package() {
+        let source = polygon_package("large", "files/check.cpp");
+        let target = test_dir();
+        let imported = import_dir(Format::Polygon, &source, &target).unwrap();
+        assert_eq!(imported.test_count, 2);
+        assert_eq!(
+            fs::read_to_string(target.join("iobot.yaml")).unwrap(),
+            "---
+input:
+  type: files
+  path: in
+  extensions:
+    - \".in\"
+outputFiles:
+  path: out
+  extensions:
+    - \".out\"
+modelProgram: solutions/main.cpp
+verifier: files/check.cpp
+timeLimit: 2000
+memoryLimit: 256
+"
+        );
+        assert_eq!(
+            relative_files(&target),
+            [
+                "files/check.cpp",
+                "files/testlib.h",
+                "in/01.in",
+                "in/large/02.in",
+                "iobot.yaml",
+                "out/01.out",
+                "out/large/02.out",
+                "solutions/main.cpp",
+            ]
+        );
+        assert_eq!(
+            fs::read_to_string(target.join("out/large/02.out")).unwrap(),
+            "4\n"
+        );
+    }
+
+    #[test]
+    fn rejects_polygon_paths_outside_of_the_package() {
+        for (group, checker) in [("../large", "files/check.cpp"), ("large", "../check.cpp")] {
+            let source = polygon_package(group, checker);
+            let target = test_dir();
+            match import_dir(Format::Polygon, &source, &target) {
+                Err(ImportError::Invalid(message)) => assert!(message.contains("..")),
+                _ => panic!("expected an invalid package"),
+            }
+            assert!(relative_files(&target).is_empty());
+        }
+    }
+
+    #[test]
+    fn rejects_polygon_path_patterns_outside_of_the_package() {
+        let source = polygon_package("large", "files/check.cpp");
+        let name = source.file_name().unwrap().to_str().unwrap();
+        let xml = polygon_xml("large", "files/check.cpp").replace(
+            "<input-path-pattern>tests/",
+            &format!("<input-path-pattern>../{}/tests/", name),
+        );
+        fs::write(source.join("problem.xml"), xml).unwrap();
+        assert!(matches!(
+            import_dir(Format::Polygon, &source, &test_dir()),
+            Err(ImportError::Invalid(_))
+        ));
+    }
+
+    #[test]
+    fn imports_a_kattis_package() {
+        let source = test_dir();
+        write(
+            &source,
+            &[
+                ("problem.yaml", "name: Sum\nlimits:\n  memory: 512\n"),
+                (".timelimit", "1.5\n"),
+                ("data/sample/1.in", "1 2\n"),
+                ("data/sample/1.ans", "3\n"),
+                ("data/secret/large/1.in", "2 2\n"),
+                ("data/secret/large/1.ans", "4\n"),
+                ("output_validators/validate/validate.cpp", ""),
+            ],
+        );
+        let target = test_dir();
+        let imported = import_dir(Format::Kattis, &source, &target).unwrap();
+        assert_eq!(imported.test_count, 2);
+        assert_eq!(imported.warnings.len(), 1);
+        assert_eq!(
+            fs::read_to_string(target.join("iobot.yaml")).unwrap(),
+            "---
+input:
+  type: files
+  path: in
+  extensions:
+    - \".in\"
+outputFiles:
+  path: out
+  extensions:
+    - \".out\"
+verifier: output_validators/validate/validate.cpp
+timeLimit: 1500
+memoryLimit: 512
+"
+        );
+        assert_eq!(
+            relative_files(&target),
+            [
+                "in/large/1.in",
+                "in/sample/1.in",
+                "iobot.yaml",
+                "out/large/1.out",
+                "out/sample/1.out",
+                "output_validators/validate/validate.cpp",
+            ]
+        );
+    }
+}
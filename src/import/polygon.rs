@@ -0,0 +1,127 @@
+use super::{ImportError, ImportedTest, Problem};
+use roxmltree::{Document, Node};
+use std::cmp;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+fn child<'a, 'input>(node: Node<'a, 'input>, name: &str) -> Option<Node<'a, 'input>> {
+    node.children().find(|child| child.has_tag_name(name))
+}
+
+/// Path of the `<source>` of an asset such as `<checker>` or `<executable>`.
+fn source_path(node: Node) -> Option<PathBuf> {
+    child(node, "source")
+        .and_then(|source| source.attribute("path"))
+        .map(PathBuf::from)
+}
+
+/// Expands a printf-style pattern such as `tests/%02d` for test `index`.
+fn format_pattern(pattern: &str, index: usize) -> String {
+    let start = match pattern.find('%') {
+        Some(start) => start,
+        None => return pattern.to_string(),
+    };
+    let end = match pattern[start..].find('d') {
+        Some(end) => start + end,
+        None => return pattern.to_string(),
+    };
+    let width: usize = pattern[start + 1..end].parse().unwrap_or(0);
+    format!(
+        "{}{:0width$}{}",
+        &pattern[..start],
+        index,
+        &pattern[end + 1..],
+        width = width
+    )
+}
+
+/// Reads `problem.xml` and the tests of the `tests` testset. Tests are named
+/// by their index and put in a directory named after their group. Generated
+/// tests missing from the package are replaced by the generator of the first
+/// of them, which iobot runs with the test index instead of the script
+/// arguments.
+pub(super) fn read(dir: &Path) -> Result<Problem, ImportError> {
+    let xml = fs::read_to_string(dir.join("problem.xml"))
+        .map_err(|error| ImportError::Invalid(format!("cannot read problem.xml: {}", error)))?;
+    let document = Document::parse(&xml)?;
+    let root = document.root_element();
+    let testset = root
+        .descendants()
+        .filter(|node| node.has_tag_name("testset"))
+        .find(|node| node.attribute("name").is_none_or(|name| name == "tests"))
+        .ok_or_else(|| ImportError::Invalid("problem.xml has no testset".to_string()))?;
+
+    let mut problem = Problem::default();
+    let number = |name: &str| {
+        child(testset, name)
+            .and_then(|node| node.text())
+            .and_then(|text| text.trim().parse::<u64>().ok())
+    };
+    problem.options.time_limit = number("time-limit");
+    problem.options.memory_limit = number("memory-limit").map(|bytes| bytes / 1024 / 1024);
+
+    let input_pattern = child(testset, "input-path-pattern")
+        .and_then(|node| node.text())
+        .unwrap_or("tests/%02d");
+    let answer_pattern = child(testset, "answer-path-pattern")
+        .and_then(|node| node.text())
+        .unwrap_or("tests/%02d.a");
+    let tests: Vec<Node> = child(testset, "tests")
+        .map(|tests| {
+            tests
+                .children()
+                .filter(|node| node.has_tag_name("test"))
+                .collect()
+        })
+        .unwrap_or_default();
+    let width = cmp::max(2, tests.len().to_string().len());
+    let mut generator_command = None;
+    for (index, test) in tests.iter().enumerate() {
+        let input = PathBuf::from(format_pattern(input_pattern, index + 1));
+        let answer = PathBuf::from(format_pattern(answer_pattern, index + 1));
+        if !dir.join(&input).is_file() {
+            problem.missing_inputs += 1;
+            if generator_command.is_none() {
+                generator_command = test.attribute("cmd");
+            }
+            continue;
+        }
+        let name = format!("{:0width$}", index + 1, width = width);
+        problem.tests.push(ImportedTest {
+            name: match test.attribute("group") {
+                Some(group) => Path::new(group).join(name),
+                None => PathBuf::from(name),
+            },
+            input,
+            answer: dir.join(&answer).is_file().then_some(answer),
+        });
+    }
+
+    problem.checker = root
+        .descendants()
+        .find(|node| node.has_tag_name("checker"))
+        .and_then(source_path);
+    problem.model = root
+        .descendants()
+        .filter(|node| node.has_tag_name("solution"))
+        .find(|node| node.attribute("tag") == Some("main"))
+        .and_then(source_path);
+    if let Some(command) = generator_command {
+        let name = command.split_whitespace().next().unwrap_or_default();
+        problem.generator = root
+            .descendants()
+            .filter(|node| node.has_tag_name("executable"))
+            .filter_map(source_path)
+            .find(|path| path.file_stem().is_some_and(|stem| stem == name));
+        match &problem.generator {
+            Some(_) => problem.warnings.push(format!(
+                "the generator {} is run with the test index instead of the arguments of `{}`",
+                name, command
+            )),
+            None => problem
+                .warnings
+                .push(format!("cannot find the source of the generator {}", name)),
+        }
+    }
+    Ok(problem)
+}
@@ -0,0 +1,763 @@
+use crate::config::{Config, Expectation, InputRef, Program, UnknownExtensionError};
+use crate::runner::{build_all, CompileError, ProgramRole, RunError, RunMonitor, Runner};
+use crate::utils::{
+    config_extensions, list_config_files, matching_extension, natural_sort_key, run_parallel,
+    strip_extension, to_lines, with_suffix, FilesType, PoolError,
+};
+use indicatif::ProgressBar;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf, StripPrefixError};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+use std::{env, error, fmt, fs, io};
+use uuid::Uuid;
+
+/// A single test of a package.
+#[derive(Clone, Debug)]
+pub struct TestCase {
+    /// Path of the input file relative to the input directory.
+    pub name: PathBuf,
+    /// The name without the input extension, e.g. `subtask1/01.small` for
+    /// `subtask1/01.small.in`. Outputs are named after it.
+    pub stem: PathBuf,
+    pub input: PathBuf,
+    /// Expected output, if the package lists output files.
+    pub output: Option<PathBuf>,
+}
+
+impl TestCase {
+    /// The first directory of the test name, e.g. `subtask1` for
+    /// `subtask1/3.in`. Tests directly in the input directory have no group.
+    pub fn group(&self) -> Option<String> {
+        let mut components = self.name.components();
+        let first = components.next()?;
+        components.next()?;
+        Some(first.as_os_str().to_string_lossy().into_owned())
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Verdict {
+    Accepted,
+    /// The verifier gave the output a score between 0 and 1.
+    PartiallyAccepted,
+    WrongAnswer,
+    /// The solution exited with a non-zero exit code, `None` if it was killed
+    /// by a signal.
+    RuntimeError(Option<i32>),
+    TimeLimitExceeded,
+    /// The test was not run after an earlier failure, see [`FailFast`].
+    Skipped,
+}
+
+impl Verdict {
+    pub fn is_accepted(&self) -> bool {
+        *self == Verdict::Accepted
+    }
+}
+
+impl fmt::Display for Verdict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Verdict::Accepted => write!(f, "accepted"),
+            Verdict::PartiallyAccepted => write!(f, "partially accepted"),
+            Verdict::WrongAnswer => write!(f, "wrong answer"),
+            Verdict::RuntimeError(Some(code)) => write!(f, "runtime error (exit code {})", code),
+            Verdict::RuntimeError(None) => write!(f, "runtime error (killed by a signal)"),
+            Verdict::TimeLimitExceeded => write!(f, "time limit exceeded"),
+            Verdict::Skipped => write!(f, "skipped"),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct TestResult {
+    pub verdict: Verdict,
+    /// Fraction of the points of the test, between 0 and 1.
+    pub score: f64,
+    /// Wall time of the solution run.
+    pub time: Duration,
+    /// Peak memory of the solution run in KiB, if it could be measured.
+    pub memory: Option<u64>,
+    /// Output of the solution, kept only if enabled with
+    /// [`Judge::with_outputs_kept`] and the solution finished.
+    pub outputs: Option<TestOutputs>,
+}
+
+impl TestResult {
+    fn skipped() -> TestResult {
+        TestResult {
+            verdict: Verdict::Skipped,
+            score: 0.0,
+            time: Duration::ZERO,
+            memory: None,
+            outputs: None,
+        }
+    }
+}
+
+/// Which tests are skipped after a test fails in [`Judge::judge_all`]. Any
+/// verdict other than accepted is a failure, including
+/// [`Verdict::PartiallyAccepted`] for a test with part of the points.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FailFast {
+    /// All tests are run.
+    #[default]
+    Never,
+    /// The remaining tests of the group of the failed test.
+    Group,
+    /// All remaining tests.
+    Run,
+}
+
+/// Failures seen by [`Judge::judge_all`], to skip tests with [`FailFast`].
+#[derive(Default)]
+struct Failures {
+    run: AtomicBool,
+    groups: Mutex<HashSet<String>>,
+}
+
+impl Failures {
+    fn lock_groups(&self) -> MutexGuard<'_, HashSet<String>> {
+        self.groups
+            .lock()
+            .unwrap_or_else(|error| error.into_inner())
+    }
+
+    fn add(&self, test: &TestCase) {
+        self.run.store(true, Ordering::SeqCst);
+        if let Some(group) = test.group() {
+            self.lock_groups().insert(group);
+        }
+    }
+
+    fn skips(&self, fail_fast: FailFast, test: &TestCase) -> bool {
+        match fail_fast {
+            FailFast::Never => false,
+            FailFast::Group => test
+                .group()
+                .is_some_and(|group| self.lock_groups().contains(&group)),
+            FailFast::Run => self.run.load(Ordering::SeqCst),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct TestOutputs {
+    pub output: Vec<u8>,
+    /// Expected output, `None` for packages with just a verifier.
+    pub expected: Option<Vec<u8>>,
+}
+
+/// Receives the progress of [`Judge::judge_all`] from its worker threads.
+pub trait JudgeProgress: Send + Sync {
+    /// Called when the solution starts running on the test with `index`, the
+    /// run can be followed through `monitor`.
+    fn started(&self, _index: usize, _monitor: &Arc<RunMonitor>) {}
+
+    fn finished(&self, index: usize, result: &TestResult);
+
+    /// Whether the run was cancelled, the tests that have not started yet are
+    /// then skipped.
+    fn cancelled(&self) -> bool {
+        false
+    }
+}
+
+impl JudgeProgress for ProgressBar {
+    fn finished(&self, _index: usize, _result: &TestResult) {
+        self.inc(1);
+    }
+}
+
+#[derive(Debug)]
+pub enum JudgeError {
+    IO(io::Error),
+    /// The package inputs come from a generator and have to be generated first.
+    NotGenerated,
+    MissingOutput(PathBuf),
+    StripPrefix(StripPrefixError),
+    UnknownExtension(UnknownExtensionError),
+    Compile(ProgramRole, CompileError),
+    Model(RunError),
+    Verifier(RunError),
+    Solution(RunError),
+    /// A worker thread panicked.
+    Panicked,
+}
+
+impl fmt::Display for JudgeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            JudgeError::IO(error) => write!(f, "cannot read the package: {}", error),
+            JudgeError::NotGenerated => {
+                write!(
+                    f,
+                    "package inputs are not generated, run `iobot generate` first"
+                )
+            }
+            JudgeError::MissingOutput(input) => {
+                write!(f, "no output file for input {}", input.display())
+            }
+            JudgeError::StripPrefix(error) => {
+                write!(f, "input file outside of the input directory: {}", error)
+            }
+            JudgeError::UnknownExtension(error) => write!(f, "{}", error),
+            JudgeError::Compile(role, error) => write!(f, "cannot build the {}: {}", role, error),
+            JudgeError::Model(error) => write!(f, "model program failed: {}", error),
+            JudgeError::Verifier(error) => write!(f, "verifier failed: {}", error),
+            JudgeError::Solution(error) => write!(f, "cannot run the solution: {}", error),
+            JudgeError::Panicked => write!(f, "judging thread panicked"),
+        }
+    }
+}
+
+impl error::Error for JudgeError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            JudgeError::IO(error) => Some(error),
+            JudgeError::StripPrefix(error) => Some(error),
+            JudgeError::UnknownExtension(error) => Some(error),
+            JudgeError::Compile(_, error) => Some(error),
+            JudgeError::Model(error) => Some(error),
+            JudgeError::Verifier(error) => Some(error),
+            JudgeError::Solution(error) => Some(error),
+            JudgeError::NotGenerated | JudgeError::MissingOutput(_) | JudgeError::Panicked => None,
+        }
+    }
+}
+
+impl From<io::Error> for JudgeError {
+    fn from(error: io::Error) -> Self {
+        JudgeError::IO(error)
+    }
+}
+
+impl From<StripPrefixError> for JudgeError {
+    fn from(error: StripPrefixError) -> Self {
+        JudgeError::StripPrefix(error)
+    }
+}
+
+impl From<UnknownExtensionError> for JudgeError {
+    fn from(error: UnknownExtensionError) -> Self {
+        JudgeError::UnknownExtension(error)
+    }
+}
+
+/// Checks solutions against the tests of a package.
+///
+/// The expected output of a test comes from the package output files or, for
+/// configs with a model program, from running the model program. If the
+/// package has a verifier, it decides whether an output is accepted: it is
+/// called with the input path, the solution output path and, if present, the
+/// expected output path as arguments and accepts with exit code 0. An accepting
+/// verifier may print a score between 0 and 1 as its only output for partial
+/// scoring. Without a verifier the outputs have to be equal up to trailing
+/// whitespace.
+pub struct Judge {
+    tests: Vec<TestCase>,
+    model: Option<Runner>,
+    verifier: Option<Runner>,
+    time_limit: Option<Duration>,
+    keep_outputs: bool,
+    fail_fast: FailFast,
+}
+
+fn find_output(
+    test: &TestCase,
+    output_dir: &Path,
+    extensions: &[String],
+) -> Result<PathBuf, JudgeError> {
+    extensions
+        .iter()
+        .map(|ext| output_dir.join(with_suffix(&test.stem, ext)))
+        .find(|path| path.is_file())
+        .ok_or_else(|| JudgeError::MissingOutput(test.name.clone()))
+}
+
+/// Lists the tests of the package in `package`, sorted by input path with
+/// numbers compared by value, see [`natural_sort_key`].
+pub fn list_tests(config: &Config, package: &Path) -> Result<Vec<TestCase>, JudgeError> {
+    let input = match config.get_input() {
+        InputRef::Files(files) => files,
+        InputRef::Generator(_) | InputRef::Tests(_) | InputRef::Script(_) => {
+            return Err(JudgeError::NotGenerated)
+        }
+    };
+    let input_dir = input.dir(package)?;
+    let extensions = config_extensions(input, FilesType::Input);
+    let mut inputs = list_config_files(input, package, FilesType::Input)?;
+    inputs.sort_by_cached_key(|input| natural_sort_key(input));
+    let mut tests = vec![];
+    for input in inputs {
+        let name = input.strip_prefix(&input_dir)?.to_path_buf();
+        let ext = matching_extension(&name, &extensions).unwrap_or_default();
+        let mut test = TestCase {
+            stem: strip_extension(&name, ext),
+            name,
+            input,
+            output: None,
+        };
+        if let Config::OutputFiles(config) = config {
+            test.output = Some(find_output(
+                &test,
+                &config.output_files.dir(package)?,
+                &config_extensions(&config.output_files, FilesType::Output),
+            )?);
+        }
+        tests.push(test);
+    }
+    Ok(tests)
+}
+
+impl Judge {
+    /// Programs needed to judge the package of `config`, to be built with
+    /// [`build_all`] and passed to [`Judge::new`].
+    pub fn programs(config: &Config) -> Result<Vec<(ProgramRole, Program)>, UnknownExtensionError> {
+        let mut programs = vec![];
+        if let Config::ModelProgram(config) = config {
+            programs.push((
+                ProgramRole::ModelProgram,
+                config.model_program.to_program()?,
+            ));
+        }
+        if let Some(verifier) = config.verifier() {
+            programs.push((ProgramRole::Verifier, verifier.to_program()?));
+        }
+        Ok(programs)
+    }
+
+    /// Creates a judge using the model program and verifier from `runners`.
+    /// Runners with other roles are ignored.
+    pub fn new(tests: Vec<TestCase>, runners: Vec<(ProgramRole, Runner)>) -> Judge {
+        let mut model = None;
+        let mut verifier = None;
+        for (role, runner) in runners {
+            match role {
+                ProgramRole::ModelProgram => model = Some(runner),
+                ProgramRole::Verifier => verifier = Some(runner),
+                _ => {}
+            }
+        }
+        Judge {
+            tests,
+            model,
+            verifier,
+            time_limit: None,
+            keep_outputs: false,
+            fail_fast: FailFast::Never,
+        }
+    }
+
+    /// Sets the time limit of a solution run, exceeding it results in
+    /// [`Verdict::TimeLimitExceeded`].
+    pub fn with_time_limit(mut self, time_limit: Option<Duration>) -> Judge {
+        self.time_limit = time_limit;
+        self
+    }
+
+    /// Keeps the solution and expected outputs in [`TestResult::outputs`], to
+    /// show them after judging.
+    pub fn with_outputs_kept(mut self, keep: bool) -> Judge {
+        self.keep_outputs = keep;
+        self
+    }
+
+    /// Skips tests in [`Judge::judge_all`] after a test fails, giving them
+    /// [`Verdict::Skipped`]. Tests that already started are not stopped.
+    pub fn with_fail_fast(mut self, fail_fast: FailFast) -> Judge {
+        self.fail_fast = fail_fast;
+        self
+    }
+
+    /// Lists the tests of the package in `package` and builds its model program
+    /// and verifier. The time limit is taken from `config`.
+    pub fn load(config: &Config, package: &Path) -> Result<Judge, JudgeError> {
+        let tests = list_tests(config, package)?;
+        let programs = Judge::programs(config)?;
+        let runners = build_all(&programs, package)
+            .map_err(|(role, error)| JudgeError::Compile(role, error))?;
+        Ok(Judge::new(tests, runners).with_time_limit(config.options().time_limit()))
+    }
+
+    pub fn tests(&self) -> &[TestCase] {
+        &self.tests
+    }
+
+    fn expected_output(&self, test: &TestCase) -> Result<Option<Vec<u8>>, JudgeError> {
+        if let Some(output) = &test.output {
+            return Ok(Some(fs::read(output)?));
+        }
+        match &self.model {
+            Some(model) => Ok(Some(
+                model
+                    .run(fs::read(&test.input)?, vec![])
+                    .map_err(JudgeError::Model)?
+                    .output,
+            )),
+            None => Ok(None),
+        }
+    }
+
+    fn verify(
+        &self,
+        verifier: &Runner,
+        test: &TestCase,
+        output: &[u8],
+        expected: Option<&[u8]>,
+    ) -> Result<(Verdict, f64), JudgeError> {
+        let dir = env::temp_dir().join("iobot/judge");
+        fs::create_dir_all(&dir)?;
+        let id = Uuid::new_v4().to_string();
+        let output_path = dir.join(id.clone() + ".out");
+        let expected_path = dir.join(id + ".ans");
+        fs::write(&output_path, output)?;
+        // The verifier runs in the package directory, so the input path has
+        // to be absolute.
+        let input = test.input.canonicalize()?;
+        let mut args = vec![
+            input.to_string_lossy().into_owned(),
+            output_path.to_string_lossy().into_owned(),
+        ];
+        if let Some(expected) = expected {
+            fs::write(&expected_path, expected)?;
+            args.push(expected_path.to_string_lossy().into_owned());
+        }
+        let result = verifier.run_without_input(args);
+        let _ = fs::remove_file(&output_path);
+        let _ = fs::remove_file(&expected_path);
+        match result {
+            Ok(result) => match parse_score(&result.output) {
+                Some(score) if score >= 1.0 => Ok((Verdict::Accepted, 1.0)),
+                Some(score) if score <= 0.0 => Ok((Verdict::WrongAnswer, 0.0)),
+                Some(score) => Ok((Verdict::PartiallyAccepted, score)),
+                None => Ok((Verdict::Accepted, 1.0)),
+            },
+            Err(RunError::Unsuccessful(_)) => Ok((Verdict::WrongAnswer, 0.0)),
+            Err(error) => Err(JudgeError::Verifier(error)),
+        }
+    }
+
+    fn check(
+        &self,
+        test: &TestCase,
+        output: &[u8],
+        expected: Option<&[u8]>,
+    ) -> Result<(Verdict, f64), JudgeError> {
+        Ok(match (&self.verifier, expected) {
+            (Some(verifier), expected) => self.verify(verifier, test, output, expected)?,
+            (None, Some(expected)) if outputs_match(output, expected) => (Verdict::Accepted, 1.0),
+            (None, Some(_)) => (Verdict::WrongAnswer, 0.0),
+            // A config always has either an output source or a verifier.
+            (None, None) => (Verdict::Accepted, 1.0),
+        })
+    }
+
+    /// Checks `output` as the output of a solution on `test`, returning its
+    /// verdict and score.
+    pub fn check_output(
+        &self,
+        test: &TestCase,
+        output: &[u8],
+    ) -> Result<(Verdict, f64), JudgeError> {
+        let expected = self.expected_output(test)?;
+        self.check(test, output, expected.as_deref())
+    }
+
+    pub fn has_verifier(&self) -> bool {
+        self.verifier.is_some()
+    }
+
+    /// Runs `solution` on `test` and checks its output.
+    pub fn judge(&self, solution: &Runner, test: &TestCase) -> Result<TestResult, JudgeError> {
+        self.judge_monitored(solution, test, &RunMonitor::default())
+    }
+
+    /// Like [`Judge::judge`], updating `monitor` while the solution runs.
+    pub fn judge_monitored(
+        &self,
+        solution: &Runner,
+        test: &TestCase,
+        monitor: &RunMonitor,
+    ) -> Result<TestResult, JudgeError> {
+        let input = fs::read(&test.input)?;
+        let start = Instant::now();
+        let result = solution.run_monitored(input, vec![], self.time_limit, monitor);
+        let time = start.elapsed();
+        let memory = monitor.memory();
+        let (output, time) = match result {
+            Ok(result) => (result.output, result.time),
+            Err(RunError::Unsuccessful(code)) => {
+                return Ok(TestResult {
+                    verdict: Verdict::RuntimeError(code),
+                    score: 0.0,
+                    time,
+                    memory,
+                    outputs: None,
+                })
+            }
+            Err(RunError::TimeLimitExceeded) => {
+                return Ok(TestResult {
+                    verdict: Verdict::TimeLimitExceeded,
+                    score: 0.0,
+                    time,
+                    memory,
+                    outputs: None,
+                })
+            }
+            Err(error) => return Err(JudgeError::Solution(error)),
+        };
+        let expected = self.expected_output(test)?;
+        let (verdict, score) = self.check(test, &output, expected.as_deref())?;
+        Ok(TestResult {
+            verdict,
+            score,
+            time,
+            memory,
+            outputs: self
+                .keep_outputs
+                .then_some(TestOutputs { output, expected }),
+        })
+    }
+}
+
+/// Whether `verdicts`, the verdicts of a solution on all tests, meet the
+/// `expected` outcome. Skipped tests are ignored.
+pub fn meets_expectation(expected: Expectation, verdicts: &[Verdict]) -> bool {
+    let mut failures = verdicts
+        .iter()
+        .filter(|verdict| !verdict.is_accepted() && **verdict != Verdict::Skipped)
+        .peekable();
+    let allowed = |verdict: &Verdict| match expected {
+        Expectation::Accepted => false,
+        Expectation::WrongAnswer => {
+            matches!(verdict, Verdict::WrongAnswer | Verdict::PartiallyAccepted)
+        }
+        Expectation::TimeLimit => *verdict == Verdict::TimeLimitExceeded,
+        Expectation::RuntimeError => matches!(verdict, Verdict::RuntimeError(_)),
+        Expectation::Rejected => true,
+    };
+    match expected {
+        Expectation::Accepted => failures.peek().is_none(),
+        _ => failures.peek().is_some() && failures.all(allowed),
+    }
+}
+
+/// The score printed by a verifier, if its whole output is a number.
+fn parse_score(output: &[u8]) -> Option<f64> {
+    let score: f64 = std::str::from_utf8(output).ok()?.trim().parse().ok()?;
+    score.is_finite().then_some(score)
+}
+
+/// Compares outputs line by line, ignoring trailing whitespace. Outputs that
+/// are not valid UTF-8 have to be byte-for-byte equal.
+pub fn outputs_match(output: &[u8], expected: &[u8]) -> bool {
+    match (to_lines(output), to_lines(expected)) {
+        (Ok(output), Ok(expected)) => output == expected,
+        _ => output == expected,
+    }
+}
+
+impl Judge {
+    /// Judges `solution` on all tests on `jobs` threads and returns the results
+    /// in test order.
+    pub fn judge_all<P: JudgeProgress + Clone + 'static>(
+        self: &Arc<Self>,
+        solution: &Arc<Runner>,
+        jobs: usize,
+        progress: &P,
+    ) -> Result<Vec<TestResult>, JudgeError> {
+        let judge = self.clone();
+        let solution = solution.clone();
+        let progress = progress.clone();
+        let tests: Vec<(usize, TestCase)> = self.tests.iter().cloned().enumerate().collect();
+        let failures = Failures::default();
+        run_parallel(tests, jobs, move |(index, test)| {
+            // Tests queued after a failure or a cancellation are skipped when
+            // their turn comes.
+            let result = if progress.cancelled() || failures.skips(judge.fail_fast, &test) {
+                TestResult::skipped()
+            } else {
+                let monitor = Arc::new(RunMonitor::default());
+                progress.started(index, &monitor);
+                let result = judge.judge_monitored(&solution, &test, &monitor)?;
+                if !result.verdict.is_accepted() {
+                    failures.add(&test);
+                }
+                result
+            };
+            progress.finished(index, &result);
+            Ok(result)
+        })
+        .map_err(|error| match error {
+            PoolError::Job(error) => error,
+            PoolError::Panicked => JudgeError::Panicked,
+        })
+    }
+}
+
+/// Writes tests named by their stem, like `group/1`, with their input and
+/// expected output to `dir`.
+#[cfg(test)]
+pub(crate) fn write_tests(dir: &Path, tests: &[(&str, &str, &str)]) -> Vec<TestCase> {
+    tests
+        .iter()
+        .map(|(stem, input, output)| {
+            let input_path = dir.join("in").join(format!("{}.in", stem));
+            let output_path = dir.join("out").join(format!("{}.out", stem));
+            for (path, contents) in [(&input_path, input), (&output_path, output)] {
+                fs::create_dir_all(path.parent().expect("tests are in a directory"))
+                    .expect("cannot create a test directory");
+                fs::write(path, contents).expect("cannot write a test");
+            }
+            TestCase {
+                name: PathBuf::from(format!("{}.in", stem)),
+                stem: PathBuf::from(stem),
+                input: input_path,
+                output: Some(output_path),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_dir;
+
+    /// Judges a Python solution printing `answers` by the input on one thread.
+    fn judge(judge: Judge, dir: &Path, answers: &str) -> Vec<Verdict> {
+        let path = dir.join("solution.py");
+        fs::write(
+            &path,
+            format!("answers = {}\nprint(answers[input().strip()])\n", answers),
+        )
+        .unwrap();
+        let program = Program::Python {
+            path,
+            checksum: None,
+        };
+        let solution = Arc::new(Runner::build(&program, dir).unwrap());
+        Arc::new(judge)
+            .judge_all(&solution, 1, &ProgressBar::hidden())
+            .unwrap()
+            .into_iter()
+            .map(|result| result.verdict)
+            .collect()
+    }
+
+    #[test]
+    fn fail_fast_skips_the_remaining_tests() {
+        let dir = test_dir();
+        let tests = write_tests(
+            &dir,
+            &[
+                ("1", "a\n", "1\n"),
+                ("2", "b\n", "2\n"),
+                ("3", "c\n", "3\n"),
+            ],
+        );
+        let answers = "{'a': 1, 'b': 0, 'c': 3}";
+        let run = Judge::new(tests.clone(), vec![]).with_fail_fast(FailFast::Run);
+        assert_eq!(
+            judge(run, &dir, answers),
+            [Verdict::Accepted, Verdict::WrongAnswer, Verdict::Skipped]
+        );
+        assert_eq!(
+            judge(Judge::new(tests, vec![]), &dir, answers),
+            [Verdict::Accepted, Verdict::WrongAnswer, Verdict::Accepted]
+        );
+    }
+
+    #[test]
+    fn group_fail_fast_skips_the_group() {
+        let dir = test_dir();
+        let tests = write_tests(
+            &dir,
+            &[
+                ("a/1", "a1\n", "1\n"),
+                ("a/2", "a2\n", "2\n"),
+                ("b/1", "b1\n", "1\n"),
+            ],
+        );
+        let judge_run = Judge::new(tests, vec![]).with_fail_fast(FailFast::Group);
+        assert_eq!(
+            judge(judge_run, &dir, "{'a1': 0, 'a2': 2, 'b1': 1}"),
+            [Verdict::WrongAnswer, Verdict::Skipped, Verdict::Accepted]
+        );
+    }
+
+    #[test]
+    fn partially_accepted_tests_fail_fast() {
+        let dir = test_dir();
+        let tests = write_tests(&dir, &[("1", "a\n", ""), ("2", "b\n", "")]);
+        // Half of the points for any output.
+        let verifier = dir.join("verifier.py");
+        fs::write(&verifier, "print(0.5)\n").unwrap();
+        let verifier = Runner::build(
+            &Program::Python {
+                path: verifier,
+                checksum: None,
+            },
+            &dir,
+        )
+        .unwrap();
+        let judge_run = Judge::new(tests, vec![(ProgramRole::Verifier, verifier)])
+            .with_fail_fast(FailFast::Run);
+        assert_eq!(
+            judge(judge_run, &dir, "{'a': 1, 'b': 2}"),
+            [Verdict::PartiallyAccepted, Verdict::Skipped]
+        );
+    }
+
+    #[test]
+    fn expectations_allow_their_failures() {
+        use Verdict::*;
+        let cases = [
+            (Expectation::Accepted, vec![Accepted, Skipped], true),
+            (
+                Expectation::Accepted,
+                vec![Accepted, PartiallyAccepted],
+                false,
+            ),
+            (
+                Expectation::WrongAnswer,
+                vec![Accepted, PartiallyAccepted],
+                true,
+            ),
+            (Expectation::WrongAnswer, vec![Accepted, Accepted], false),
+            (
+                Expectation::WrongAnswer,
+                vec![WrongAnswer, TimeLimitExceeded],
+                false,
+            ),
+            (
+                Expectation::TimeLimit,
+                vec![TimeLimitExceeded, Skipped],
+                true,
+            ),
+            (
+                Expectation::RuntimeError,
+                vec![RuntimeError(Some(1)), Accepted],
+                true,
+            ),
+            (
+                Expectation::Rejected,
+                vec![WrongAnswer, RuntimeError(None)],
+                true,
+            ),
+            (Expectation::Rejected, vec![Accepted], false),
+        ];
+        for (expected, verdicts, meets) in cases {
+            assert_eq!(
+                meets_expectation(expected, &verdicts),
+                meets,
+                "{} on {:?}",
+                expected,
+                verdicts
+            );
+        }
+    }
+}
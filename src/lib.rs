@@ -0,0 +1,34 @@
+//! iobot generates and judges input/output test packages.
+//!
+//! The typical flow is:
+//! 1. parse an `iobot.yaml` with [`config::Config::load`],
+//! 2. copy or generate the inputs and generate the outputs with the functions
+//!    in [`generator`],
+//! 3. build a solution with [`runner::Runner::build`] and judge it against the
+//!    package with [`judge::Judge`].
+//!
+//! All fallible functions return structured errors implementing
+//! [`std::error::Error`], which convert into the top-level [`error::Error`].
+
+pub mod archive;
+pub mod calibrate;
+pub mod check;
+pub mod config;
+pub mod error;
+pub mod export;
+pub mod generator;
+pub mod import;
+pub mod judge;
+pub mod naming;
+pub mod provenance;
+pub mod remote;
+pub mod report;
+pub mod runner;
+pub mod samples;
+pub mod score;
+pub mod script;
+pub mod server;
+pub mod stats;
+pub mod submissions;
+pub mod utils;
+pub mod watch;
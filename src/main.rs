@@ -1,10 +1,7 @@
 mod commands;
-mod config;
-mod generator;
-mod runner;
-mod utils;
 
 use clap::Parser;
+use std::process;
 
 #[derive(Parser)]
 #[clap()]
@@ -19,15 +16,37 @@ enum SubCommand {
     Init(commands::init::Params),
     #[clap()]
     Generate(commands::generate::Params),
-    // #[clap()]
-    // Test(commands::test::Params),
+    #[clap()]
+    Test(commands::test::Params),
+    #[clap()]
+    Export(commands::export::Params),
+    #[clap()]
+    Import(commands::import::Params),
+    #[clap()]
+    Calibrate(commands::calibrate::Params),
+    #[clap()]
+    Judge(commands::judge::Params),
+    #[clap()]
+    Serve(commands::serve::Params),
+    #[clap()]
+    CheckPackage(commands::check_package::Params),
 }
 
 fn main() {
     let opts: Opts = Opts::parse();
-    match opts.subcommand {
+    let result = match opts.subcommand {
         SubCommand::Init(_) => commands::init::run(),
         SubCommand::Generate(params) => commands::generate::run(params),
-        // SubCommand::Test(params) => commands::test::run(params),
+        SubCommand::Test(params) => commands::test::run(params),
+        SubCommand::Export(params) => commands::export::run(params),
+        SubCommand::Import(params) => commands::import::run(params),
+        SubCommand::Calibrate(params) => commands::calibrate::run(params),
+        SubCommand::Judge(params) => commands::judge::run(params),
+        SubCommand::Serve(params) => commands::serve::run(params),
+        SubCommand::CheckPackage(params) => commands::check_package::run(params),
+    };
+    if let Err(error) = result {
+        commands::print_error(&error);
+        process::exit(error.exit_code());
     }
 }
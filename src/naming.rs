@@ -0,0 +1,329 @@
+//! Names of generated tests, from a template like `{group}/{index:02}-max`.
+//!
+//! Placeholders are `{index}`, the index of the test among all tests and the
+//! generator argument, `{group}`, the name of its group, and `{group_index}`,
+//! its index inside the group. Indices start at 0 and can be padded with
+//! zeros to a width, e.g. `{index:03}`. The input extension is added to the
+//! name.
+//!
+//! Tests listed in the config are named explicitly or by their list index.
+
+use crate::config::{GeneratorInput, TestList};
+use std::collections::HashSet;
+use std::path::{Component, PathBuf};
+use std::{error, fmt};
+
+/// Number of generated tests without groups.
+pub const DEFAULT_COUNT: u64 = 100;
+
+#[derive(Debug)]
+pub enum NamingError {
+    /// A `{` without a matching `}` or the other way around.
+    UnmatchedBrace(String),
+    UnknownPlaceholder(String),
+    /// `{group}` or `{group_index}` in a template without groups.
+    NoGroups(String),
+    /// A name that is empty, absolute or goes outside of the input directory.
+    InvalidName(String),
+    DuplicateName(PathBuf),
+}
+
+impl fmt::Display for NamingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NamingError::UnmatchedBrace(template) => {
+                write!(f, "unmatched brace in test name template {}", template)
+            }
+            NamingError::UnknownPlaceholder(placeholder) => write!(
+                f,
+                "unknown placeholder {{{}}} in test name template, expected index, group or group_index",
+                placeholder
+            ),
+            NamingError::NoGroups(template) => write!(
+                f,
+                "test name template {} uses the group, but the generator has no groups",
+                template
+            ),
+            NamingError::InvalidName(name) => write!(
+                f,
+                "invalid test name {}, names have to be relative paths inside the input directory",
+                name
+            ),
+            NamingError::DuplicateName(name) => {
+                write!(f, "several tests are named {}", name.display())
+            }
+        }
+    }
+}
+
+impl error::Error for NamingError {}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Value {
+    Index,
+    Group,
+    GroupIndex,
+}
+
+#[derive(Clone, Debug)]
+enum Part {
+    Text(String),
+    Placeholder { value: Value, width: usize },
+}
+
+/// A parsed test name template.
+#[derive(Clone, Debug)]
+pub struct NameTemplate {
+    template: String,
+    parts: Vec<Part>,
+}
+
+impl NameTemplate {
+    pub fn parse(template: &str) -> Result<NameTemplate, NamingError> {
+        let unmatched = || NamingError::UnmatchedBrace(template.to_string());
+        let mut parts = vec![];
+        let mut rest = template;
+        while !rest.is_empty() {
+            let start = match rest.find(['{', '}']) {
+                Some(start) if rest[start..].starts_with('}') => return Err(unmatched()),
+                Some(start) => start,
+                None => rest.len(),
+            };
+            if start > 0 {
+                parts.push(Part::Text(rest[..start].to_string()));
+            }
+            rest = &rest[start..];
+            if rest.is_empty() {
+                break;
+            }
+            let end = rest.find('}').ok_or_else(unmatched)?;
+            let placeholder = &rest[1..end];
+            if placeholder.contains('{') {
+                return Err(unmatched());
+            }
+            let (name, width) = match placeholder.split_once(':') {
+                Some((name, width)) => match width.parse::<usize>() {
+                    Ok(width) => (name, width),
+                    Err(_) => return Err(NamingError::UnknownPlaceholder(placeholder.to_string())),
+                },
+                None => (placeholder, 0),
+            };
+            let value = match name {
+                "index" => Value::Index,
+                "group" => Value::Group,
+                "group_index" => Value::GroupIndex,
+                _ => return Err(NamingError::UnknownPlaceholder(placeholder.to_string())),
+            };
+            parts.push(Part::Placeholder { value, width });
+            rest = &rest[end + 1..];
+        }
+        Ok(NameTemplate {
+            template: template.to_string(),
+            parts,
+        })
+    }
+
+    fn uses_group(&self) -> bool {
+        self.parts.iter().any(|part| {
+            matches!(
+                part,
+                Part::Placeholder {
+                    value: Value::Group | Value::GroupIndex,
+                    ..
+                }
+            )
+        })
+    }
+
+    fn render(&self, test: &GeneratedTest, group_index: u64) -> Result<PathBuf, NamingError> {
+        let mut name = String::new();
+        for part in &self.parts {
+            match part {
+                Part::Text(text) => name.push_str(text),
+                Part::Placeholder { value, width } => {
+                    let number = match value {
+                        Value::Index => test.index,
+                        Value::GroupIndex => group_index,
+                        Value::Group => {
+                            name.push_str(test.group.as_deref().unwrap_or_default());
+                            continue;
+                        }
+                    };
+                    name.push_str(&format!("{:0width$}", number, width = width));
+                }
+            }
+        }
+        check_name(&name)
+    }
+}
+
+/// `name` as a path, if it is a valid test name.
+pub(crate) fn check_name(name: &str) -> Result<PathBuf, NamingError> {
+    let path = PathBuf::from(name);
+    let valid = path
+        .components()
+        .all(|component| matches!(component, Component::Normal(_)));
+    if name.is_empty() || !valid || name.ends_with(['/', '\\']) {
+        return Err(NamingError::InvalidName(name.to_string()));
+    }
+    Ok(path)
+}
+
+/// A test to generate.
+#[derive(Clone, Debug)]
+pub struct GeneratedTest {
+    /// Index of the test among all tests, passed to the generator.
+    pub index: u64,
+    pub group: Option<String>,
+    /// Name of the input file relative to the input directory, without the
+    /// extension.
+    pub name: PathBuf,
+}
+
+/// The tests of `input` with their names, in index order. Without a template,
+/// tests are named `{index}`, or `{group}/{index}` with groups.
+pub fn plan_tests(input: &GeneratorInput) -> Result<Vec<GeneratedTest>, NamingError> {
+    let template = match &input.name {
+        Some(template) => NameTemplate::parse(template)?,
+        None if input.groups.is_empty() => NameTemplate::parse("{index}")?,
+        None => NameTemplate::parse("{group}/{index}")?,
+    };
+    if input.groups.is_empty() && template.uses_group() {
+        return Err(NamingError::NoGroups(template.template));
+    }
+    let mut groups: Vec<(Option<&str>, u64)> = input
+        .groups
+        .iter()
+        .map(|group| (Some(group.name.as_str()), group.count))
+        .collect();
+    if groups.is_empty() {
+        groups.push((None, input.count.unwrap_or(DEFAULT_COUNT)));
+    }
+    let mut tests = vec![];
+    let mut names = HashSet::new();
+    for (group, count) in groups {
+        for group_index in 0..count {
+            let mut test = GeneratedTest {
+                index: tests.len() as u64,
+                group: group.map(str::to_string),
+                name: PathBuf::new(),
+            };
+            test.name = template.render(&test, group_index)?;
+            if !names.insert(test.name.clone()) {
+                return Err(NamingError::DuplicateName(test.name));
+            }
+            tests.push(test);
+        }
+    }
+    Ok(tests)
+}
+
+/// Names of the tests in `list`, in list order. Tests without a name are
+/// named by their index in the list.
+pub fn listed_test_names(list: &TestList) -> Result<Vec<PathBuf>, NamingError> {
+    let mut names = vec![];
+    let mut unique = HashSet::new();
+    for (index, test) in list.tests.iter().enumerate() {
+        let name = match &test.name {
+            Some(name) => check_name(name)?,
+            None => PathBuf::from(index.to_string()),
+        };
+        if !unique.insert(name.clone()) {
+            return Err(NamingError::DuplicateName(name));
+        }
+        names.push(name);
+    }
+    Ok(names)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn input(yaml: &str) -> GeneratorInput {
+        serde_yaml::from_str(&format!("program: gen.py\n{}", yaml)).unwrap()
+    }
+
+    fn names(yaml: &str) -> Vec<String> {
+        plan_tests(&input(yaml))
+            .unwrap()
+            .iter()
+            .map(|test| test.name.to_string_lossy().into_owned())
+            .collect()
+    }
+
+    #[test]
+    fn default_names_are_indices() {
+        assert_eq!(names("count: 3"), ["0", "1", "2"]);
+        assert_eq!(plan_tests(&input("")).unwrap().len() as u64, DEFAULT_COUNT);
+    }
+
+    #[test]
+    fn placeholders_are_padded() {
+        assert_eq!(
+            names("count: 2\nname: \"test{index:03}\""),
+            ["test000", "test001"]
+        );
+    }
+
+    #[test]
+    fn grouped_tests_count_indices_across_groups() {
+        let yaml = "name: \"{group}/{group_index:02}-{index}\"\n\
+                    groups:\n  - name: small\n    count: 2\n  - name: big\n    count: 1";
+        assert_eq!(names(yaml), ["small/00-0", "small/01-1", "big/00-2"]);
+        let tests = plan_tests(&input(yaml)).unwrap();
+        assert_eq!(tests[2].group.as_deref(), Some("big"));
+        assert_eq!(tests[2].index, 2);
+    }
+
+    #[test]
+    fn malformed_templates_are_rejected() {
+        for template in ["{index", "index}", "{{index}}", "a}{index}"] {
+            assert!(
+                matches!(
+                    NameTemplate::parse(template),
+                    Err(NamingError::UnmatchedBrace(_))
+                ),
+                "{}",
+                template
+            );
+        }
+        for template in ["{count}", "{index:x}", "{index:}", "{}"] {
+            assert!(
+                matches!(
+                    NameTemplate::parse(template),
+                    Err(NamingError::UnknownPlaceholder(_))
+                ),
+                "{}",
+                template
+            );
+        }
+    }
+
+    #[test]
+    fn group_placeholder_needs_groups() {
+        assert!(matches!(
+            plan_tests(&input("count: 2\nname: \"{group}/{index}\"")),
+            Err(NamingError::NoGroups(_))
+        ));
+    }
+
+    #[test]
+    fn names_must_be_unique_and_relative() {
+        assert!(matches!(
+            plan_tests(&input("count: 2\nname: same")),
+            Err(NamingError::DuplicateName(_))
+        ));
+        for name in ["", "/abs", "../up", "a/../b", "dir/"] {
+            assert!(
+                matches!(
+                    plan_tests(&input(&format!("count: 1\nname: \"{}\"", name))),
+                    Err(NamingError::InvalidName(_))
+                ),
+                "{}",
+                name
+            );
+        }
+        assert_eq!(names("count: 1\nname: edge/max"), ["edge/max"]);
+    }
+}
@@ -0,0 +1,207 @@
+//! Provenance of a generated package, written next to its `iobot.yaml` so
+//! that the package can be audited or generated again: the iobot version, the
+//! source config, the programs with their source hashes and command lines and
+//! the generator runs that wrote the inputs.
+
+use crate::config::{Program, CONFIG_FILE_NAME};
+use crate::generator::GeneratorRun;
+use crate::remote::{self, sha256_hex};
+use crate::runner::{command_lines, ProgramRole};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Name of the provenance file in a generated package.
+pub const PROVENANCE_FILE_NAME: &str = "provenance.yaml";
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Provenance {
+    pub iobot_version: String,
+    /// UTC time in RFC 3339 format.
+    pub generated_at: String,
+    /// Absolute path of the source directory.
+    pub source: PathBuf,
+    /// SHA-256 hash of the source `iobot.yaml`.
+    pub config_sha256: String,
+    pub programs: Vec<ProgramProvenance>,
+    /// Generator runs that wrote the inputs, empty for copied inputs.
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub generator_runs: Vec<GeneratorRun>,
+}
+
+/// A program used for generating, with paths relative to the source directory.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProgramProvenance {
+    pub role: String,
+    /// Name of the generator in a script.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    pub program: Program,
+    /// SHA-256 hash of the source file, if the program has one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+    /// Compile command line, `{target}` standing for the compiled program.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub compile: Option<String>,
+    pub run: String,
+}
+
+impl ProgramProvenance {
+    /// Describes `program` of the config in `source`, hashing its source file.
+    pub fn new(
+        role: ProgramRole,
+        name: Option<String>,
+        program: &Program,
+        source: &Path,
+    ) -> io::Result<ProgramProvenance> {
+        // Sources at URLs are hashed in the cache they were fetched to when
+        // the program was built.
+        let sha256 = match program {
+            Program::GPP { path, checksum, .. } | Program::Python { path, checksum } => {
+                let local = remote::local_path(path, checksum.as_deref())?;
+                Some(sha256_hex(&fs::read(source.join(local))?))
+            }
+            Program::Command { .. } | Program::Compiled { .. } => None,
+        };
+        let (compile, run) = command_lines(program);
+        Ok(ProgramProvenance {
+            role: role.to_string(),
+            name,
+            program: program.clone(),
+            sha256,
+            compile,
+            run,
+        })
+    }
+}
+
+impl Provenance {
+    /// Provenance of a package generated now from the config in `source`.
+    pub fn new(
+        source: &Path,
+        programs: Vec<ProgramProvenance>,
+        generator_runs: Vec<GeneratorRun>,
+    ) -> io::Result<Provenance> {
+        Ok(Provenance {
+            iobot_version: env!("CARGO_PKG_VERSION").to_string(),
+            generated_at: format_utc(SystemTime::now()),
+            source: source.canonicalize()?,
+            config_sha256: sha256_hex(&fs::read(source.join(CONFIG_FILE_NAME))?),
+            programs,
+            generator_runs,
+        })
+    }
+
+    /// Writes the provenance to the `provenance.yaml` file in `dir`.
+    pub fn save(&self, dir: &Path) -> io::Result<()> {
+        let yaml = serde_yaml::to_string(self)
+            .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))?;
+        fs::write(dir.join(PROVENANCE_FILE_NAME), yaml)
+    }
+}
+
+/// Formats `time` as `YYYY-MM-DDTHH:MM:SSZ`.
+fn format_utc(time: SystemTime) -> String {
+    let seconds = time
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs());
+    let (days, seconds) = (seconds / 86400, seconds % 86400);
+    // Civil date from days since 1970-01-01, see
+    // http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let days = days as i64 + 719_468;
+    let era = days / 146_097;
+    let day_of_era = days - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Command;
+    use crate::utils::test_dir;
+    use std::time::Duration;
+
+    /// SHA-256 hash of `abc`.
+    const ABC_SHA256: &str = "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad";
+
+    fn utc(seconds: u64) -> String {
+        format_utc(UNIX_EPOCH + Duration::from_secs(seconds))
+    }
+
+    #[test]
+    fn format_utc_formats_dates() {
+        assert_eq!(utc(0), "1970-01-01T00:00:00Z");
+        assert_eq!(utc(951_868_800), "2000-03-01T00:00:00Z");
+        assert_eq!(utc(1_709_164_799), "2024-02-28T23:59:59Z");
+        assert_eq!(utc(1_709_210_096), "2024-02-29T12:34:56Z");
+        assert_eq!(utc(1_709_251_200), "2024-03-01T00:00:00Z");
+    }
+
+    #[test]
+    fn program_provenance_hashes_the_source() {
+        let dir = test_dir();
+        fs::create_dir(dir.join("gen")).unwrap();
+        fs::write(dir.join("gen/gen.py"), "abc").unwrap();
+        let program = Program::Python {
+            path: PathBuf::from("gen/gen.py"),
+            checksum: None,
+        };
+        let provenance = ProgramProvenance::new(
+            ProgramRole::Generator,
+            Some("gen".to_string()),
+            &program,
+            &dir,
+        )
+        .unwrap();
+        assert_eq!(provenance.role, "generator");
+        assert_eq!(provenance.name.as_deref(), Some("gen"));
+        assert_eq!(provenance.sha256.as_deref(), Some(ABC_SHA256));
+        assert_eq!(provenance.compile, None);
+    }
+
+    #[test]
+    fn program_provenance_has_no_hash_without_a_source() {
+        let program = Program::Command {
+            run: Command {
+                command: "cat".to_string(),
+                args: None,
+            },
+        };
+        let provenance =
+            ProgramProvenance::new(ProgramRole::ModelProgram, None, &program, &test_dir()).unwrap();
+        assert_eq!(provenance.sha256, None);
+        assert_eq!(provenance.run, "cat");
+    }
+
+    #[test]
+    fn provenance_hashes_the_config() {
+        let dir = test_dir();
+        fs::write(dir.join(CONFIG_FILE_NAME), "abc").unwrap();
+        let provenance = Provenance::new(&dir, vec![], vec![]).unwrap();
+        assert_eq!(provenance.config_sha256, ABC_SHA256);
+        assert_eq!(provenance.source, dir.canonicalize().unwrap());
+    }
+}
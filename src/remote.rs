@@ -0,0 +1,334 @@
+//! Fetching input files and programs from `http(s)://` URLs.
+//!
+//! Fetched files are stored in a content-addressed cache, named by their
+//! SHA-256 hash, and reused without network access: with a checksum the file
+//! is looked up by it, without one by the hash recorded on the first fetch of
+//! the URL. A URL without a checksum is therefore never fetched again, even if
+//! its content changes, until the cache directory is removed.
+
+use crate::archive::{self, ArchiveFormat};
+use sha2::{Digest, Sha256};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::{env, error, fmt, fs, io};
+use uuid::Uuid;
+
+#[derive(Debug)]
+pub enum FetchError {
+    IO(io::Error),
+    Http {
+        url: String,
+        error: Box<ureq::Error>,
+    },
+    ChecksumMismatch {
+        url: String,
+        expected: String,
+        actual: String,
+    },
+    InvalidChecksum(String),
+    /// Remote files must be an archive, as directories cannot be fetched.
+    NotArchive(String),
+}
+
+impl fmt::Display for FetchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FetchError::IO(error) => write!(f, "cannot cache a fetched file: {}", error),
+            // The error of ureq starts with the URL.
+            FetchError::Http { error, .. } => write!(f, "cannot fetch {}", error),
+            FetchError::ChecksumMismatch {
+                url,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "checksum of {} does not match, expected sha256 {} but got {}",
+                url, expected, actual
+            ),
+            FetchError::InvalidChecksum(checksum) => write!(
+                f,
+                "invalid checksum {}, expected a hex SHA-256 hash optionally prefixed with sha256:",
+                checksum
+            ),
+            FetchError::NotArchive(url) => {
+                write!(f, "remote files {} must be a .zip or .tar.gz archive", url)
+            }
+        }
+    }
+}
+
+impl error::Error for FetchError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            FetchError::IO(error) => Some(error),
+            FetchError::Http { error, .. } => Some(error),
+            FetchError::ChecksumMismatch { .. }
+            | FetchError::InvalidChecksum(_)
+            | FetchError::NotArchive(_) => None,
+        }
+    }
+}
+
+impl From<io::Error> for FetchError {
+    fn from(error: io::Error) -> Self {
+        FetchError::IO(error)
+    }
+}
+
+impl From<FetchError> for io::Error {
+    fn from(error: FetchError) -> Self {
+        match error {
+            FetchError::IO(error) => error,
+            error => io::Error::other(error),
+        }
+    }
+}
+
+pub fn is_url(path: &Path) -> bool {
+    path.to_str()
+        .is_some_and(|path| path.starts_with("http://") || path.starts_with("https://"))
+}
+
+/// Cache directory, `IOBOT_CACHE_DIR` if set, otherwise `iobot` in the user
+/// cache directory.
+pub fn cache_dir() -> PathBuf {
+    if let Some(dir) = env::var_os("IOBOT_CACHE_DIR") {
+        return PathBuf::from(dir);
+    }
+    if let Some(dir) = env::var_os("XDG_CACHE_HOME") {
+        return PathBuf::from(dir).join("iobot");
+    }
+    match env::var_os("HOME") {
+        Some(home) => PathBuf::from(home).join(".cache/iobot"),
+        None => env::temp_dir().join("iobot/cache"),
+    }
+}
+
+pub(crate) fn sha256_hex(bytes: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(bytes))
+}
+
+/// Normalizes `sha256:<hex>` and `<hex>` checksums to lowercase hex.
+fn parse_checksum(checksum: &str) -> Result<String, FetchError> {
+    let hex = checksum.strip_prefix("sha256:").unwrap_or(checksum);
+    if hex.len() != 64 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(FetchError::InvalidChecksum(checksum.to_string()));
+    }
+    Ok(hex.to_ascii_lowercase())
+}
+
+/// Last path segment of `url`, kept as the name of the cached file so that
+/// its extension is preserved. URLs without a usable segment, like ones ending
+/// in `/` or `/..`, are named by their hash.
+fn file_name(url: &str) -> String {
+    let path = url.split(['?', '#']).next().unwrap_or(url);
+    match path.rsplit('/').next() {
+        Some(name) if !matches!(name, "" | "." | "..") && !name.contains('\\') => name.to_string(),
+        _ => sha256_hex(url.as_bytes()),
+    }
+}
+
+/// Downloads `url` to the cache, or returns the cached file if it was fetched
+/// before. The content is verified against `checksum` if given, without one
+/// the cached file is returned even if the content at `url` changed.
+pub fn fetch(url: &str, checksum: Option<&str>) -> Result<PathBuf, FetchError> {
+    fetch_to(&cache_dir(), url, checksum)
+}
+
+fn fetch_to(cache: &Path, url: &str, checksum: Option<&str>) -> Result<PathBuf, FetchError> {
+    let expected = checksum.map(parse_checksum).transpose()?;
+    let index = cache.join("urls").join(sha256_hex(url.as_bytes()));
+    let blob = |hash: &str| cache.join("sha256").join(hash).join(file_name(url));
+    let known = match &expected {
+        Some(expected) => Some(expected.clone()),
+        None => fs::read_to_string(&index)
+            .ok()
+            .map(|hash| hash.trim().to_string()),
+    };
+    if let Some(path) = known.map(|hash| blob(&hash)).filter(|path| path.is_file()) {
+        return Ok(path);
+    }
+
+    let response = ureq::get(url).call().map_err(|error| FetchError::Http {
+        url: url.to_string(),
+        error: Box::new(error),
+    })?;
+    let mut bytes = vec![];
+    response.into_reader().read_to_end(&mut bytes)?;
+    let actual = sha256_hex(&bytes);
+    if let Some(expected) = expected {
+        if expected != actual {
+            return Err(FetchError::ChecksumMismatch {
+                url: url.to_string(),
+                expected,
+                actual,
+            });
+        }
+    }
+    let path = blob(&actual);
+    write_atomically(&path, &bytes)?;
+    write_atomically(&index, actual.as_bytes())?;
+    Ok(path)
+}
+
+/// Writes to a temporary file first, so that concurrent or interrupted
+/// fetches never leave a partial file in the cache.
+fn write_atomically(path: &Path, contents: &[u8]) -> io::Result<()> {
+    let parent = path.parent().expect("cache files are in a directory");
+    fs::create_dir_all(parent)?;
+    let partial = parent.join(format!(".{}", Uuid::new_v4()));
+    fs::write(&partial, contents)?;
+    fs::rename(&partial, path)
+}
+
+/// The local path of a program source, fetched first if it is a URL.
+pub fn local_path(path: &Path, checksum: Option<&str>) -> Result<PathBuf, FetchError> {
+    match path.to_str() {
+        Some(url) if is_url(path) => fetch(url, checksum),
+        _ => Ok(path.to_path_buf()),
+    }
+}
+
+/// Splits a URL like `https://example.com/tests.zip/in` into the URL of the
+/// archive and the path inside of it.
+fn split_archive_url(url: &str) -> Option<(&str, &str)> {
+    let mut end = 0;
+    for segment in url.split('/') {
+        end += segment.len();
+        if ArchiveFormat::from_path(Path::new(segment)).is_some() {
+            return Some((&url[..end], url[end..].trim_start_matches('/')));
+        }
+        end += 1;
+    }
+    None
+}
+
+/// Local directory with the files at `url`, which must point at an archive or
+/// a directory inside of one.
+pub fn fetch_files(url: &str, checksum: Option<&str>) -> Result<PathBuf, FetchError> {
+    let (archive_url, inner) =
+        split_archive_url(url).ok_or_else(|| FetchError::NotArchive(url.to_string()))?;
+    let archive = fetch(archive_url, checksum)?;
+    Ok(archive::extract(&archive)?.join(inner))
+}
+
+/// Relative path the files at `url` are copied to, named after the archive
+/// without its extension.
+pub fn unpacked_path(url: &str) -> Option<PathBuf> {
+    let (archive_url, inner) = split_archive_url(url)?;
+    let dir = archive::unpacked_path(Path::new(&file_name(archive_url)));
+    Some(if inner.is_empty() {
+        dir
+    } else {
+        dir.join(inner)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_dir;
+    use std::io::Write;
+    use std::net::TcpListener;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::thread;
+
+    /// Serves `body` at every path, returns the base URL and the number of
+    /// requests served.
+    fn serve(body: &'static str) -> (String, Arc<AtomicUsize>) {
+        let server = tiny_http::Server::http("127.0.0.1:0").expect("cannot start a server");
+        let url = format!("http://{}", server.server_addr());
+        let requests = Arc::new(AtomicUsize::new(0));
+        let served = requests.clone();
+        thread::spawn(move || {
+            for request in server.incoming_requests() {
+                served.fetch_add(1, Ordering::SeqCst);
+                let _ = request.respond(tiny_http::Response::from_string(body));
+            }
+        });
+        (url, requests)
+    }
+
+    fn cached_files(cache: &Path) -> Vec<PathBuf> {
+        fn walk(dir: &Path, files: &mut Vec<PathBuf>) {
+            for entry in fs::read_dir(dir).into_iter().flatten().flatten() {
+                if entry.path().is_dir() {
+                    walk(&entry.path(), files);
+                } else {
+                    files.push(entry.path());
+                }
+            }
+        }
+        let mut files = vec![];
+        walk(cache, &mut files);
+        files
+    }
+
+    #[test]
+    fn fetch_reuses_the_cached_file() {
+        let cache = test_dir();
+        let (url, requests) = serve("1 2\n");
+        let url = format!("{}/tests/input.txt", url);
+        let first = fetch_to(&cache, &url, None).unwrap();
+        assert_eq!(fs::read_to_string(&first).unwrap(), "1 2\n");
+        assert_eq!(first.file_name().unwrap(), "input.txt");
+        let second = fetch_to(&cache, &url, None).unwrap();
+        let checksum = sha256_hex(b"1 2\n");
+        let third = fetch_to(&cache, &url, Some(&format!("sha256:{}", checksum))).unwrap();
+        assert_eq!(first, second);
+        assert_eq!(first, third);
+        assert_eq!(requests.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn fetch_rejects_a_checksum_mismatch() {
+        let cache = test_dir();
+        let (url, _) = serve("1 2\n");
+        let url = format!("{}/input.txt", url);
+        let expected = sha256_hex(b"2 1\n");
+        match fetch_to(&cache, &url, Some(&expected)) {
+            Err(FetchError::ChecksumMismatch {
+                expected: mismatched,
+                actual,
+                ..
+            }) => {
+                assert_eq!(mismatched, expected);
+                assert_eq!(actual, sha256_hex(b"1 2\n"));
+            }
+            result => panic!("expected a checksum mismatch, got {:?}", result),
+        }
+        assert!(cached_files(&cache).is_empty());
+    }
+
+    #[test]
+    fn fetch_does_not_cache_an_interrupted_download() {
+        let cache = test_dir();
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/input.txt", listener.local_addr().unwrap());
+        thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = [0; 1024];
+            let _ = stream.read(&mut request);
+            // The connection is closed before the announced length is sent.
+            let _ = stream.write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 100\r\n\r\n1 2\n");
+        });
+        match fetch_to(&cache, &url, None) {
+            Err(FetchError::IO(error)) => assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof),
+            result => panic!("expected an interrupted download, got {:?}", result),
+        }
+        assert!(cached_files(&cache).is_empty());
+    }
+
+    #[test]
+    fn file_name_falls_back_to_the_url_hash() {
+        assert_eq!(file_name("https://example.com/a/gen.cpp?raw=1"), "gen.cpp");
+        for url in [
+            "https://example.com/a/",
+            "https://example.com/a/..",
+            "https://example.com/a/.",
+        ] {
+            assert_eq!(file_name(url), sha256_hex(url.as_bytes()));
+        }
+    }
+}
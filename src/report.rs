@@ -0,0 +1,294 @@
+//! Self-contained HTML reports of test runs, to share results with people who
+//! do not use iobot.
+
+use crate::judge::{TestCase, TestResult, Verdict};
+use crate::score::{format_points, Score};
+use crate::utils::{
+    diff_outputs, format_memory, format_time, preview_file, truncated_text, DiffLine,
+};
+use std::fmt::Write;
+use std::path::Path;
+use std::time::Duration;
+
+/// Inputs and outputs longer than this are truncated in the report.
+const PREVIEW_BYTES: usize = 4 * 1024;
+
+const STYLE: &str = "
+body { font-family: sans-serif; margin: 2em; color: #222; }
+table { border-collapse: collapse; margin-bottom: 1em; }
+th, td { border: 1px solid #ccc; padding: 0.3em 0.8em; text-align: left; }
+details.test { border: 1px solid #ddd; margin: 0.2em 0; }
+details.test > summary { display: grid; grid-template-columns: 2fr 2fr 1fr 1fr;
+    padding: 0.3em 0.6em; cursor: pointer; }
+.files { display: grid; grid-template-columns: repeat(auto-fit, minmax(20em, 1fr));
+    gap: 0.6em; padding: 0.6em; }
+.files h4 { margin: 0 0 0.3em 0; }
+pre { background: #f6f6f6; padding: 0.5em; margin: 0; overflow: auto; max-height: 30em; }
+.accepted { color: #1a7f37; }
+.partially-accepted { color: #bc4c00; }
+.wrong-answer { color: #cf222e; }
+.runtime-error { color: #8250df; }
+.time-limit-exceeded { color: #0969da; }
+.not-judged { color: #9a6700; }
+.skipped { color: #6e7781; }
+.expected { background: #ffebe9; }
+.actual { background: #dafbe1; }
+";
+
+/// Results of judging a solution on the tests of a package.
+pub struct Report<'a> {
+    pub solution: &'a Path,
+    pub package: &'a Path,
+    pub tests: &'a [TestCase],
+    /// Result of every test, `None` for tests that were not judged.
+    pub results: &'a [Option<TestResult>],
+    /// Score of the run, if the package has scoring groups.
+    pub score: Option<&'a Score>,
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn verdict_class(result: Option<&TestResult>) -> &'static str {
+    match result.map(|result| result.verdict) {
+        Some(Verdict::Accepted) => "accepted",
+        Some(Verdict::PartiallyAccepted) => "partially-accepted",
+        Some(Verdict::WrongAnswer) => "wrong-answer",
+        Some(Verdict::RuntimeError(_)) => "runtime-error",
+        Some(Verdict::TimeLimitExceeded) => "time-limit-exceeded",
+        Some(Verdict::Skipped) => "skipped",
+        None => "not-judged",
+    }
+}
+
+fn verdict_text(result: Option<&TestResult>) -> String {
+    match result {
+        Some(result) if result.verdict == Verdict::PartiallyAccepted => {
+            format!("{} ({})", result.verdict, format_points(result.score))
+        }
+        Some(result) => result.verdict.to_string(),
+        None => "not judged".to_string(),
+    }
+}
+
+fn is_accepted(result: Option<&TestResult>) -> bool {
+    result.is_some_and(|result| result.verdict.is_accepted())
+}
+
+fn preview(bytes: &[u8]) -> String {
+    truncated_text(bytes, bytes.len() as u64, PREVIEW_BYTES)
+}
+
+/// Tests of a [`TestCase::group`] with their results.
+struct Group<'a> {
+    name: Option<String>,
+    tests: Vec<(&'a TestCase, Option<&'a TestResult>)>,
+}
+
+impl Report<'_> {
+    /// Tests and results split by group, in test order.
+    fn groups(&self) -> Vec<Group<'_>> {
+        let mut groups: Vec<Group> = vec![];
+        for (test, result) in self.tests.iter().zip(self.results) {
+            let name = test.group();
+            let entry = (test, result.as_ref());
+            match groups.iter_mut().find(|group| group.name == name) {
+                Some(group) => group.tests.push(entry),
+                None => groups.push(Group {
+                    name,
+                    tests: vec![entry],
+                }),
+            }
+        }
+        groups
+    }
+
+    pub fn to_html(&self) -> String {
+        let accepted = self
+            .results
+            .iter()
+            .filter(|result| is_accepted(result.as_ref()))
+            .count();
+        let mut html = String::new();
+        // Writing to a String cannot fail.
+        let _ = write!(
+            html,
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n\
+             <title>iobot report: {solution}</title>\n<style>{style}</style>\n</head>\n<body>\n\
+             <h1>{solution}</h1>\n<p>Package {package}</p>\n\
+             <p class=\"{class}\"><strong>{accepted} of {total} tests accepted</strong></p>\n",
+            solution = escape(&self.solution.display().to_string()),
+            package = escape(&self.package.display().to_string()),
+            style = STYLE,
+            class = if accepted == self.tests.len() {
+                "accepted"
+            } else {
+                "wrong-answer"
+            },
+            accepted = accepted,
+            total = self.tests.len(),
+        );
+        if let Some(score) = self.score {
+            write_score(&mut html, score);
+        }
+        let groups = self.groups();
+        self.write_summary(&mut html, &groups);
+        for group in &groups {
+            if let Some(name) = &group.name {
+                let _ = writeln!(html, "<h2>{}</h2>", escape(name));
+            }
+            for (test, result) in &group.tests {
+                self.write_test(&mut html, test, *result);
+            }
+        }
+        html.push_str("</body>\n</html>\n");
+        html
+    }
+
+    fn write_summary(&self, html: &mut String, groups: &[Group]) {
+        html.push_str(
+            "<table>\n<tr><th>Group</th><th>Accepted</th><th>Failed</th>\
+             <th>Max time</th><th>Max memory</th></tr>\n",
+        );
+        for Group { name, tests } in groups {
+            let results = tests.iter().filter_map(|(_, result)| *result);
+            let accepted = tests
+                .iter()
+                .filter(|(_, result)| is_accepted(*result))
+                .count();
+            let max_time = results
+                .clone()
+                .map(|result| result.time)
+                .max()
+                .unwrap_or(Duration::ZERO);
+            let max_memory = results.filter_map(|result| result.memory).max();
+            let _ = writeln!(
+                html,
+                "<tr><td>{}</td><td class=\"accepted\">{}</td><td class=\"{}\">{}</td>\
+                 <td>{}</td><td>{}</td></tr>",
+                escape(name.as_deref().unwrap_or("(no group)")),
+                accepted,
+                if accepted == tests.len() {
+                    ""
+                } else {
+                    "wrong-answer"
+                },
+                tests.len() - accepted,
+                format_time(max_time),
+                format_memory(max_memory),
+            );
+        }
+        html.push_str("</table>\n");
+    }
+
+    fn write_test(&self, html: &mut String, test: &TestCase, result: Option<&TestResult>) {
+        let class = verdict_class(result);
+        let _ = write!(
+            html,
+            "<details class=\"test\">\n<summary><span>{}</span><span class=\"{}\">{}</span>\
+             <span>{}</span><span>{}</span></summary>\n<div class=\"files\">\n",
+            escape(&test.name.display().to_string()),
+            class,
+            escape(&verdict_text(result)),
+            result.map_or_else(String::new, |result| format_time(result.time)),
+            result.map_or_else(String::new, |result| format_memory(result.memory)),
+        );
+        let input = preview_file(&test.input, PREVIEW_BYTES)
+            .unwrap_or_else(|error| format!("cannot read the input: {}", error));
+        write_file(html, "Input", &input);
+        let outputs = result.and_then(|result| result.outputs.as_ref());
+        let expected = match (
+            outputs.and_then(|outputs| outputs.expected.as_ref()),
+            &test.output,
+        ) {
+            (Some(expected), _) => Some(preview(expected)),
+            (None, Some(path)) => Some(
+                preview_file(path, PREVIEW_BYTES)
+                    .unwrap_or_else(|error| format!("cannot read the output: {}", error)),
+            ),
+            (None, None) => None,
+        };
+        if let Some(expected) = expected {
+            write_file(html, "Expected output", &expected);
+        }
+        if let Some(outputs) = outputs {
+            write_file(html, "Output", &preview(&outputs.output));
+            if let (Some(expected), false) = (&outputs.expected, is_accepted(result)) {
+                write_diff(html, expected, &outputs.output);
+            }
+        }
+        html.push_str("</div>\n</details>\n");
+    }
+}
+
+fn write_score(html: &mut String, score: &Score) {
+    let _ = writeln!(
+        html,
+        "<h2>Score {} of {}</h2>\n<table>\n<tr><th>Group</th><th>Policy</th><th>Points</th>\
+         <th>Max points</th><th></th></tr>",
+        format_points(score.points),
+        format_points(score.max_points),
+    );
+    for group in &score.groups {
+        let _ = writeln!(
+            html,
+            "<tr><td>{}</td><td>{}</td><td class=\"{}\">{}</td><td>{}</td><td>{}</td></tr>",
+            escape(&group.name),
+            group.policy,
+            if group.points >= group.max_points {
+                "accepted"
+            } else {
+                "wrong-answer"
+            },
+            format_points(group.points),
+            format_points(group.max_points),
+            group
+                .failed_dependency
+                .as_ref()
+                .map(|dependency| format!("needs {}", escape(dependency)))
+                .unwrap_or_default(),
+        );
+    }
+    html.push_str("</table>\n");
+}
+
+fn write_file(html: &mut String, title: &str, contents: &str) {
+    let _ = writeln!(
+        html,
+        "<div><h4>{}</h4><pre>{}</pre></div>",
+        title,
+        escape(contents)
+    );
+}
+
+fn write_diff(html: &mut String, expected: &[u8], output: &[u8]) {
+    html.push_str("<div><h4>Diff</h4><pre>");
+    match diff_outputs(expected, output, PREVIEW_BYTES) {
+        Some(diff) => {
+            for line in diff {
+                let _ = match line {
+                    DiffLine::Same(line) => writeln!(html, "  {}", escape(&line)),
+                    DiffLine::Expected(line) => {
+                        writeln!(html, "<span class=\"expected\">- {}</span>", escape(&line))
+                    }
+                    DiffLine::Actual(line) => {
+                        writeln!(html, "<span class=\"actual\">+ {}</span>", escape(&line))
+                    }
+                };
+            }
+        }
+        None => html.push_str("outputs are not valid UTF-8"),
+    }
+    html.push_str("</pre></div>\n");
+}
@@ -1,12 +1,28 @@
 use crate::config::Program;
-use std::io::Write;
+use crate::remote::{self, FetchError};
+use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
+use std::io::{Read, Write};
 use std::path::{Path, PathBuf};
 use std::process::{Command, Output, Stdio};
-use std::{env, fs, io};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use std::{cmp, env, error, fmt, fs, io, panic, thread};
 use uuid::Uuid;
 
+/// How often a running program is first checked for having finished or
+/// exceeding its time limit. The interval doubles after each check up to
+/// [`MAX_POLL_INTERVAL`], so that short runs are timed precisely without
+/// waking up every millisecond during long ones.
+const MIN_POLL_INTERVAL: Duration = Duration::from_millis(1);
+const MAX_POLL_INTERVAL: Duration = Duration::from_millis(10);
+/// How often memory usage is sampled, as reading it is slower than a check.
+const MEMORY_INTERVAL: Duration = Duration::from_millis(50);
+
 struct Compiled {
     pub target: PathBuf,
+    /// Output of the compiler, e.g. its warnings.
+    pub log: String,
 }
 
 impl Compiled {
@@ -18,11 +34,76 @@ impl Compiled {
     }
 }
 
+impl Drop for Compiled {
+    fn drop(&mut self) {
+        // Leftovers in the temporary directory are not worth a panic.
+        let _ = self.delete_file();
+    }
+}
+
+/// Which part of a package a program plays, used to label build output.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ProgramRole {
+    Generator,
+    ModelProgram,
+    Verifier,
+    Solution,
+}
+
+impl fmt::Display for ProgramRole {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProgramRole::Generator => write!(f, "generator"),
+            ProgramRole::ModelProgram => write!(f, "model program"),
+            ProgramRole::Verifier => write!(f, "verifier"),
+            ProgramRole::Solution => write!(f, "solution"),
+        }
+    }
+}
+
+/// A compiler run that exited unsuccessfully.
+#[derive(Debug)]
+pub struct CompileFailure {
+    /// Source file of the program, if the program config names one.
+    pub program: Option<PathBuf>,
+    /// The compiler command line, with arguments quoted where needed.
+    pub command_line: String,
+    /// Exit code of the compiler, `None` if it was killed by a signal.
+    pub code: Option<i32>,
+    pub stdout: String,
+    pub stderr: String,
+}
+
+impl fmt::Display for CompileFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.program {
+            Some(program) => write!(f, "compilation of {} failed", program.display())?,
+            None => write!(f, "compilation failed")?,
+        }
+        match self.code {
+            Some(code) => writeln!(f, " with exit code {}", code)?,
+            None => writeln!(f, ", the compiler was killed by a signal")?,
+        }
+        writeln!(f, "╭─ $ {}", self.command_line)?;
+        for line in self.stdout.lines().chain(self.stderr.lines()) {
+            writeln!(f, "│ {}", line)?;
+        }
+        write!(f, "╰─")
+    }
+}
+
 #[derive(Debug)]
 pub enum CompileError {
     IO(io::Error),
-    Unsuccessful,
+    Unsuccessful(CompileFailure),
     Extension,
+    Fetch(FetchError),
+}
+
+impl From<FetchError> for CompileError {
+    fn from(error: FetchError) -> Self {
+        CompileError::Fetch(error)
+    }
 }
 
 impl From<io::Error> for CompileError {
@@ -31,88 +112,247 @@ impl From<io::Error> for CompileError {
     }
 }
 
+impl fmt::Display for CompileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CompileError::IO(error) => write!(f, "cannot run the compiler: {}", error),
+            CompileError::Unsuccessful(failure) => write!(f, "{}", failure),
+            CompileError::Extension => write!(f, "target extension must start with a dot"),
+            CompileError::Fetch(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl error::Error for CompileError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            CompileError::IO(error) => Some(error),
+            CompileError::Fetch(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
 fn replace_target(template: &str, target: &Path) -> String {
-    template.replace("{target}", target.to_str().unwrap())
+    template.replace("{target}", &target.to_string_lossy())
+}
+
+fn quote_arg(arg: &str) -> String {
+    if !arg.is_empty() && !arg.contains(|c: char| c.is_whitespace() || c == '"' || c == '\'') {
+        return arg.to_string();
+    }
+    format!("'{}'", arg.replace('\'', "'\\''"))
 }
 
+fn command_line<S: AsRef<str>>(command: &str, args: &[S]) -> String {
+    std::iter::once(command)
+        .chain(args.iter().map(AsRef::as_ref))
+        .map(quote_arg)
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+// TODO: Add config for compiler
+const GPP_COMPILER: &str = "g++";
+// TODO: Add config for python command
+const PYTHON_INTERPRETER: &str = "python";
+
 fn compile(
+    program: Option<&Path>,
     command: &str,
     args: &[String],
     config_dir: &Path,
     ext: &str,
 ) -> Result<Compiled, CompileError> {
-    if !ext.is_empty() && !ext.starts_with(".") {
+    if !ext.is_empty() && !ext.starts_with('.') {
         return Err(CompileError::Extension);
     }
     let build_folder = env::temp_dir().join("iobot/build");
-    fs::create_dir_all(&build_folder).unwrap();
+    fs::create_dir_all(&build_folder)?;
     let target = build_folder.join(Uuid::new_v4().to_string() + ext);
-    let mut compile_command = Command::new(replace_target(command, &target));
-    compile_command.args(args.into_iter().map(|x| replace_target(x, &target)));
-    compile_command.current_dir(config_dir);
-    if !compile_command.spawn()?.wait()?.success() {
-        return Err(CompileError::Unsuccessful);
+    let command = replace_target(command, &target);
+    let args: Vec<String> = args.iter().map(|x| replace_target(x, &target)).collect();
+    let output = Command::new(&command)
+        .args(&args)
+        .current_dir(config_dir)
+        .stdin(Stdio::null())
+        .output()?;
+    if !output.status.success() {
+        return Err(CompileError::Unsuccessful(CompileFailure {
+            program: program.map(Path::to_path_buf),
+            command_line: command_line(&command, &args),
+            code: output.status.code(),
+            stdout: String::from_utf8_lossy(&output.stdout).into_owned(),
+            stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+        }));
     }
-    Ok(Compiled { target })
+    Ok(Compiled {
+        target,
+        log: String::from_utf8_lossy(&output.stdout).into_owned()
+            + &String::from_utf8_lossy(&output.stderr),
+    })
 }
 
+/// Standard output of a successful run.
 pub struct RunResult {
     pub output: Vec<u8>,
+    /// Wall time of the run.
+    pub time: Duration,
 }
 
 impl RunResult {
-    pub fn from_output(output: Output) -> Result<RunResult, RunError> {
+    pub fn from_output(output: Output, time: Duration) -> Result<RunResult, RunError> {
         if !output.status.success() {
-            return Err(RunError(output.status.code()));
+            return Err(RunError::Unsuccessful(output.status.code()));
         }
-        return Ok(RunResult {
+        Ok(RunResult {
             output: output.stdout,
-        });
+            time,
+        })
+    }
+}
+
+/// Statistics of a program run, updated while the program runs so that they
+/// can be read from other threads.
+#[derive(Debug, Default)]
+pub struct RunMonitor {
+    start: Mutex<Option<Instant>>,
+    /// Peak resident memory in KiB, 0 until it is first sampled.
+    memory: AtomicU64,
+}
+
+impl RunMonitor {
+    fn start(&self, start: Instant) {
+        *self.start.lock().unwrap_or_else(|error| error.into_inner()) = Some(start);
+        self.memory.store(0, Ordering::Relaxed);
+    }
+
+    fn sample_memory(&self, pid: u32) {
+        if let Some(memory) = peak_memory(pid) {
+            self.memory.fetch_max(memory, Ordering::Relaxed);
+        }
+    }
+
+    /// Time since the program started, `None` before it starts.
+    pub fn elapsed(&self) -> Option<Duration> {
+        self.start
+            .lock()
+            .unwrap_or_else(|error| error.into_inner())
+            .map(|start| start.elapsed())
+    }
+
+    /// Peak resident memory in KiB, sampled while the program runs. `None` if
+    /// it could not be measured, which is the case outside of Linux.
+    pub fn memory(&self) -> Option<u64> {
+        match self.memory.load(Ordering::Relaxed) {
+            0 => None,
+            memory => Some(memory),
+        }
     }
 }
 
+/// Peak resident memory of the process `pid` in KiB.
+#[cfg(target_os = "linux")]
+fn peak_memory(pid: u32) -> Option<u64> {
+    let status = fs::read_to_string(format!("/proc/{}/status", pid)).ok()?;
+    status
+        .lines()
+        .find_map(|line| line.strip_prefix("VmHWM:"))
+        .and_then(|value| value.trim().trim_end_matches("kB").trim().parse().ok())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn peak_memory(_pid: u32) -> Option<u64> {
+    None
+}
+
 #[derive(Debug)]
-pub struct RunError(pub Option<i32>);
+pub enum RunError {
+    /// The process could not be started or communicated with.
+    IO(io::Error),
+    /// The process exited with a non-zero exit code, `None` if it was killed
+    /// by a signal.
+    Unsuccessful(Option<i32>),
+    /// The process was killed after running longer than the time limit.
+    TimeLimitExceeded,
+}
 
+impl From<io::Error> for RunError {
+    fn from(error: io::Error) -> Self {
+        RunError::IO(error)
+    }
+}
+
+impl fmt::Display for RunError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RunError::IO(error) => write!(f, "cannot run the program: {}", error),
+            RunError::Unsuccessful(Some(code)) => {
+                write!(f, "program exited with code {}", code)
+            }
+            RunError::Unsuccessful(None) => write!(f, "program was terminated by a signal"),
+            RunError::TimeLimitExceeded => write!(f, "program exceeded the time limit"),
+        }
+    }
+}
+
+impl error::Error for RunError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            RunError::IO(error) => Some(error),
+            RunError::Unsuccessful(_) | RunError::TimeLimitExceeded => None,
+        }
+    }
+}
+
+/// A program ready to be executed, compiled beforehand if necessary.
+///
+/// The compiled binary is shared by clones of the runner and removed when the
+/// last of them is dropped.
+#[derive(Clone)]
 pub struct Runner {
     config_dir: PathBuf,
     command: String,
     args: Vec<String>,
-    compiled: Option<Compiled>,
+    /// Keeps the binary alive, it is run through `command`.
+    compiled: Option<Arc<Compiled>>,
 }
 
 impl Runner {
+    /// Compiles `program` if needed. Paths in `program` are relative to
+    /// `config_dir`, which is also the working directory of every run.
     pub fn build(program: &Program, config_dir: &Path) -> Result<Runner, CompileError> {
         let (command, args, compiled): (String, Vec<String>, Option<Compiled>) = match program {
             Program::GPP {
                 path,
                 compiler_args,
+                checksum,
             } => {
-                let mut args = compiler_args.clone().unwrap_or(vec![]);
+                let path = &remote::local_path(path, checksum.as_deref())?;
+                let mut args = compiler_args.clone().unwrap_or_default();
                 args.extend([
-                    path.clone().to_str().unwrap().to_string(),
+                    path.to_string_lossy().into_owned(),
                     "-o".to_string(),
                     "{target}".to_string(),
                 ]);
-                // TODO: Add config for compiler
-                let compiled = compile("g++".as_ref(), &args, config_dir, ".exe")?;
+                let compiled = compile(Some(path), GPP_COMPILER, &args, config_dir, ".exe")?;
                 (
-                    compiled.target.to_str().unwrap().to_string(),
+                    compiled.target.to_string_lossy().into_owned(),
                     vec![],
                     Some(compiled),
                 )
             }
-            Program::Python { path } => {
-                // TODO: Add config for python command
+            Program::Python { path, checksum } => {
+                let path = remote::local_path(path, checksum.as_deref())?;
                 (
-                    "python".to_string(),
-                    vec![path.to_str().unwrap().to_string()],
+                    PYTHON_INTERPRETER.to_string(),
+                    vec![path.to_string_lossy().into_owned()],
                     None,
                 )
             }
             Program::Command { run } => (
                 run.command.clone(),
-                run.args.clone().unwrap_or(vec![]),
+                run.args.clone().unwrap_or_default(),
                 None,
             ),
             Program::Compiled {
@@ -121,16 +361,17 @@ impl Runner {
                 run,
             } => {
                 let compiled = compile(
+                    None,
                     &compile_config.command,
-                    &compile_config.args.clone().unwrap_or(vec![]),
+                    &compile_config.args.clone().unwrap_or_default(),
                     config_dir,
-                    &extension,
+                    extension,
                 )?;
                 (
                     replace_target(&run.command, &compiled.target),
                     run.args
                         .clone()
-                        .unwrap_or(vec![])
+                        .unwrap_or_default()
                         .into_iter()
                         .map(|x| replace_target(&x, &compiled.target))
                         .collect(),
@@ -139,13 +380,18 @@ impl Runner {
             }
         };
         Ok(Runner {
-            compiled,
+            compiled: compiled.map(Arc::new),
             command,
             args,
             config_dir: config_dir.to_path_buf(),
         })
     }
 
+    /// Output of the compiler, `None` for programs that are not compiled.
+    pub fn compile_log(&self) -> Option<&str> {
+        self.compiled.as_ref().map(|compiled| compiled.log.as_str())
+    }
+
     fn get_command(&self) -> Command {
         let mut command = Command::new(&self.command);
         command.args(&self.args);
@@ -153,36 +399,427 @@ impl Runner {
         command
     }
 
+    /// Runs the program with `input` on standard input and `args` appended to
+    /// its arguments.
     pub fn run(&self, input: Vec<u8>, args: Vec<String>) -> Result<RunResult, RunError> {
+        self.run_with_limit(input, args, None)
+    }
+
+    /// Like [`Runner::run`], but kills the program once it runs longer than
+    /// `time_limit`.
+    pub fn run_with_limit(
+        &self,
+        input: Vec<u8>,
+        args: Vec<String>,
+        time_limit: Option<Duration>,
+    ) -> Result<RunResult, RunError> {
+        self.run_monitored(input, args, time_limit, &RunMonitor::default())
+    }
+
+    /// Like [`Runner::run_with_limit`], updating `monitor` while the program
+    /// runs.
+    pub fn run_monitored(
+        &self,
+        input: Vec<u8>,
+        args: Vec<String>,
+        time_limit: Option<Duration>,
+        monitor: &RunMonitor,
+    ) -> Result<RunResult, RunError> {
         let mut command = self.get_command();
         command.args(args);
-        let child = command
+        let start = Instant::now();
+        let mut child = command
             .stdin(Stdio::piped())
             .stdout(Stdio::piped())
-            .spawn()
-            .map_err(|_| RunError(None))?;
-        child
-            .stdin
-            .as_ref()
-            .unwrap()
-            .write_all(&input)
-            .map_err(|_| RunError(None))?;
-        let output = child.wait_with_output().map_err(|_| RunError(None))?;
-        return RunResult::from_output(output);
+            .spawn()?;
+        monitor.start(start);
+        // Writing and reading happen on separate threads, so that a program
+        // writing output before consuming its whole input cannot deadlock.
+        let stdin = child.stdin.take();
+        let writer = thread::spawn(move || -> io::Result<()> {
+            match stdin {
+                Some(mut stdin) => stdin.write_all(&input),
+                None => Ok(()),
+            }
+        });
+        let stdout = child.stdout.take();
+        let reader = thread::spawn(move || -> io::Result<Vec<u8>> {
+            let mut output = vec![];
+            if let Some(mut stdout) = stdout {
+                stdout.read_to_end(&mut output)?;
+            }
+            Ok(output)
+        });
+        let mut interval = MIN_POLL_INTERVAL;
+        let mut sampled: Option<Instant> = None;
+        let status = loop {
+            if sampled.is_none_or(|sampled| sampled.elapsed() >= MEMORY_INTERVAL) {
+                monitor.sample_memory(child.id());
+                sampled = Some(Instant::now());
+            }
+            if let Some(status) = child.try_wait()? {
+                break status;
+            }
+            if time_limit.is_some_and(|limit| start.elapsed() > limit) {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(RunError::TimeLimitExceeded);
+            }
+            thread::sleep(interval);
+            interval = cmp::min(interval * 2, MAX_POLL_INTERVAL);
+        };
+        let time = start.elapsed();
+        let output = reader
+            .join()
+            .unwrap_or_else(|error| panic::resume_unwind(error))?;
+        match writer
+            .join()
+            .unwrap_or_else(|error| panic::resume_unwind(error))
+        {
+            // The program does not have to read its whole input.
+            Err(error) if error.kind() != io::ErrorKind::BrokenPipe => return Err(error.into()),
+            _ => {}
+        }
+        RunResult::from_output(
+            Output {
+                status,
+                stdout: output,
+                stderr: vec![],
+            },
+            time,
+        )
     }
 
     pub fn run_without_input(&self, args: Vec<String>) -> Result<RunResult, RunError> {
         let mut command = self.get_command();
         command.args(args);
-        let output = command.output().map_err(|_| RunError(None))?;
-        return RunResult::from_output(output);
+        let start = Instant::now();
+        let output = command.output()?;
+        RunResult::from_output(output, start.elapsed())
     }
 }
 
-impl Drop for Runner {
-    fn drop(&mut self) {
-        if let Some(compiled) = &self.compiled {
-            compiled.delete_file().unwrap();
+/// Short description of `program` for messages, its source or command.
+pub fn describe(program: &Program) -> String {
+    match program {
+        Program::GPP { path, .. } | Program::Python { path, .. } => path.display().to_string(),
+        Program::Command { run } => run.command.clone(),
+        Program::Compiled { compile, .. } => compile.command.clone(),
+    }
+}
+
+/// Compile and run command lines of `program` as [`Runner::build`] uses them,
+/// with `{target}` standing for the compiled program. The compile command line
+/// is `None` for programs that are not compiled.
+pub fn command_lines(program: &Program) -> (Option<String>, String) {
+    let args = |args: &Option<Vec<String>>| args.clone().unwrap_or_default();
+    match program {
+        Program::GPP {
+            path,
+            compiler_args,
+            ..
+        } => {
+            let mut args = args(compiler_args);
+            args.extend([
+                path.to_string_lossy().into_owned(),
+                "-o".to_string(),
+                "{target}".to_string(),
+            ]);
+            (
+                Some(command_line(GPP_COMPILER, &args)),
+                "{target}".to_string(),
+            )
         }
+        Program::Python { path, .. } => (
+            None,
+            command_line(PYTHON_INTERPRETER, &[path.to_string_lossy()]),
+        ),
+        Program::Command { run } => (None, command_line(&run.command, &args(&run.args))),
+        Program::Compiled { compile, run, .. } => (
+            Some(command_line(&compile.command, &args(&compile.args))),
+            command_line(&run.command, &args(&run.args)),
+        ),
+    }
+}
+
+/// Builds all `programs` concurrently, showing the status of every build.
+///
+/// Returns the runners in the order of `programs`, or the role and error of
+/// the first program that failed to build.
+#[allow(clippy::type_complexity)]
+pub fn build_all(
+    programs: &[(ProgramRole, Program)],
+    config_dir: &Path,
+) -> Result<Vec<(ProgramRole, Runner)>, (ProgramRole, CompileError)> {
+    let multi = MultiProgress::new();
+    let style =
+        ProgressStyle::default_spinner().template("{spinner:.cyan} {prefix:.bold} {wide_msg}");
+    let results: Vec<Result<Runner, CompileError>> = thread::scope(|scope| {
+        let handles: Vec<_> = programs
+            .iter()
+            .map(|(role, program)| {
+                let bar = multi.add(ProgressBar::new_spinner());
+                bar.set_style(style.clone());
+                bar.set_prefix(role.to_string());
+                bar.set_message(format!("building {}", describe(program)));
+                bar.enable_steady_tick(100);
+                scope.spawn(move || {
+                    let result = Runner::build(program, config_dir);
+                    match &result {
+                        Ok(_) => bar.finish_with_message(format!("built {}", describe(program))),
+                        Err(_) => bar
+                            .abandon_with_message(format!("failed to build {}", describe(program))),
+                    }
+                    result
+                })
+            })
+            .collect();
+        // Drawing errors only affect the progress display.
+        let _ = multi.join();
+        handles
+            .into_iter()
+            .map(|handle| {
+                handle
+                    .join()
+                    .unwrap_or_else(|error| panic::resume_unwind(error))
+            })
+            .collect()
+    });
+    results
+        .into_iter()
+        .zip(programs)
+        .map(|(result, (role, _))| {
+            result
+                .map(|runner| (*role, runner))
+                .map_err(|error| (*role, error))
+        })
+        .collect()
+}
+
+/// Removes the first runner with `role` from the result of [`build_all`].
+pub fn take_runner(runners: &mut Vec<(ProgramRole, Runner)>, role: ProgramRole) -> Option<Runner> {
+    let index = runners
+        .iter()
+        .position(|(runner_role, _)| *runner_role == role)?;
+    Some(runners.remove(index).1)
+}
+
+/// Runners kept between builds, so that programs whose sources did not change
+/// are not compiled again, e.g. in watch mode.
+#[derive(Default)]
+pub struct RunnerCache {
+    runners: Vec<(Program, Runner)>,
+}
+
+impl RunnerCache {
+    /// Forgets the runners of programs whose source is one of `changed`, paths
+    /// relative to `config_dir` or absolute.
+    pub fn invalidate(&mut self, changed: &[PathBuf], config_dir: &Path) {
+        let changed: Vec<PathBuf> = changed
+            .iter()
+            .map(|path| canonical(&config_dir.join(path)))
+            .collect();
+        self.runners
+            .retain(|(program, _)| match program.source_path() {
+                Some(path) => !changed.contains(&canonical(&config_dir.join(path))),
+                None => true,
+            });
+    }
+
+    /// Like [`build_all`], but reuses the cached runners of identical
+    /// programs. Only the runners of `programs` are kept afterwards.
+    #[allow(clippy::type_complexity)]
+    pub fn build_all(
+        &mut self,
+        programs: &[(ProgramRole, Program)],
+        config_dir: &Path,
+    ) -> Result<Vec<(ProgramRole, Runner)>, (ProgramRole, CompileError)> {
+        let cached = |program: &Program| {
+            self.runners
+                .iter()
+                .find(|(cached, _)| cached == program)
+                .map(|(_, runner)| runner.clone())
+        };
+        let missing: Vec<(ProgramRole, Program)> = programs
+            .iter()
+            .filter(|(_, program)| cached(program).is_none())
+            .cloned()
+            .collect();
+        let mut built = build_all(&missing, config_dir)?;
+        let runners: Vec<(ProgramRole, Runner)> = programs
+            .iter()
+            .map(|(role, program)| {
+                let runner = cached(program).unwrap_or_else(|| {
+                    take_runner(&mut built, *role).expect("missing programs were built")
+                });
+                (*role, runner)
+            })
+            .collect();
+        self.runners = programs
+            .iter()
+            .zip(&runners)
+            .map(|((_, program), (_, runner))| (program.clone(), runner.clone()))
+            .collect();
+        Ok(runners)
+    }
+}
+
+/// `path` canonicalized if it exists, so that differently spelled paths of
+/// the same file compare equal.
+fn canonical(path: &Path) -> PathBuf {
+    path.canonicalize().unwrap_or_else(|_| path.to_path_buf())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config;
+    use crate::utils::test_dir;
+
+    fn python(source: &str) -> Runner {
+        let dir = test_dir();
+        let path = dir.join("program.py");
+        fs::write(&path, source).unwrap();
+        let program = Program::Python {
+            path,
+            checksum: None,
+        };
+        Runner::build(&program, &dir).unwrap()
+    }
+
+    #[test]
+    fn run_with_limit_returns_the_output() {
+        let runner = python("print(sum(map(int, input().split())))\n");
+        let result = runner
+            .run_with_limit(b"1 2\n".to_vec(), vec![], Some(Duration::from_secs(10)))
+            .unwrap();
+        assert_eq!(result.output, b"3\n");
+    }
+
+    #[test]
+    fn run_with_limit_stops_at_the_time_limit() {
+        let runner = python("import time\ntime.sleep(10)\n");
+        let start = Instant::now();
+        let result = runner.run_with_limit(vec![], vec![], Some(Duration::from_millis(200)));
+        assert!(matches!(result, Err(RunError::TimeLimitExceeded)));
+        assert!(start.elapsed() < Duration::from_secs(5));
+    }
+
+    fn shell(script: &str) -> Program {
+        Program::Compiled {
+            compile: config::Command {
+                command: "sh".to_string(),
+                args: Some(vec!["-c".to_string(), script.to_string()]),
+            },
+            extension: String::new(),
+            run: config::Command {
+                command: "{target}".to_string(),
+                args: None,
+            },
+        }
+    }
+
+    #[test]
+    fn compile_failure_keeps_the_compiler_output() {
+        let error = Runner::build(&shell("echo checking; echo oops >&2; exit 3"), &test_dir())
+            .err()
+            .unwrap();
+        let failure = match &error {
+            CompileError::Unsuccessful(failure) => failure,
+            error => panic!("unexpected error {}", error),
+        };
+        assert_eq!(failure.code, Some(3));
+        assert_eq!(failure.stdout, "checking\n");
+        assert_eq!(failure.stderr, "oops\n");
+        assert_eq!(
+            error.to_string(),
+            "compilation failed with exit code 3\n\
+             ╭─ $ sh -c 'echo checking; echo oops >&2; exit 3'\n\
+             │ checking\n\
+             │ oops\n\
+             ╰─"
+        );
+    }
+
+    #[test]
+    fn compile_failure_names_the_source() {
+        let dir = test_dir();
+        fs::write(dir.join("broken.cpp"), "int main( {\n").unwrap();
+        let program = Program::GPP {
+            path: PathBuf::from("broken.cpp"),
+            compiler_args: None,
+            checksum: None,
+        };
+        match Runner::build(&program, &dir) {
+            Err(CompileError::Unsuccessful(failure)) => {
+                assert_eq!(failure.program, Some(PathBuf::from("broken.cpp")));
+                assert!(failure.command_line.starts_with("g++ broken.cpp -o "));
+                assert!(
+                    failure.stderr.contains("broken.cpp:1:"),
+                    "{}",
+                    failure.stderr
+                );
+            }
+            Err(error) => panic!("unexpected error {}", error),
+            Ok(_) => panic!("broken.cpp compiled"),
+        }
+    }
+
+    #[test]
+    fn build_all_names_the_failing_program() {
+        let dir = test_dir();
+        fs::write(dir.join("gen.py"), "print(1)\n").unwrap();
+        let programs = [
+            (
+                ProgramRole::Generator,
+                Program::Python {
+                    path: PathBuf::from("gen.py"),
+                    checksum: None,
+                },
+            ),
+            (ProgramRole::Verifier, shell("exit 1")),
+        ];
+        match build_all(&programs, &dir) {
+            Err((role, CompileError::Unsuccessful(_))) => assert_eq!(role, ProgramRole::Verifier),
+            Err((_, error)) => panic!("unexpected error {}", error),
+            Ok(_) => panic!("the verifier was built"),
+        }
+        let mut runners = build_all(&programs[..1], &dir).unwrap();
+        let generator = take_runner(&mut runners, ProgramRole::Generator).unwrap();
+        assert_eq!(generator.run(vec![], vec![]).unwrap().output, b"1\n");
+    }
+
+    #[test]
+    fn runner_cache_rebuilds_changed_sources() {
+        let dir = test_dir();
+        for name in ["a.cpp", "b.cpp"] {
+            fs::write(dir.join(name), "int main() {}\n").unwrap();
+        }
+        let gpp = |path: &str| Program::GPP {
+            path: PathBuf::from(path),
+            compiler_args: None,
+            checksum: None,
+        };
+        let programs = [
+            (ProgramRole::ModelProgram, gpp("a.cpp")),
+            (ProgramRole::Verifier, gpp("b.cpp")),
+        ];
+        let targets = |runners: Vec<(ProgramRole, Runner)>| -> Vec<String> {
+            runners
+                .into_iter()
+                .map(|(_, runner)| runner.command)
+                .collect()
+        };
+        let mut cache = RunnerCache::default();
+        let first = targets(cache.build_all(&programs, &dir).unwrap());
+        assert_eq!(targets(cache.build_all(&programs, &dir).unwrap()), first);
+        cache.invalidate(&[dir.join("b.cpp")], &dir);
+        let rebuilt = targets(cache.build_all(&programs, &dir).unwrap());
+        assert_eq!(rebuilt[0], first[0]);
+        assert_ne!(rebuilt[1], first[1]);
+        // Programs no longer built are forgotten.
+        cache.build_all(&programs[..1], &dir).unwrap();
+        let verifier = targets(cache.build_all(&programs[1..], &dir).unwrap());
+        assert_ne!(verifier[0], rebuilt[1]);
     }
 }
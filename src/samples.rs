@@ -0,0 +1,222 @@
+//! Sample tests of a package, written to a `samples/` directory with snippets
+//! listing them for the problem statement.
+
+use crate::config::Config;
+use crate::judge::{list_tests, JudgeError, TestCase};
+use crate::utils::with_suffix;
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+use std::{cmp, error, fmt, fs, io};
+
+/// Directory of the samples in a generated package.
+pub const SAMPLES_DIR: &str = "samples";
+
+#[derive(Debug)]
+pub enum SamplesError {
+    IO(io::Error),
+    Judge(JudgeError),
+    /// A sample in the config is not a test of the package.
+    UnknownSample(String),
+}
+
+impl fmt::Display for SamplesError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SamplesError::IO(error) => write!(f, "cannot write samples: {}", error),
+            SamplesError::Judge(error) => write!(f, "{}", error),
+            SamplesError::UnknownSample(name) => {
+                write!(f, "sample {} is not a test of the package", name)
+            }
+        }
+    }
+}
+
+impl error::Error for SamplesError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            SamplesError::IO(error) => Some(error),
+            SamplesError::Judge(error) => Some(error),
+            SamplesError::UnknownSample(_) => None,
+        }
+    }
+}
+
+impl From<io::Error> for SamplesError {
+    fn from(error: io::Error) -> Self {
+        SamplesError::IO(error)
+    }
+}
+
+impl From<JudgeError> for SamplesError {
+    fn from(error: JudgeError) -> Self {
+        SamplesError::Judge(error)
+    }
+}
+
+/// A sample test with its expected output, if the package has outputs.
+pub struct Sample {
+    pub test: TestCase,
+    pub input: String,
+    pub output: Option<String>,
+}
+
+/// The tests of the generated package in `package` listed in the `samples`
+/// of `config`, in config order.
+pub fn load_samples(config: &Config, package: &Path) -> Result<Vec<Sample>, SamplesError> {
+    let names = &config.options().samples;
+    if names.is_empty() {
+        return Ok(vec![]);
+    }
+    let tests = list_tests(config, package)?;
+    let mut samples = vec![];
+    for name in names {
+        let test = tests
+            .iter()
+            .find(|test| test.stem == Path::new(name) || test.name == Path::new(name))
+            .ok_or_else(|| SamplesError::UnknownSample(name.clone()))?;
+        samples.push(Sample {
+            input: String::from_utf8_lossy(&fs::read(&test.input)?).into_owned(),
+            output: match &test.output {
+                Some(output) => Some(String::from_utf8_lossy(&fs::read(output)?).into_owned()),
+                None => None,
+            },
+            test: test.clone(),
+        });
+    }
+    Ok(samples)
+}
+
+/// Writes the samples to the `samples/` directory of `package`, numbered
+/// from 1 like in statements, with `statement.md` and `statement.tex`
+/// snippets.
+pub fn write_samples(samples: &[Sample], package: &Path) -> io::Result<PathBuf> {
+    let dir = package.join(SAMPLES_DIR);
+    if dir.is_dir() {
+        fs::remove_dir_all(&dir)?;
+    }
+    fs::create_dir_all(&dir)?;
+    for (index, sample) in samples.iter().enumerate() {
+        let name = PathBuf::from((index + 1).to_string());
+        fs::copy(&sample.test.input, dir.join(with_suffix(&name, ".in")))?;
+        if let Some(output) = &sample.test.output {
+            fs::copy(output, dir.join(with_suffix(&name, ".out")))?;
+        }
+    }
+    fs::write(dir.join("statement.md"), markdown(samples))?;
+    fs::write(dir.join("statement.tex"), latex(samples))?;
+    Ok(dir)
+}
+
+/// A Markdown section per sample with its input and output in code blocks.
+pub fn markdown(samples: &[Sample]) -> String {
+    let mut markdown = String::new();
+    for (index, sample) in samples.iter().enumerate() {
+        // Writing to a String cannot fail.
+        let _ = write!(
+            markdown,
+            "### Sample {}\n\nInput:\n\n{}",
+            index + 1,
+            code_block(&sample.input)
+        );
+        if let Some(output) = &sample.output {
+            let _ = write!(markdown, "\nOutput:\n\n{}", code_block(output));
+        }
+        markdown.push('\n');
+    }
+    markdown
+}
+
+/// An `example` environment with an `\exmp{input}{output}` line per sample,
+/// as defined by `olymp.sty`, with the LaTeX special characters escaped.
+pub fn latex(samples: &[Sample]) -> String {
+    let mut latex = String::from("\\begin{example}\n");
+    for sample in samples {
+        let _ = writeln!(
+            latex,
+            "\\exmp{{{}}}{{{}}}%",
+            escape_latex(&with_newline(&sample.input)),
+            escape_latex(&with_newline(sample.output.as_deref().unwrap_or_default()))
+        );
+    }
+    latex.push_str("\\end{example}\n");
+    latex
+}
+
+/// `text` in a fenced code block, with a fence longer than any run of
+/// backticks in it.
+fn code_block(text: &str) -> String {
+    let longest_run = text.split(|c| c != '`').map(str::len).max().unwrap_or(0);
+    let fence = "`".repeat(cmp::max(3, longest_run + 1));
+    format!("{}\n{}{}\n", fence, with_newline(text), fence)
+}
+
+/// Escapes the characters with a special meaning in LaTeX text.
+fn escape_latex(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' => escaped.push_str("\\textbackslash{}"),
+            '~' => escaped.push_str("\\textasciitilde{}"),
+            '^' => escaped.push_str("\\textasciicircum{}"),
+            '%' | '#' | '_' | '{' | '}' | '$' | '&' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn with_newline(text: &str) -> String {
+    if text.is_empty() || text.ends_with('\n') {
+        text.to_string()
+    } else {
+        format!("{}\n", text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(input: &str, output: Option<&str>) -> Sample {
+        Sample {
+            test: TestCase {
+                name: PathBuf::from("1.in"),
+                stem: PathBuf::from("1"),
+                input: PathBuf::from("in/1.in"),
+                output: None,
+            },
+            input: input.to_string(),
+            output: output.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn markdown_lists_samples() {
+        assert_eq!(
+            markdown(&[sample("1 2\n", Some("3")), sample("5", None)]),
+            "### Sample 1\n\nInput:\n\n```\n1 2\n```\n\nOutput:\n\n```\n3\n```\n\n\
+             ### Sample 2\n\nInput:\n\n```\n5\n```\n\n"
+        );
+    }
+
+    #[test]
+    fn markdown_fence_is_longer_than_backticks() {
+        assert_eq!(
+            markdown(&[sample("a ```` b\n`\n", None)]),
+            "### Sample 1\n\nInput:\n\n`````\na ```` b\n`\n`````\n\n"
+        );
+    }
+
+    #[test]
+    fn latex_escapes_special_characters() {
+        assert_eq!(
+            latex(&[sample("50% #1 a_b {x}\n", Some("C:\\dir ~ $5 & 2^3\n"))]),
+            "\\begin{example}\n\
+             \\exmp{50\\% \\#1 a\\_b \\{x\\}\n}{C:\\textbackslash{}dir \\textasciitilde{} \\$5 \\& 2\\textasciicircum{}3\n}%\n\
+             \\end{example}\n"
+        );
+    }
+}
@@ -0,0 +1,379 @@
+//! Scores of test runs, combined per group as configured by the `groups` of
+//! `iobot.yaml`.
+
+use crate::config::{GroupScoring, ScoringPolicy};
+use crate::judge::{TestCase, TestResult};
+use std::collections::BTreeMap;
+use std::{error, fmt};
+
+#[derive(Debug)]
+pub enum ScoringError {
+    UnknownDependency {
+        group: String,
+        dependency: String,
+    },
+    /// Groups depend on each other in a cycle going through this group.
+    DependencyCycle(String),
+    /// A group has scoring but no tests, which is most likely a typo.
+    EmptyGroup(String),
+}
+
+impl fmt::Display for ScoringError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScoringError::UnknownDependency { group, dependency } => write!(
+                f,
+                "group {} depends on {}, which has no scoring",
+                group, dependency
+            ),
+            ScoringError::DependencyCycle(group) => {
+                write!(f, "group {} depends on itself", group)
+            }
+            ScoringError::EmptyGroup(group) => {
+                write!(f, "group {} has scoring but no tests", group)
+            }
+        }
+    }
+}
+
+impl error::Error for ScoringError {}
+
+/// The scoring groups of a package, checked against its tests.
+pub struct Scoring {
+    /// Groups in the order of their first test.
+    groups: Vec<ScoredGroup>,
+}
+
+struct ScoredGroup {
+    name: String,
+    scoring: GroupScoring,
+    /// Indices of the tests of the group.
+    tests: Vec<usize>,
+}
+
+/// Score of a group in a test run.
+#[derive(Clone, Debug)]
+pub struct GroupScore {
+    pub name: String,
+    pub policy: ScoringPolicy,
+    pub points: f64,
+    pub max_points: f64,
+    /// Whether all tests of the group and of its dependencies are fully
+    /// accepted.
+    pub passed: bool,
+    /// A dependency that did not pass, so the group scores nothing.
+    pub failed_dependency: Option<String>,
+}
+
+#[derive(Clone, Debug)]
+pub struct Score {
+    pub groups: Vec<GroupScore>,
+    pub points: f64,
+    pub max_points: f64,
+}
+
+/// Formats points without trailing zeros, e.g. `30` or `12.5`.
+pub fn format_points(points: f64) -> String {
+    let formatted = format!("{:.2}", points);
+    formatted
+        .trim_end_matches('0')
+        .trim_end_matches('.')
+        .to_string()
+}
+
+impl Scoring {
+    /// Checks the `groups` config against `tests`. Returns `None` if no groups
+    /// have scoring.
+    pub fn new(
+        groups: &BTreeMap<String, GroupScoring>,
+        tests: &[TestCase],
+    ) -> Result<Option<Scoring>, ScoringError> {
+        if groups.is_empty() {
+            return Ok(None);
+        }
+        let mut scored: Vec<ScoredGroup> = vec![];
+        for (index, test) in tests.iter().enumerate() {
+            let name = match test.group() {
+                Some(name) if groups.contains_key(&name) => name,
+                _ => continue,
+            };
+            match scored.iter_mut().find(|group| group.name == name) {
+                Some(group) => group.tests.push(index),
+                None => scored.push(ScoredGroup {
+                    scoring: groups[&name].clone(),
+                    name,
+                    tests: vec![index],
+                }),
+            }
+        }
+        for (name, scoring) in groups {
+            if !scored.iter().any(|group| group.name == *name) {
+                return Err(ScoringError::EmptyGroup(name.clone()));
+            }
+            for dependency in &scoring.depends_on {
+                if !groups.contains_key(dependency) {
+                    return Err(ScoringError::UnknownDependency {
+                        group: name.clone(),
+                        dependency: dependency.clone(),
+                    });
+                }
+            }
+        }
+        for name in groups.keys() {
+            check_cycle(groups, name, &mut vec![])?;
+        }
+        Ok(Some(Scoring { groups: scored }))
+    }
+
+    /// Scores `results`, the result of every test or `None` for tests that
+    /// were not judged.
+    pub fn score(&self, results: &[Option<TestResult>]) -> Score {
+        let test_scores = |group: &ScoredGroup| -> Vec<f64> {
+            group
+                .tests
+                .iter()
+                .map(|index| results[*index].as_ref().map_or(0.0, |result| result.score))
+                .collect()
+        };
+        let fully_accepted: BTreeMap<&str, bool> = self
+            .groups
+            .iter()
+            .map(|group| {
+                let accepted = test_scores(group).iter().all(|score| *score >= 1.0);
+                (group.name.as_str(), accepted)
+            })
+            .collect();
+        let mut groups = vec![];
+        for group in &self.groups {
+            let scores = test_scores(group);
+            let scoring = &group.scoring;
+            let (points, max_points) = match scoring.policy {
+                ScoringPolicy::AllOrNothing => {
+                    let all = scores.iter().all(|score| *score >= 1.0);
+                    (if all { scoring.points } else { 0.0 }, scoring.points)
+                }
+                ScoringPolicy::Min => {
+                    let min = scores.iter().copied().fold(1.0, f64::min);
+                    (scoring.points * min, scoring.points)
+                }
+                ScoringPolicy::Sum => (
+                    scoring.points * scores.iter().sum::<f64>(),
+                    scoring.points * scores.len() as f64,
+                ),
+                ScoringPolicy::Average => (
+                    scoring.points * scores.iter().sum::<f64>() / scores.len() as f64,
+                    scoring.points,
+                ),
+            };
+            let failed_dependency = scoring
+                .depends_on
+                .iter()
+                .find(|dependency| !self.passed(dependency, &fully_accepted))
+                .cloned();
+            groups.push(GroupScore {
+                name: group.name.clone(),
+                policy: scoring.policy,
+                points: if failed_dependency.is_some() {
+                    0.0
+                } else {
+                    points
+                },
+                max_points,
+                passed: self.passed(&group.name, &fully_accepted),
+                failed_dependency,
+            });
+        }
+        Score {
+            points: groups.iter().map(|group| group.points).sum(),
+            max_points: groups.iter().map(|group| group.max_points).sum(),
+            groups,
+        }
+    }
+
+    /// Whether the group and all groups it depends on are fully accepted.
+    fn passed(&self, name: &str, fully_accepted: &BTreeMap<&str, bool>) -> bool {
+        let group = self
+            .groups
+            .iter()
+            .find(|group| group.name == name)
+            .expect("dependencies are checked");
+        fully_accepted[name]
+            && group
+                .scoring
+                .depends_on
+                .iter()
+                .all(|dependency| self.passed(dependency, fully_accepted))
+    }
+}
+
+fn check_cycle(
+    groups: &BTreeMap<String, GroupScoring>,
+    name: &str,
+    path: &mut Vec<String>,
+) -> Result<(), ScoringError> {
+    if path.iter().any(|group| group == name) {
+        return Err(ScoringError::DependencyCycle(name.to_string()));
+    }
+    path.push(name.to_string());
+    for dependency in &groups[name].depends_on {
+        check_cycle(groups, dependency, path)?;
+    }
+    path.pop();
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::judge::Verdict;
+    use std::path::PathBuf;
+    use std::time::Duration;
+
+    fn test_case(name: &str) -> TestCase {
+        TestCase {
+            name: PathBuf::from(name),
+            stem: PathBuf::from(name.trim_end_matches(".in")),
+            input: PathBuf::from(name),
+            output: None,
+        }
+    }
+
+    fn group(points: f64, policy: ScoringPolicy, depends_on: &[&str]) -> GroupScoring {
+        GroupScoring {
+            points,
+            policy,
+            depends_on: depends_on.iter().map(|group| group.to_string()).collect(),
+        }
+    }
+
+    fn result(score: f64) -> Option<TestResult> {
+        Some(TestResult {
+            verdict: if score >= 1.0 {
+                Verdict::Accepted
+            } else {
+                Verdict::WrongAnswer
+            },
+            score,
+            time: Duration::ZERO,
+            memory: None,
+            outputs: None,
+        })
+    }
+
+    fn tests() -> Vec<TestCase> {
+        ["a/1.in", "a/2.in", "b/1.in", "b/2.in", "free.in"]
+            .iter()
+            .map(|name| test_case(name))
+            .collect()
+    }
+
+    /// Scores of groups `a` and `b` with `policy` for the test `scores`.
+    fn score_groups(policy: ScoringPolicy, depends_on: &[&str], scores: [f64; 4]) -> Score {
+        let groups = BTreeMap::from([
+            ("a".to_string(), group(10.0, policy, &[])),
+            ("b".to_string(), group(20.0, ScoringPolicy::Sum, depends_on)),
+        ]);
+        let scoring = Scoring::new(&groups, &tests()).unwrap().unwrap();
+        let mut results: Vec<Option<TestResult>> = scores.iter().map(|s| result(*s)).collect();
+        results.push(None);
+        scoring.score(&results)
+    }
+
+    #[test]
+    fn no_groups_means_no_scoring() {
+        assert!(Scoring::new(&BTreeMap::new(), &tests()).unwrap().is_none());
+    }
+
+    #[test]
+    fn policies_combine_test_scores() {
+        let scores = [1.0, 0.5, 1.0, 0.0];
+        let points = |policy| score_groups(policy, &[], scores).groups[0].points;
+        assert_eq!(points(ScoringPolicy::AllOrNothing), 0.0);
+        assert_eq!(points(ScoringPolicy::Min), 5.0);
+        assert_eq!(points(ScoringPolicy::Average), 7.5);
+        let score = score_groups(ScoringPolicy::Sum, &[], scores);
+        assert_eq!(score.groups[0].points, 15.0);
+        assert_eq!(score.groups[0].max_points, 20.0);
+        assert_eq!(score.groups[1].points, 20.0);
+        assert_eq!(score.points, 35.0);
+        assert_eq!(score.max_points, 60.0);
+    }
+
+    #[test]
+    fn failed_dependency_scores_nothing() {
+        let score = score_groups(ScoringPolicy::AllOrNothing, &["a"], [1.0, 0.0, 1.0, 1.0]);
+        assert_eq!(score.groups[1].points, 0.0);
+        assert_eq!(score.groups[1].failed_dependency.as_deref(), Some("a"));
+        assert!(!score.groups[1].passed);
+
+        let score = score_groups(ScoringPolicy::AllOrNothing, &["a"], [1.0, 1.0, 1.0, 1.0]);
+        assert_eq!(score.groups[1].points, 40.0);
+        assert!(score.groups[1].passed);
+    }
+
+    #[test]
+    fn unjudged_tests_score_nothing() {
+        let groups = BTreeMap::from([("a".to_string(), group(10.0, ScoringPolicy::Sum, &[]))]);
+        let scoring = Scoring::new(&groups, &tests()).unwrap().unwrap();
+        let results = vec![result(1.0), None, None, None, None];
+        assert_eq!(scoring.score(&results).points, 10.0);
+    }
+
+    #[test]
+    fn unknown_dependency_is_an_error() {
+        let groups = BTreeMap::from([(
+            "a".to_string(),
+            group(10.0, ScoringPolicy::AllOrNothing, &["c"]),
+        )]);
+        assert!(matches!(
+            Scoring::new(&groups, &tests()),
+            Err(ScoringError::UnknownDependency { group, dependency })
+                if group == "a" && dependency == "c"
+        ));
+    }
+
+    #[test]
+    fn dependency_cycle_is_an_error() {
+        let groups = BTreeMap::from([
+            (
+                "a".to_string(),
+                group(10.0, ScoringPolicy::AllOrNothing, &["b"]),
+            ),
+            (
+                "b".to_string(),
+                group(10.0, ScoringPolicy::AllOrNothing, &["a"]),
+            ),
+        ]);
+        assert!(matches!(
+            Scoring::new(&groups, &tests()),
+            Err(ScoringError::DependencyCycle(_))
+        ));
+        let groups = BTreeMap::from([(
+            "a".to_string(),
+            group(10.0, ScoringPolicy::AllOrNothing, &["a"]),
+        )]);
+        assert!(matches!(
+            Scoring::new(&groups, &tests()),
+            Err(ScoringError::DependencyCycle(group)) if group == "a"
+        ));
+    }
+
+    #[test]
+    fn group_without_tests_is_an_error() {
+        let groups = BTreeMap::from([(
+            "typo".to_string(),
+            group(10.0, ScoringPolicy::AllOrNothing, &[]),
+        )]);
+        assert!(matches!(
+            Scoring::new(&groups, &tests()),
+            Err(ScoringError::EmptyGroup(group)) if group == "typo"
+        ));
+    }
+
+    #[test]
+    fn points_are_formatted_without_trailing_zeros() {
+        assert_eq!(format_points(30.0), "30");
+        assert_eq!(format_points(12.5), "12.5");
+        assert_eq!(format_points(1.0 / 3.0), "0.33");
+    }
+}
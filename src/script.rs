@@ -0,0 +1,429 @@
+//! Generation scripts in the style of Polygon, saying which generator writes
+//! every test:
+//!
+//! ```text
+//! # comments start with a hash
+//! gen_random 10 5 > $
+//! <#list 1..10 as i>
+//! gen_tree ${i} 100000 > $
+//! </#list>
+//! gen_maxtest > edge/max
+//! ```
+//!
+//! A line runs a generator with the given arguments and writes its output to
+//! the test after `>`, where `$` is the next numbered test. Numbered tests are
+//! named `1`, `2`, ... in script order, named tests like `edge/max` do not
+//! take a number. Loops go over inclusive ranges like `1..10` or ranges
+//! without their end like `0..<n`, setting a variable used as `${i}`. A range
+//! has at most [`MAX_RANGE_SIZE`] values.
+
+use crate::naming::{check_name, NamingError};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::PathBuf;
+use std::{error, fmt};
+
+/// Largest number of values of a loop range, so that a typo like
+/// `1..1000000000` fails instead of running for ages.
+pub const MAX_RANGE_SIZE: i64 = 100_000;
+
+/// A test written by a generator of the script.
+#[derive(Clone, Debug)]
+pub struct ScriptTest {
+    pub name: PathBuf,
+    pub generator: String,
+    pub args: Vec<String>,
+}
+
+#[derive(Debug)]
+pub enum ScriptError {
+    Syntax { line: usize, message: String },
+    UnknownGenerator { line: usize, generator: String },
+    UnknownVariable { line: usize, variable: String },
+    Naming { line: usize, error: NamingError },
+}
+
+impl fmt::Display for ScriptError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScriptError::Syntax { line, message } => {
+                write!(f, "line {} of the script: {}", line, message)
+            }
+            ScriptError::UnknownGenerator { line, generator } => {
+                write!(
+                    f,
+                    "line {} of the script: unknown generator {}",
+                    line, generator
+                )
+            }
+            ScriptError::UnknownVariable { line, variable } => {
+                write!(
+                    f,
+                    "line {} of the script: unknown variable {}",
+                    line, variable
+                )
+            }
+            ScriptError::Naming { line, error } => {
+                write!(f, "line {} of the script: {}", line, error)
+            }
+        }
+    }
+}
+
+impl error::Error for ScriptError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            ScriptError::Naming { error, .. } => Some(error),
+            _ => None,
+        }
+    }
+}
+
+fn syntax(line: usize, message: &str) -> ScriptError {
+    ScriptError::Syntax {
+        line,
+        message: message.to_string(),
+    }
+}
+
+enum Statement<'a> {
+    Command {
+        line: usize,
+        text: &'a str,
+    },
+    Loop {
+        line: usize,
+        variable: &'a str,
+        /// Start and end of the range, which may use variables.
+        range: (&'a str, &'a str),
+        inclusive: bool,
+        body: Vec<Statement<'a>>,
+    },
+}
+
+/// Parses the statements until `</#list>` or the end of the script.
+fn parse_block<'a>(
+    lines: &mut impl Iterator<Item = (usize, &'a str)>,
+    opening: Option<usize>,
+) -> Result<Vec<Statement<'a>>, ScriptError> {
+    let mut statements = vec![];
+    while let Some((line, text)) = lines.next() {
+        if text.is_empty() || text.starts_with('#') {
+            continue;
+        }
+        if text == "</#list>" {
+            return match opening {
+                Some(_) => Ok(statements),
+                None => Err(syntax(line, "</#list> without a matching <#list>")),
+            };
+        }
+        let header = match text
+            .strip_prefix("<#list ")
+            .and_then(|text| text.strip_suffix('>'))
+        {
+            Some(header) => header,
+            None if text.starts_with("<#") => return Err(syntax(line, "unknown directive")),
+            None => {
+                statements.push(Statement::Command { line, text });
+                continue;
+            }
+        };
+        let (range, variable) = header
+            .split_once(" as ")
+            .ok_or_else(|| syntax(line, "expected <#list FROM..TO as VARIABLE>"))?;
+        let (from, to) = range
+            .trim()
+            .split_once("..")
+            .ok_or_else(|| syntax(line, "expected a range like 1..10 or 0..<10"))?;
+        let (to, inclusive) = match to.strip_prefix('<') {
+            Some(to) => (to, false),
+            None => (to, true),
+        };
+        let variable = variable.trim();
+        if variable.is_empty() || !variable.chars().all(|c| c.is_alphanumeric() || c == '_') {
+            return Err(syntax(line, "invalid loop variable name"));
+        }
+        statements.push(Statement::Loop {
+            line,
+            variable,
+            range: (from.trim(), to.trim()),
+            inclusive,
+            body: parse_block(lines, Some(line))?,
+        });
+    }
+    match opening {
+        Some(line) => Err(syntax(line, "<#list> without a matching </#list>")),
+        None => Ok(statements),
+    }
+}
+
+/// `text` with the `${variable}` references replaced by their values.
+fn substitute(
+    text: &str,
+    variables: &HashMap<&str, i64>,
+    line: usize,
+) -> Result<String, ScriptError> {
+    let mut result = String::new();
+    let mut rest = text;
+    while let Some(start) = rest.find("${") {
+        result.push_str(&rest[..start]);
+        let end = rest[start..]
+            .find('}')
+            .ok_or_else(|| syntax(line, "unclosed ${"))?;
+        let variable = &rest[start + 2..start + end];
+        let value = variables
+            .get(variable)
+            .ok_or_else(|| ScriptError::UnknownVariable {
+                line,
+                variable: variable.to_string(),
+            })?;
+        result.push_str(&value.to_string());
+        rest = &rest[start + end + 1..];
+    }
+    result.push_str(rest);
+    Ok(result)
+}
+
+struct Evaluation<'a, T> {
+    generators: &'a BTreeMap<String, T>,
+    variables: HashMap<&'a str, i64>,
+    names: HashSet<PathBuf>,
+    /// Number of tests written to `$` so far.
+    numbered: u64,
+    tests: Vec<ScriptTest>,
+}
+
+impl<'a, T> Evaluation<'a, T> {
+    fn run(&mut self, statements: &[Statement<'a>]) -> Result<(), ScriptError> {
+        for statement in statements {
+            match statement {
+                Statement::Command { line, text } => self.command(*line, text)?,
+                Statement::Loop {
+                    line,
+                    variable,
+                    range,
+                    inclusive,
+                    body,
+                } => {
+                    let bound = |text: &str| -> Result<i64, ScriptError> {
+                        substitute(text, &self.variables, *line)?
+                            .parse()
+                            .map_err(|_| syntax(*line, "range bounds have to be integers"))
+                    };
+                    let (from, to) = (bound(range.0)?, bound(range.1)?);
+                    let size = i128::from(to) - i128::from(from) + i128::from(*inclusive);
+                    if size > i128::from(MAX_RANGE_SIZE) {
+                        return Err(ScriptError::Syntax {
+                            line: *line,
+                            message: format!(
+                                "range {}..{}{} has more than {} values",
+                                from,
+                                if *inclusive { "" } else { "<" },
+                                to,
+                                MAX_RANGE_SIZE
+                            ),
+                        });
+                    }
+                    let shadowed = self.variables.get(variable).copied();
+                    // Values stay between the bounds, so counting from `from` cannot
+                    // overflow.
+                    for offset in 0..size.max(0) as i64 {
+                        self.variables.insert(variable, from + offset);
+                        self.run(body)?;
+                    }
+                    match shadowed {
+                        Some(value) => self.variables.insert(variable, value),
+                        None => self.variables.remove(variable),
+                    };
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn command(&mut self, line: usize, text: &str) -> Result<(), ScriptError> {
+        let text = substitute(text, &self.variables, line)?;
+        let (command, target) = text
+            .rsplit_once('>')
+            .ok_or_else(|| syntax(line, "expected GENERATOR ARGS > TEST"))?;
+        let mut words = command.split_whitespace().map(str::to_string);
+        let generator = words
+            .next()
+            .ok_or_else(|| syntax(line, "missing generator name"))?;
+        if !self.generators.contains_key(&generator) {
+            return Err(ScriptError::UnknownGenerator { line, generator });
+        }
+        let name = match target.trim() {
+            "$" => {
+                self.numbered += 1;
+                PathBuf::from(self.numbered.to_string())
+            }
+            name => check_name(name).map_err(|error| ScriptError::Naming { line, error })?,
+        };
+        if !self.names.insert(name.clone()) {
+            return Err(ScriptError::Naming {
+                line,
+                error: NamingError::DuplicateName(name),
+            });
+        }
+        self.tests.push(ScriptTest {
+            name,
+            generator,
+            args: words.collect(),
+        });
+        Ok(())
+    }
+}
+
+/// Parses and runs `script`, returning the tests it writes in script order.
+/// Every generator used has to be a key of `generators`.
+pub fn parse_script<T>(
+    script: &str,
+    generators: &BTreeMap<String, T>,
+) -> Result<Vec<ScriptTest>, ScriptError> {
+    let mut lines = script
+        .lines()
+        .enumerate()
+        .map(|(index, text)| (index + 1, text.trim()));
+    let statements = parse_block(&mut lines, None)?;
+    let mut evaluation = Evaluation {
+        generators,
+        variables: HashMap::new(),
+        names: HashSet::new(),
+        numbered: 0,
+        tests: vec![],
+    };
+    evaluation.run(&statements)?;
+    Ok(evaluation.tests)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(script: &str) -> Result<Vec<ScriptTest>, ScriptError> {
+        let generators = BTreeMap::from([("gen".to_string(), ()), ("tree".to_string(), ())]);
+        parse_script(script, &generators)
+    }
+
+    fn lines(script: &str) -> Vec<String> {
+        parse(script)
+            .unwrap()
+            .iter()
+            .map(|test| {
+                let command = std::iter::once(&test.generator).chain(&test.args);
+                let command: Vec<&str> = command.map(String::as_str).collect();
+                format!("{} > {}", command.join(" "), test.name.display())
+            })
+            .collect()
+    }
+
+    fn error_line(script: &str) -> usize {
+        match parse(script).unwrap_err() {
+            ScriptError::Syntax { line, .. }
+            | ScriptError::UnknownGenerator { line, .. }
+            | ScriptError::UnknownVariable { line, .. }
+            | ScriptError::Naming { line, .. } => line,
+        }
+    }
+
+    #[test]
+    fn commands_name_numbered_and_named_tests() {
+        let script = "# comment\n\ngen 10 5 > $\ntree > edge/max\ngen 1 > $\n";
+        assert_eq!(
+            lines(script),
+            ["gen 10 5 > 1", "tree > edge/max", "gen 1 > 2"]
+        );
+    }
+
+    #[test]
+    fn loops_cover_their_ranges() {
+        let script = "<#list 1..3 as i>\ngen ${i} > $\n</#list>\n<#list 0..<2 as j>\ntree ${j} > t${j}\n</#list>";
+        assert_eq!(
+            lines(script),
+            [
+                "gen 1 > 1",
+                "gen 2 > 2",
+                "gen 3 > 3",
+                "tree 0 > t0",
+                "tree 1 > t1"
+            ]
+        );
+    }
+
+    #[test]
+    fn nested_loops_use_outer_variables() {
+        let script =
+            "<#list 1..2 as i>\n<#list ${i}..2 as j>\ngen ${i} ${j} > $\n</#list>\n</#list>";
+        assert_eq!(lines(script), ["gen 1 1 > 1", "gen 1 2 > 2", "gen 2 2 > 3"]);
+    }
+
+    #[test]
+    fn empty_ranges_write_nothing() {
+        assert!(parse("<#list 5..4 as i>\ngen ${i} > $\n</#list>")
+            .unwrap()
+            .is_empty());
+        assert!(parse("<#list 0..<0 as i>\ngen ${i} > $\n</#list>")
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn loop_variables_are_scoped() {
+        assert!(matches!(
+            parse("<#list 1..1 as i>\ngen > $\n</#list>\ngen ${i} > $"),
+            Err(ScriptError::UnknownVariable { line: 4, variable }) if variable == "i"
+        ));
+    }
+
+    #[test]
+    fn errors_point_to_their_line() {
+        assert_eq!(error_line("gen > $\n<#list 1..2 as i>\ngen > $"), 2);
+        assert_eq!(error_line("gen > $\n</#list>"), 2);
+        assert_eq!(error_line("<#if x>"), 1);
+        assert_eq!(error_line("<#list 1-2 as i>\n</#list>"), 1);
+        assert_eq!(error_line("<#list 1..2 as a b>\n</#list>"), 1);
+        assert_eq!(error_line("<#list 1..n as i>\n</#list>"), 1);
+        assert_eq!(error_line("gen 1 2"), 1);
+        assert_eq!(error_line("gen ${i > $"), 1);
+        assert_eq!(error_line("\nunknown > $"), 2);
+        assert_eq!(error_line("gen > ../x"), 1);
+        assert_eq!(error_line("gen > a\ngen > a"), 2);
+        assert_eq!(error_line("gen > 1\ngen > $"), 2);
+    }
+
+    #[test]
+    fn ranges_are_capped() {
+        assert_eq!(
+            parse(&format!(
+                "<#list 1..{} as i>\ngen ${{i}} > $\n</#list>",
+                MAX_RANGE_SIZE
+            ))
+            .unwrap()
+            .len(),
+            MAX_RANGE_SIZE as usize
+        );
+        for range in [
+            "1..1000000000",
+            "0..<100001",
+            "-9223372036854775808..9223372036854775807",
+        ] {
+            let error = parse(&format!("gen > a\n<#list {} as i>\n</#list>", range)).unwrap_err();
+            assert_eq!(
+                error.to_string(),
+                format!(
+                    "line 2 of the script: range {} has more than 100000 values",
+                    range
+                )
+            );
+        }
+        assert!(parse(
+            "<#list -9223372036854775808..<-9223372036854775808 as i>\ngen ${i} > $\n</#list>"
+        )
+        .unwrap()
+        .is_empty());
+        assert_eq!(
+            lines("<#list 9223372036854775806..9223372036854775807 as i>\ngen ${i} > $\n</#list>"),
+            ["gen 9223372036854775806 > 1", "gen 9223372036854775807 > 2"]
+        );
+    }
+}
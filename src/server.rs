@@ -0,0 +1,534 @@
+//! A small judge server for training sessions, accepting submissions to the
+//! loaded packages over a JSON API:
+//!
+//! - `GET /problems` lists the problems,
+//! - `POST /submissions` with `{"user", "problem", "filename", "source"}`
+//!   queues a submission, its language found from the file name,
+//! - `GET /submissions` and `GET /submissions/{id}` show the submissions with
+//!   their results,
+//! - `GET /leaderboard` ranks the users by the sum of their best scores.
+//!
+//! Submissions are judged one at a time in submission order. Everything runs
+//! locally, the server needs no network access beyond its own address.
+
+use crate::config::Options;
+use crate::judge::Judge;
+use crate::score::Scoring;
+use crate::submissions::{judge_submission, Submission, SubmissionResult};
+use indicatif::ProgressBar;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::{mpsc, Arc, Mutex, MutexGuard};
+use std::{env, error, fmt, fs, io, thread};
+use tiny_http::{Header, Method, Request, Response};
+use uuid::Uuid;
+
+/// Address the server listens on by default, reachable only from this machine.
+pub const DEFAULT_ADDRESS: &str = "127.0.0.1:8080";
+/// Submissions with a larger request body are rejected.
+const MAX_BODY_BYTES: u64 = 1024 * 1024;
+
+#[derive(Debug)]
+pub enum ServeError {
+    IO(io::Error),
+    Bind {
+        address: String,
+        error: Box<dyn error::Error + Send + Sync>,
+    },
+}
+
+impl fmt::Display for ServeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ServeError::IO(error) => write!(f, "cannot store submissions: {}", error),
+            ServeError::Bind { address, error } => {
+                write!(f, "cannot listen on {}: {}", address, error)
+            }
+        }
+    }
+}
+
+impl error::Error for ServeError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            ServeError::IO(error) => Some(error),
+            ServeError::Bind { error, .. } => Some(error.as_ref()),
+        }
+    }
+}
+
+impl From<io::Error> for ServeError {
+    fn from(error: io::Error) -> Self {
+        ServeError::IO(error)
+    }
+}
+
+/// A generated package submissions can be sent to.
+pub struct Problem {
+    pub name: String,
+    pub package: PathBuf,
+    /// Options of the package, for its time limit and languages.
+    pub options: Options,
+    pub judge: Arc<Judge>,
+    pub scoring: Option<Scoring>,
+    /// Number of tests run in parallel.
+    pub jobs: usize,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+enum Status {
+    Queued,
+    Judging,
+    Judged,
+    /// The package could not judge the submission, e.g. its verifier failed.
+    Failed,
+}
+
+struct Entry {
+    id: usize,
+    user: String,
+    problem: usize,
+    submission: Submission,
+    status: Status,
+    result: Option<SubmissionResult>,
+    error: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct NewSubmission {
+    user: String,
+    problem: String,
+    filename: String,
+    source: String,
+}
+
+struct State {
+    problems: Vec<Problem>,
+    entries: Mutex<Vec<Entry>>,
+    /// Directory with a subdirectory per submission holding its source.
+    dir: PathBuf,
+}
+
+impl State {
+    fn lock_entries(&self) -> MutexGuard<'_, Vec<Entry>> {
+        self.entries
+            .lock()
+            .unwrap_or_else(|error| error.into_inner())
+    }
+
+    fn entry_json(&self, entry: &Entry) -> Value {
+        let mut value = json!({
+            "id": entry.id,
+            "user": entry.user,
+            "problem": self.problems[entry.problem].name,
+            "filename": entry.submission.name,
+            "language": entry.submission.language,
+            "status": entry.status,
+        });
+        if let Some(result) = &entry.result {
+            value["verdict"] = json!(result.verdict.code());
+            value["score"] = json!(result.score);
+            value["maxScore"] = json!(result.max_score);
+            value["maxTime"] = json!(result.max_time.map(|time| time.as_millis()));
+            value["compileLog"] = json!(result.compile_log);
+        }
+        if let Some(error) = &entry.error {
+            value["error"] = json!(error);
+        }
+        value
+    }
+
+    fn problems_json(&self) -> Value {
+        self.problems
+            .iter()
+            .map(|problem| {
+                json!({
+                    "name": problem.name,
+                    "tests": problem.judge.tests().len(),
+                    "timeLimit": problem.options.time_limit,
+                    "memoryLimit": problem.options.memory_limit,
+                })
+            })
+            .collect()
+    }
+
+    /// Users sorted by the sum of their best score on every problem, ties
+    /// broken by name.
+    fn leaderboard_json(&self) -> Value {
+        let mut best: BTreeMap<&str, BTreeMap<&str, f64>> = BTreeMap::new();
+        let entries = self.lock_entries();
+        for entry in entries.iter() {
+            if let Some(result) = &entry.result {
+                let score = best
+                    .entry(&entry.user)
+                    .or_default()
+                    .entry(&self.problems[entry.problem].name)
+                    .or_insert(0.0);
+                *score = score.max(result.score);
+            }
+        }
+        let mut rows: Vec<(&str, f64, BTreeMap<&str, f64>)> = best
+            .into_iter()
+            .map(|(user, scores)| (user, scores.values().sum(), scores))
+            .collect();
+        rows.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(b.0)));
+        rows.into_iter()
+            .enumerate()
+            .map(|(index, (user, score, problems))| {
+                json!({
+                    "rank": index + 1,
+                    "user": user,
+                    "score": score,
+                    "problems": problems,
+                })
+            })
+            .collect()
+    }
+
+    /// Stores the source of `new` and adds it to the entries, returning its id.
+    fn submit(&self, new: NewSubmission) -> Result<usize, (u16, String)> {
+        let problem = self
+            .problems
+            .iter()
+            .position(|problem| problem.name == new.problem)
+            .ok_or_else(|| (404, format!("unknown problem {}", new.problem)))?;
+        if new.user.trim().is_empty() {
+            return Err((400, "user must not be empty".to_string()));
+        }
+        if Path::new(&new.filename).file_name() != Some(new.filename.as_ref()) {
+            return Err((400, format!("invalid file name {}", new.filename)));
+        }
+        let mut entries = self.lock_entries();
+        let id = entries.len() + 1;
+        let dir = self.dir.join(id.to_string());
+        let path = dir.join(&new.filename);
+        let (language, program) = self.problems[problem]
+            .options
+            .submission_language(&path)
+            .map_err(|_| (400, format!("unknown language of {}", new.filename)))?;
+        let stored = fs::create_dir_all(&dir).and_then(|_| fs::write(&path, &new.source));
+        stored.map_err(|error| (500, format!("cannot store the submission: {}", error)))?;
+        entries.push(Entry {
+            id,
+            user: new.user,
+            problem,
+            submission: Submission {
+                name: new.filename,
+                path,
+                language,
+                program,
+            },
+            status: Status::Queued,
+            result: None,
+            error: None,
+        });
+        Ok(id)
+    }
+
+    /// Judges the submission with `id`, recording its result.
+    fn judge(&self, id: usize) {
+        let (problem, submission) = {
+            let mut entries = self.lock_entries();
+            let entry = &mut entries[id - 1];
+            entry.status = Status::Judging;
+            (&self.problems[entry.problem], entry.submission.clone())
+        };
+        let result = judge_submission(
+            &problem.judge,
+            problem.scoring.as_ref(),
+            &submission,
+            &problem.package,
+            problem.jobs,
+            &ProgressBar::hidden(),
+        );
+        let mut entries = self.lock_entries();
+        let entry = &mut entries[id - 1];
+        match result {
+            Ok(result) => {
+                entry.status = Status::Judged;
+                entry.result = Some(result);
+            }
+            Err(error) => {
+                entry.status = Status::Failed;
+                entry.error = Some(error.to_string());
+            }
+        }
+    }
+}
+
+fn json_response(status: u16, body: &Value) -> Response<io::Cursor<Vec<u8>>> {
+    let header = Header::from_bytes("Content-Type", "application/json")
+        .expect("the content type header is valid");
+    Response::from_string(body.to_string() + "\n")
+        .with_status_code(status)
+        .with_header(header)
+}
+
+fn error_response(status: u16, message: &str) -> Response<io::Cursor<Vec<u8>>> {
+    json_response(status, &json!({ "error": message }))
+}
+
+fn handle(
+    state: &State,
+    queue: &mpsc::Sender<usize>,
+    request: &mut Request,
+) -> Response<io::Cursor<Vec<u8>>> {
+    let url = request.url().to_string();
+    let path = url
+        .split('?')
+        .next()
+        .unwrap_or_default()
+        .trim_end_matches('/');
+    match (request.method(), path) {
+        (Method::Get, "/problems") => json_response(200, &state.problems_json()),
+        (Method::Get, "/leaderboard") => json_response(200, &state.leaderboard_json()),
+        (Method::Get, "/submissions") => {
+            let entries = state.lock_entries();
+            let body: Value = entries
+                .iter()
+                .map(|entry| state.entry_json(entry))
+                .collect();
+            json_response(200, &body)
+        }
+        (Method::Post, "/submissions") => {
+            let mut body = vec![];
+            let read = request
+                .as_reader()
+                .take(MAX_BODY_BYTES + 1)
+                .read_to_end(&mut body);
+            if let Err(error) = read {
+                return error_response(400, &format!("cannot read the request: {}", error));
+            }
+            if body.len() as u64 > MAX_BODY_BYTES {
+                return error_response(413, "submission is too large");
+            }
+            let new: NewSubmission = match serde_json::from_slice(&body) {
+                Ok(new) => new,
+                Err(error) => {
+                    return error_response(400, &format!("invalid submission: {}", error))
+                }
+            };
+            match state.submit(new) {
+                Ok(id) => {
+                    // The worker only stops with the server.
+                    let _ = queue.send(id);
+                    json_response(202, &json!({ "id": id }))
+                }
+                Err((status, message)) => error_response(status, &message),
+            }
+        }
+        (Method::Get, path) => {
+            let entry = path
+                .strip_prefix("/submissions/")
+                .and_then(|id| id.parse::<usize>().ok());
+            let entries = state.lock_entries();
+            match entry.and_then(|id| entries.get(id.wrapping_sub(1))) {
+                Some(entry) => json_response(200, &state.entry_json(entry)),
+                None => error_response(404, "not found"),
+            }
+        }
+        _ => error_response(405, "method not allowed"),
+    }
+}
+
+/// Serves `problems` on `address` until the process is stopped.
+pub fn serve(problems: Vec<Problem>, address: &str) -> Result<(), ServeError> {
+    let server = tiny_http::Server::http(address).map_err(|error| ServeError::Bind {
+        address: address.to_string(),
+        error,
+    })?;
+    serve_on(server, problems)
+}
+
+fn serve_on(server: tiny_http::Server, problems: Vec<Problem>) -> Result<(), ServeError> {
+    let dir = env::temp_dir()
+        .join("iobot/serve")
+        .join(Uuid::new_v4().to_string());
+    fs::create_dir_all(&dir)?;
+    let state = Arc::new(State {
+        problems,
+        entries: Mutex::new(vec![]),
+        dir,
+    });
+    let (queue, queued) = mpsc::channel();
+    let worker = state.clone();
+    thread::spawn(move || {
+        for id in queued {
+            worker.judge(id);
+        }
+    });
+    for mut request in server.incoming_requests() {
+        let response = handle(&state, &queue, &mut request);
+        // A client that went away does not stop the server.
+        let _ = request.respond(response);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::judge::{write_tests, Verdict};
+    use crate::submissions::SubmissionVerdict;
+    use crate::utils::test_dir;
+    use std::time::{Duration, Instant};
+
+    const SUM: &str = "a, b = map(int, input().split())\nprint(a + b)\n";
+
+    /// Starts a server for a problem summing two numbers, returns its URL.
+    fn problem(name: &str) -> Problem {
+        let dir = test_dir();
+        let tests = write_tests(&dir, &[("1", "1 2\n", "3\n"), ("2", "2 2\n", "4\n")]);
+        Problem {
+            name: name.to_string(),
+            package: dir,
+            options: Options::default(),
+            judge: Arc::new(Judge::new(tests, vec![])),
+            scoring: None,
+            jobs: 1,
+        }
+    }
+
+    fn start() -> String {
+        let server = tiny_http::Server::http("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", server.server_addr());
+        let problem = problem("sum");
+        thread::spawn(move || serve_on(server, vec![problem]));
+        url
+    }
+
+    fn submit(url: &str, user: &str, filename: &str, source: &str) -> Result<Value, u16> {
+        let submission = json!({
+            "user": user,
+            "problem": "sum",
+            "filename": filename,
+            "source": source,
+        });
+        match ureq::post(&format!("{}/submissions", url)).send_string(&submission.to_string()) {
+            Ok(response) => Ok(serde_json::from_reader(response.into_reader()).unwrap()),
+            Err(ureq::Error::Status(status, _)) => Err(status),
+            Err(error) => panic!("cannot submit: {}", error),
+        }
+    }
+
+    fn get(url: &str, path: &str) -> Value {
+        let response = ureq::get(&format!("{}{}", url, path)).call().unwrap();
+        serde_json::from_reader(response.into_reader()).unwrap()
+    }
+
+    /// Waits until the submission with `id` is judged.
+    fn judged(url: &str, id: &Value) -> Value {
+        let start = Instant::now();
+        loop {
+            let entry = get(url, &format!("/submissions/{}", id));
+            if entry["status"] != "queued" && entry["status"] != "judging" {
+                return entry;
+            }
+            assert!(start.elapsed() < Duration::from_secs(60), "not judged");
+            thread::sleep(Duration::from_millis(50));
+        }
+    }
+
+    #[test]
+    fn judges_submissions() {
+        let url = start();
+        assert_eq!(get(&url, "/problems")[0]["name"], "sum");
+        let accepted = submit(&url, "ann", "sum.py", SUM).unwrap();
+        let wrong = submit(&url, "bob", "sum.py", "print(3)\n").unwrap();
+
+        let entry = judged(&url, &accepted["id"]);
+        assert_eq!(entry["status"], "judged");
+        assert_eq!(entry["language"], "Python");
+        assert_eq!(entry["verdict"], "AC");
+        assert_eq!(entry["score"], 2.0);
+        assert_eq!(judged(&url, &wrong["id"])["verdict"], "WA");
+
+        let leaderboard = get(&url, "/leaderboard");
+        assert_eq!(leaderboard[0]["user"], "ann");
+        assert_eq!(leaderboard[1]["user"], "bob");
+        assert_eq!(leaderboard[1]["score"], 1.0);
+    }
+
+    #[test]
+    fn rejects_invalid_submissions() {
+        let url = start();
+        assert_eq!(submit(&url, "ann", "../sum.py", SUM), Err(400));
+        assert_eq!(submit(&url, "ann", "sum.rs", SUM), Err(400));
+        assert_eq!(submit(&url, " ", "sum.py", SUM), Err(400));
+        assert_eq!(get(&url, "/submissions"), json!([]));
+    }
+
+    #[test]
+    fn leaderboard_sums_the_best_scores() {
+        let state = State {
+            problems: vec![problem("sum"), problem("max")],
+            entries: Mutex::new(vec![]),
+            dir: test_dir(),
+        };
+        for (user, problem, score) in [
+            ("cat", 0, 1.0),
+            ("bob", 0, 2.0),
+            ("ann", 0, 0.5),
+            ("ann", 0, 1.5),
+            ("ann", 1, 0.5),
+            ("dan", 1, 0.0),
+        ] {
+            let mut entries = state.lock_entries();
+            let id = entries.len() + 1;
+            entries.push(Entry {
+                id,
+                user: user.to_string(),
+                problem,
+                submission: Submission {
+                    name: "sum.py".to_string(),
+                    path: PathBuf::from("sum.py"),
+                    language: "Python".to_string(),
+                    program: crate::config::Program::Python {
+                        path: PathBuf::from("sum.py"),
+                        checksum: None,
+                    },
+                },
+                status: Status::Judged,
+                result: Some(SubmissionResult {
+                    submission: "sum.py".to_string(),
+                    language: "Python".to_string(),
+                    verdict: SubmissionVerdict::Judged(Verdict::WrongAnswer),
+                    score,
+                    max_score: 2.0,
+                    max_time: None,
+                    compile_log: String::new(),
+                }),
+                error: None,
+            });
+        }
+        // Ties are ranked by name, users with no points are still listed.
+        let rows: Vec<(String, f64)> = state
+            .leaderboard_json()
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|row| {
+                (
+                    row["user"].as_str().unwrap().to_string(),
+                    row["score"].as_f64().unwrap(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            rows,
+            [
+                ("ann".to_string(), 2.0),
+                ("bob".to_string(), 2.0),
+                ("cat".to_string(), 1.0),
+                ("dan".to_string(), 0.0)
+            ]
+        );
+        let leaderboard = state.leaderboard_json();
+        assert_eq!(leaderboard[0]["rank"], 1);
+        assert_eq!(leaderboard[0]["problems"], json!({"sum": 1.5, "max": 0.5}));
+    }
+}
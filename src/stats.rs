@@ -0,0 +1,151 @@
+//! Statistics of the inputs of a package, to check that they are distinct and
+//! cover the constraints.
+
+use crate::remote::sha256_hex;
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+/// Upper bound of the smallest bucket of [`InputStats::histogram`], each next
+/// bucket being four times larger.
+const SMALLEST_BUCKET: u64 = 64;
+
+/// An input with the same contents as an earlier one.
+#[derive(Clone, Debug)]
+pub struct Duplicate {
+    pub path: PathBuf,
+    pub original: PathBuf,
+}
+
+/// Number of inputs with a size of at most `max_size` bytes and more than the
+/// bound of the previous bucket.
+#[derive(Clone, Debug)]
+pub struct SizeBucket {
+    pub max_size: u64,
+    pub count: usize,
+}
+
+#[derive(Clone, Debug)]
+pub struct InputStats {
+    /// Inputs with their sizes in bytes, sorted from the smallest.
+    pub sizes: Vec<(PathBuf, u64)>,
+    pub duplicates: Vec<Duplicate>,
+}
+
+impl InputStats {
+    /// Reads the inputs at `paths`. Duplicates are reported against the
+    /// first input with the same contents in the order of `paths`.
+    pub fn collect(paths: &[PathBuf]) -> io::Result<InputStats> {
+        let mut originals: HashMap<String, &PathBuf> = HashMap::new();
+        let mut sizes = vec![];
+        let mut duplicates = vec![];
+        for path in paths {
+            let contents = fs::read(path)?;
+            sizes.push((path.clone(), contents.len() as u64));
+            let original = *originals.entry(sha256_hex(&contents)).or_insert(path);
+            if original != path {
+                duplicates.push(Duplicate {
+                    path: path.clone(),
+                    original: original.clone(),
+                });
+            }
+        }
+        sizes.sort_by_key(|(_, size)| *size);
+        Ok(InputStats { sizes, duplicates })
+    }
+
+    pub fn smallest(&self) -> Option<&(PathBuf, u64)> {
+        self.sizes.first()
+    }
+
+    pub fn largest(&self) -> Option<&(PathBuf, u64)> {
+        self.sizes.last()
+    }
+
+    pub fn median(&self) -> Option<u64> {
+        self.sizes.get(self.sizes.len() / 2).map(|(_, size)| *size)
+    }
+
+    pub fn total(&self) -> u64 {
+        self.sizes.iter().map(|(_, size)| size).sum()
+    }
+
+    /// Input counts in buckets of sizes growing four times, from the bucket of
+    /// the smallest input to the bucket of the largest one.
+    pub fn histogram(&self) -> Vec<SizeBucket> {
+        let mut buckets: Vec<SizeBucket> = vec![];
+        for (_, size) in &self.sizes {
+            let mut max_size = SMALLEST_BUCKET;
+            while max_size < *size {
+                max_size = max_size.saturating_mul(4);
+            }
+            match buckets.last_mut() {
+                Some(bucket) if bucket.max_size == max_size => bucket.count += 1,
+                _ => {
+                    // Sizes are sorted, so empty buckets only appear between
+                    // the last bucket and this one.
+                    let mut empty = buckets
+                        .last()
+                        .map_or(max_size, |bucket| bucket.max_size.saturating_mul(4));
+                    while empty < max_size {
+                        buckets.push(SizeBucket {
+                            max_size: empty,
+                            count: 0,
+                        });
+                        empty = empty.saturating_mul(4);
+                    }
+                    buckets.push(SizeBucket { max_size, count: 1 });
+                }
+            }
+        }
+        buckets
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_dir;
+
+    #[test]
+    fn collects_sizes_and_duplicates() {
+        let dir = test_dir();
+        let inputs = [("a", "x"), ("b", "yy"), ("c", "x"), ("d", "")];
+        let paths: Vec<PathBuf> = inputs
+            .iter()
+            .map(|(name, contents)| {
+                fs::write(dir.join(name), contents).unwrap();
+                dir.join(name)
+            })
+            .collect();
+        let stats = InputStats::collect(&paths).unwrap();
+        assert_eq!(stats.smallest(), Some(&(dir.join("d"), 0)));
+        assert_eq!(stats.largest(), Some(&(dir.join("b"), 2)));
+        assert_eq!(stats.median(), Some(1));
+        assert_eq!(stats.total(), 4);
+        assert_eq!(stats.duplicates.len(), 1);
+        assert_eq!(stats.duplicates[0].path, dir.join("c"));
+        assert_eq!(stats.duplicates[0].original, dir.join("a"));
+    }
+
+    #[test]
+    fn histogram_keeps_empty_buckets_in_between() {
+        let stats = InputStats {
+            sizes: [10, 64, 65, 5000]
+                .iter()
+                .map(|size| (PathBuf::from(size.to_string()), *size))
+                .collect(),
+            duplicates: vec![],
+        };
+        let buckets: Vec<(u64, usize)> = stats
+            .histogram()
+            .iter()
+            .map(|bucket| (bucket.max_size, bucket.count))
+            .collect();
+        assert_eq!(
+            buckets,
+            [(64, 2), (256, 1), (1024, 0), (4096, 0), (16384, 1)]
+        );
+    }
+}
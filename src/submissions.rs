@@ -0,0 +1,416 @@
+//! Judging of contest submissions in bulk, written as a scoreboard with a row
+//! per submission.
+
+use crate::config::{Options, Program};
+use crate::judge::{Judge, JudgeError, JudgeProgress, TestResult, Verdict};
+use crate::runner::{CompileError, ProgramRole, Runner};
+use crate::score::Scoring;
+use serde::Serialize;
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use std::{fmt, fs, io};
+
+/// A submission with its language, found from the file extension.
+#[derive(Clone, Debug)]
+pub struct Submission {
+    pub path: PathBuf,
+    /// File name of the submission, shown on the scoreboard.
+    pub name: String,
+    pub language: String,
+    pub program: Program,
+}
+
+/// Lists the files in `dir` sorted by name as submissions in the languages
+/// of `options`. Files in an unknown language are returned separately.
+pub fn find_submissions(
+    dir: &Path,
+    options: &Options,
+) -> io::Result<(Vec<Submission>, Vec<PathBuf>)> {
+    let mut paths = vec![];
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_file() {
+            paths.push(path.canonicalize()?);
+        }
+    }
+    paths.sort();
+    let mut submissions = vec![];
+    let mut unknown = vec![];
+    for path in paths {
+        match options.submission_language(&path) {
+            Ok((language, program)) => submissions.push(Submission {
+                name: path
+                    .file_name()
+                    .map(|name| name.to_string_lossy().into_owned())
+                    .unwrap_or_default(),
+                path,
+                language,
+                program,
+            }),
+            Err(_) => unknown.push(path),
+        }
+    }
+    Ok((submissions, unknown))
+}
+
+/// Outcome of a submission as reported by online judges.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SubmissionVerdict {
+    CompilationError,
+    /// Verdict of the first test that was not accepted, or accepted.
+    Judged(Verdict),
+}
+
+impl SubmissionVerdict {
+    /// The usual short name of the verdict, e.g. `WA`.
+    pub fn code(&self) -> &'static str {
+        match self {
+            SubmissionVerdict::CompilationError => "CE",
+            SubmissionVerdict::Judged(verdict) => match verdict {
+                Verdict::Accepted => "AC",
+                Verdict::PartiallyAccepted => "PA",
+                Verdict::WrongAnswer => "WA",
+                Verdict::RuntimeError(_) => "RE",
+                Verdict::TimeLimitExceeded => "TLE",
+                Verdict::Skipped => "SK",
+            },
+        }
+    }
+
+    fn from_results(results: &[TestResult]) -> SubmissionVerdict {
+        let verdict = results
+            .iter()
+            .map(|result| result.verdict)
+            .find(|verdict| !verdict.is_accepted() && *verdict != Verdict::Skipped)
+            .unwrap_or(Verdict::Accepted);
+        SubmissionVerdict::Judged(verdict)
+    }
+}
+
+impl fmt::Display for SubmissionVerdict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SubmissionVerdict::CompilationError => write!(f, "compilation error"),
+            SubmissionVerdict::Judged(verdict) => write!(f, "{}", verdict),
+        }
+    }
+}
+
+/// Result of a submission on the scoreboard.
+#[derive(Clone, Debug)]
+pub struct SubmissionResult {
+    pub submission: String,
+    pub language: String,
+    pub verdict: SubmissionVerdict,
+    /// Points of the groups of the package, or the number of accepted tests
+    /// if it has no scoring groups.
+    pub score: f64,
+    pub max_score: f64,
+    /// Longest run of the submission on a test, `None` if it did not compile.
+    pub max_time: Option<Duration>,
+    /// Output of the compiler, empty for interpreted languages.
+    pub compile_log: String,
+}
+
+/// Compiles `submission` and judges it on the tests of `judge`. A submission
+/// that does not compile gets [`SubmissionVerdict::CompilationError`], while
+/// failing to run the compiler at all is an error.
+pub fn judge_submission<P: JudgeProgress + Clone + 'static>(
+    judge: &Arc<Judge>,
+    scoring: Option<&Scoring>,
+    submission: &Submission,
+    package: &Path,
+    jobs: usize,
+    progress: &P,
+) -> Result<SubmissionResult, JudgeError> {
+    let mut result = SubmissionResult {
+        submission: submission.name.clone(),
+        language: submission.language.clone(),
+        verdict: SubmissionVerdict::CompilationError,
+        score: 0.0,
+        max_score: match scoring {
+            Some(scoring) => scoring.score(&vec![None; judge.tests().len()]).max_points,
+            None => judge.tests().len() as f64,
+        },
+        max_time: None,
+        compile_log: String::new(),
+    };
+    let runner = match Runner::build(&submission.program, package) {
+        Ok(runner) => Arc::new(runner),
+        Err(CompileError::Unsuccessful(failure)) => {
+            result.compile_log = failure.stdout + &failure.stderr;
+            return Ok(result);
+        }
+        Err(error) => return Err(JudgeError::Compile(ProgramRole::Solution, error)),
+    };
+    result.compile_log = runner.compile_log().unwrap_or_default().to_string();
+    let results = judge.judge_all(&runner, jobs, progress)?;
+    result.verdict = SubmissionVerdict::from_results(&results);
+    result.max_time = results.iter().map(|result| result.time).max();
+    result.score = match scoring {
+        Some(scoring) => {
+            let results: Vec<Option<TestResult>> = results.into_iter().map(Some).collect();
+            scoring.score(&results).points
+        }
+        None => results.iter().map(|result| result.score).sum(),
+    };
+    Ok(result)
+}
+
+/// A scoreboard row as written to JSON.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Row<'a> {
+    submission: &'a str,
+    language: &'a str,
+    verdict: &'static str,
+    score: f64,
+    max_score: f64,
+    /// In milliseconds.
+    max_time: Option<u128>,
+    compile_log: &'a str,
+}
+
+impl<'a> From<&'a SubmissionResult> for Row<'a> {
+    fn from(result: &'a SubmissionResult) -> Self {
+        Row {
+            submission: &result.submission,
+            language: &result.language,
+            verdict: result.verdict.code(),
+            score: result.score,
+            max_score: result.max_score,
+            max_time: result.max_time.map(|time| time.as_millis()),
+            compile_log: &result.compile_log,
+        }
+    }
+}
+
+/// The scoreboard as a JSON array of rows.
+pub fn to_json(results: &[SubmissionResult]) -> String {
+    let rows: Vec<Row> = results.iter().map(Row::from).collect();
+    serde_json::to_string_pretty(&rows).expect("scoreboard rows serialize to JSON") + "\n"
+}
+
+fn csv_field(text: &str) -> String {
+    if text.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_string()
+    }
+}
+
+/// The scoreboard as CSV with a header row, times in milliseconds.
+pub fn to_csv(results: &[SubmissionResult]) -> String {
+    let mut csv =
+        String::from("submission,language,verdict,score,max_score,max_time,compile_log\n");
+    for result in results {
+        let row = Row::from(result);
+        // Writing to a String cannot fail.
+        let _ = writeln!(
+            csv,
+            "{},{},{},{},{},{},{}",
+            csv_field(row.submission),
+            csv_field(row.language),
+            row.verdict,
+            row.score,
+            row.max_score,
+            row.max_time
+                .map(|time| time.to_string())
+                .unwrap_or_default(),
+            csv_field(row.compile_log)
+        );
+    }
+    csv
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{GroupScoring, ScoringPolicy};
+    use crate::judge::write_tests;
+    use crate::utils::test_dir;
+    use indicatif::ProgressBar;
+    use std::collections::BTreeMap;
+
+    /// Judges the Python `source` on a package with the groups `a`, 5 points
+    /// per test, and `b`, 20 points for all of its tests.
+    fn judge_python(source: &str, scored: bool) -> SubmissionResult {
+        let dir = test_dir();
+        let tests = write_tests(
+            &dir,
+            &[
+                ("a/1", "1 2\n", "3\n"),
+                ("a/2", "2 2\n", "4\n"),
+                ("b/1", "10 20\n", "30\n"),
+            ],
+        );
+        let groups = BTreeMap::from([
+            (
+                "a".to_string(),
+                GroupScoring {
+                    points: 5.0,
+                    policy: ScoringPolicy::Sum,
+                    depends_on: vec![],
+                },
+            ),
+            (
+                "b".to_string(),
+                GroupScoring {
+                    points: 20.0,
+                    policy: ScoringPolicy::AllOrNothing,
+                    depends_on: vec![],
+                },
+            ),
+        ]);
+        let scoring = Scoring::new(&groups, &tests).unwrap();
+        let judge = Arc::new(Judge::new(tests, vec![]));
+        let path = dir.join("sum.py");
+        fs::write(&path, source).unwrap();
+        let submission = Submission {
+            name: "sum.py".to_string(),
+            language: "Python".to_string(),
+            program: Program::Python {
+                path: path.clone(),
+                checksum: None,
+            },
+            path,
+        };
+        let scoring = if scored { scoring.as_ref() } else { None };
+        judge_submission(
+            &judge,
+            scoring,
+            &submission,
+            &dir,
+            1,
+            &ProgressBar::hidden(),
+        )
+        .unwrap()
+    }
+
+    fn result(verdict: Verdict) -> TestResult {
+        TestResult {
+            verdict,
+            score: if verdict.is_accepted() { 1.0 } else { 0.0 },
+            time: Duration::ZERO,
+            memory: None,
+            outputs: None,
+        }
+    }
+
+    #[test]
+    fn verdict_is_the_first_failure() {
+        let verdict = |verdicts: &[Verdict]| {
+            let results: Vec<TestResult> = verdicts.iter().copied().map(result).collect();
+            SubmissionVerdict::from_results(&results).code()
+        };
+        assert_eq!(verdict(&[Verdict::Accepted, Verdict::Accepted]), "AC");
+        assert_eq!(verdict(&[]), "AC");
+        assert_eq!(
+            verdict(&[
+                Verdict::Accepted,
+                Verdict::Skipped,
+                Verdict::TimeLimitExceeded,
+                Verdict::WrongAnswer
+            ]),
+            "TLE"
+        );
+        assert_eq!(verdict(&[Verdict::Skipped, Verdict::Accepted]), "AC");
+        assert_eq!(
+            verdict(&[Verdict::PartiallyAccepted, Verdict::WrongAnswer]),
+            "PA"
+        );
+    }
+
+    #[test]
+    fn judge_submission_scores_groups() {
+        let accepted = judge_python("a, b = map(int, input().split())\nprint(a + b)\n", true);
+        assert_eq!(accepted.verdict.code(), "AC");
+        assert_eq!((accepted.score, accepted.max_score), (30.0, 30.0));
+
+        // Wrong on the second test of `a` only.
+        let partial = judge_python(
+            "a, b = map(int, input().split())\nprint(5 if a == b else a + b)\n",
+            true,
+        );
+        assert_eq!(partial.verdict.code(), "WA");
+        assert_eq!((partial.score, partial.max_score), (25.0, 30.0));
+        assert!(partial.max_time.is_some());
+    }
+
+    #[test]
+    fn judge_submission_counts_accepted_tests_without_scoring() {
+        let partial = judge_python("print(3)\n", false);
+        assert_eq!((partial.score, partial.max_score), (1.0, 3.0));
+    }
+
+    #[test]
+    fn judge_submission_reports_compilation_errors() {
+        let dir = test_dir();
+        let judge = Arc::new(Judge::new(
+            write_tests(&dir, &[("1", "1\n", "1\n")]),
+            vec![],
+        ));
+        let path = dir.join("sum.cpp");
+        fs::write(&path, "int main() { return }\n").unwrap();
+        let submission = Submission {
+            name: "sum.cpp".to_string(),
+            language: "C++".to_string(),
+            program: Program::GPP {
+                path: path.clone(),
+                compiler_args: None,
+                checksum: None,
+            },
+            path,
+        };
+        let result =
+            judge_submission(&judge, None, &submission, &dir, 1, &ProgressBar::hidden()).unwrap();
+        assert_eq!(result.verdict, SubmissionVerdict::CompilationError);
+        assert_eq!((result.score, result.max_score), (0.0, 1.0));
+        assert_eq!(result.max_time, None);
+        assert!(result.compile_log.contains("error"));
+    }
+
+    #[test]
+    fn scoreboard_keeps_ties_in_submission_order() {
+        let row = |submission: &str, verdict, score| SubmissionResult {
+            submission: submission.to_string(),
+            language: "C++".to_string(),
+            verdict,
+            score,
+            max_score: 30.0,
+            max_time: Some(Duration::from_millis(15)),
+            compile_log: String::new(),
+        };
+        let results = [
+            row(
+                "b.cpp",
+                SubmissionVerdict::Judged(Verdict::WrongAnswer),
+                25.0,
+            ),
+            row(
+                "a.cpp",
+                SubmissionVerdict::Judged(Verdict::PartiallyAccepted),
+                25.0,
+            ),
+            SubmissionResult {
+                max_time: None,
+                compile_log: "a.cpp:1: error, \"x\"".to_string(),
+                ..row("c.cpp", SubmissionVerdict::CompilationError, 0.0)
+            },
+        ];
+        assert_eq!(
+            to_csv(&results),
+            "submission,language,verdict,score,max_score,max_time,compile_log\n\
+             b.cpp,C++,WA,25,30,15,\n\
+             a.cpp,C++,PA,25,30,15,\n\
+             c.cpp,C++,CE,0,30,,\"a.cpp:1: error, \"\"x\"\"\"\n"
+        );
+        let json: serde_json::Value = serde_json::from_str(&to_json(&results)).unwrap();
+        assert_eq!(json[0]["submission"], "b.cpp");
+        assert_eq!(json[1]["verdict"], "PA");
+        assert_eq!(json[1]["maxScore"], 30.0);
+        assert_eq!(json[2]["maxTime"], serde_json::Value::Null);
+    }
+}
@@ -1,9 +1,18 @@
 use crate::config;
+use std::ffi::OsString;
+use std::fs::File;
+use std::io::Read;
 use std::path::{Path, PathBuf};
 use std::str::{from_utf8, Utf8Error};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 use std::{cmp, io};
+use threadpool::ThreadPool;
 
-pub fn to_lines(output: &Vec<u8>) -> Result<Vec<String>, Utf8Error> {
+/// Splits `output` into lines, ignoring trailing whitespace and trailing empty
+/// lines.
+pub fn to_lines(output: &[u8]) -> Result<Vec<String>, Utf8Error> {
     let mut lines: Vec<String> = from_utf8(output)?
         .lines()
         .map(|x| x.trim_end().to_string())
@@ -14,6 +23,7 @@ pub fn to_lines(output: &Vec<u8>) -> Result<Vec<String>, Utf8Error> {
     Ok(lines)
 }
 
+/// Recursively lists all files in `path`.
 pub fn list_files(path: &Path) -> io::Result<Vec<PathBuf>> {
     let mut files = vec![];
     for entry in path.read_dir()? {
@@ -33,30 +43,413 @@ pub enum FilesType {
     Output,
 }
 
+/// Extensions of the files selected by `files_config`, `.in` or `.out`
+/// depending on `files_type` if it has none.
+pub fn config_extensions(files_config: &config::Files, files_type: FilesType) -> Vec<String> {
+    files_config
+        .extensions
+        .clone()
+        .unwrap_or_else(|| match files_type {
+            FilesType::Input => vec![".in".to_string()],
+            FilesType::Output => vec![".out".to_string()],
+        })
+}
+
+/// The longest of `extensions` the file name of `path` ends with. Extensions
+/// may contain several dots, like `.in.txt`, and the empty extension matches
+/// files without one.
+pub fn matching_extension<'a>(path: &Path, extensions: &'a [String]) -> Option<&'a str> {
+    let file_name = path.file_name()?.to_string_lossy();
+    extensions
+        .iter()
+        .filter(|ext| match ext.as_str() {
+            "" => path.extension().is_none(),
+            ext => file_name.len() > ext.len() && file_name.ends_with(ext),
+        })
+        .max_by_key(|ext| ext.len())
+        .map(String::as_str)
+}
+
+/// `name` with `suffix` appended to its file name, unlike
+/// [`Path::with_extension`] keeping everything after earlier dots.
+pub fn with_suffix(name: &Path, suffix: &str) -> PathBuf {
+    let mut name = OsString::from(name);
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+/// `name` without the extension `ext`, which its file name ends with.
+pub fn strip_extension(name: &Path, ext: &str) -> PathBuf {
+    match name.to_str().and_then(|name| name.strip_suffix(ext)) {
+        Some(stem) => PathBuf::from(stem),
+        None => name.to_path_buf(),
+    }
+}
+
+/// A run of digits or of other characters in a path component.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum NaturalChunk {
+    /// Digits without leading zeros, ordered by length first so that they
+    /// compare by value. Numbers sort before text.
+    Number(usize, String),
+    Text(String),
+}
+
+/// Key sorting paths component by component with the numbers in them
+/// compared by value, so that `2.in` comes before `10.in`. Paths that only
+/// differ in leading zeros are ordered by the path itself.
+pub fn natural_sort_key(path: &Path) -> (Vec<Vec<NaturalChunk>>, PathBuf) {
+    let components = path
+        .components()
+        .map(|component| {
+            let component = component.as_os_str().to_string_lossy();
+            let mut chunks: Vec<NaturalChunk> = vec![];
+            let mut chunk = String::new();
+            let mut chars = component.chars().peekable();
+            while let Some(c) = chars.next() {
+                chunk.push(c);
+                let is_digit = c.is_ascii_digit();
+                if chars.peek().map(char::is_ascii_digit) == Some(is_digit) {
+                    continue;
+                }
+                let text = std::mem::take(&mut chunk);
+                chunks.push(if is_digit {
+                    let digits = text.trim_start_matches('0').to_string();
+                    NaturalChunk::Number(digits.len(), digits)
+                } else {
+                    NaturalChunk::Text(text)
+                });
+            }
+            chunks
+        })
+        .collect();
+    (components, path.to_path_buf())
+}
+
+/// Lists the files selected by `files_config`, whose path is relative to
+/// `base` and may go through an archive or be a URL, see
+/// [`config::Files::dir`]. Files are selected by [`config_extensions`].
 pub fn list_config_files(
     files_config: &config::Files,
     base: &Path,
     files_type: FilesType,
 ) -> io::Result<Vec<PathBuf>> {
-    Ok(list_files(&base.join(&files_config.path))?
+    let extensions = config_extensions(files_config, files_type);
+    Ok(list_files(&files_config.dir(base)?)?
         .into_iter()
-        .filter(|file| {
-            let ext = match file.extension().and_then(|x| x.to_str()) {
-                None => "".to_string(),
-                Some(ext) => ".".to_string() + ext,
-            };
-            files_config
-                .extensions
-                .clone()
-                .unwrap_or(match files_type {
-                    FilesType::Input => vec![".in".to_string()],
-                    FilesType::Output => vec![".out".to_string()],
-                })
-                .contains(&ext.to_string())
-        })
+        .filter(|file| matching_extension(file, &extensions).is_some())
         .collect())
 }
 
+/// A line of a diff between an expected and an actual output.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DiffLine {
+    Same(String),
+    /// Only in the expected output.
+    Expected(String),
+    /// Only in the actual output.
+    Actual(String),
+}
+
+/// Diffs above this many compared line pairs fall back to comparing lines at
+/// the same positions, as the full diff needs quadratic memory.
+const MAX_DIFF_CELLS: usize = 4_000_000;
+
+/// Line diff of `expected` and `actual`, keeping their longest common
+/// subsequence of lines.
+pub fn diff_lines(expected: &[String], actual: &[String]) -> Vec<DiffLine> {
+    let (n, m) = (expected.len(), actual.len());
+    if (n + 1).saturating_mul(m + 1) > MAX_DIFF_CELLS {
+        let mut diff = vec![];
+        for index in 0..cmp::max(n, m) {
+            match (expected.get(index), actual.get(index)) {
+                (Some(expected), Some(actual)) if expected == actual => {
+                    diff.push(DiffLine::Same(expected.clone()))
+                }
+                (expected, actual) => {
+                    diff.extend(expected.cloned().map(DiffLine::Expected));
+                    diff.extend(actual.cloned().map(DiffLine::Actual));
+                }
+            }
+        }
+        return diff;
+    }
+    // common[i][j] is the length of the longest common subsequence of
+    // expected[i..] and actual[j..].
+    let mut common = vec![vec![0u32; m + 1]; n + 1];
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            common[i][j] = if expected[i] == actual[j] {
+                common[i + 1][j + 1] + 1
+            } else {
+                cmp::max(common[i + 1][j], common[i][j + 1])
+            };
+        }
+    }
+    let (mut i, mut j) = (0, 0);
+    let mut diff = vec![];
+    while i < n || j < m {
+        if i < n && j < m && expected[i] == actual[j] {
+            diff.push(DiffLine::Same(expected[i].clone()));
+            i += 1;
+            j += 1;
+        } else if j == m || (i < n && common[i + 1][j] >= common[i][j + 1]) {
+            diff.push(DiffLine::Expected(expected[i].clone()));
+            i += 1;
+        } else {
+            diff.push(DiffLine::Actual(actual[j].clone()));
+            j += 1;
+        }
+    }
+    diff
+}
+
+/// Line diff of the outputs `expected` and `actual`, each truncated to
+/// `limit` bytes. `None` if they are not valid UTF-8.
+pub fn diff_outputs(expected: &[u8], actual: &[u8], limit: usize) -> Option<Vec<DiffLine>> {
+    let expected = to_lines(utf8_prefix(expected, limit)?).ok()?;
+    let actual = to_lines(utf8_prefix(actual, limit)?).ok()?;
+    Some(diff_lines(&expected, &actual))
+}
+
+/// The first `limit` bytes of `bytes`, without a character cut in the middle
+/// at the end. `None` if they are not valid UTF-8.
+fn utf8_prefix(bytes: &[u8], limit: usize) -> Option<&[u8]> {
+    let prefix = &bytes[..bytes.len().min(limit)];
+    match from_utf8(prefix) {
+        Ok(_) => Some(prefix),
+        // An incomplete character at the end of a truncated output.
+        Err(error) if error.error_len().is_none() && prefix.len() < bytes.len() => {
+            Some(&prefix[..error.valid_up_to()])
+        }
+        Err(_) => None,
+    }
+}
+
+/// Text of the first `limit` of `bytes`, which are the start of contents `len`
+/// bytes long, noting how much of the contents is left out.
+pub fn truncated_text(bytes: &[u8], len: u64, limit: usize) -> String {
+    let mut text = String::from_utf8_lossy(&bytes[..bytes.len().min(limit)]).into_owned();
+    if len > limit as u64 {
+        text.push_str(&format!("\n… {} more bytes", len - limit as u64));
+    }
+    text
+}
+
+/// Text of the first `limit` bytes of the file at `path`.
+pub fn preview_file(path: &Path, limit: usize) -> io::Result<String> {
+    let mut bytes = vec![];
+    File::open(path)?
+        .take(limit as u64)
+        .read_to_end(&mut bytes)?;
+    Ok(truncated_text(&bytes, path.metadata()?.len(), limit))
+}
+
+pub fn format_time(time: Duration) -> String {
+    format!("{} ms", time.as_millis())
+}
+
+/// Formats a memory usage in KiB, which is unknown if it could not be
+/// measured.
+pub fn format_memory(memory: Option<u64>) -> String {
+    match memory {
+        Some(memory) => format!("{:.1} MiB", memory as f64 / 1024.0),
+        None => "unknown".to_string(),
+    }
+}
+
+/// Formats a file size in bytes, e.g. `512 B` or `3.4 KiB`.
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit + 1 < UNITS.len() {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", size, UNITS[unit])
+}
+
 pub fn get_thread_count() -> usize {
     cmp::max(num_cpus::get() - 1, 2)
 }
+
+#[derive(Debug)]
+pub enum PoolError<E> {
+    Job(E),
+    /// A worker thread panicked.
+    Panicked,
+}
+
+/// Runs `job` for every item on a pool of `jobs` threads and returns the
+/// results in item order.
+///
+/// Items after a failed one are skipped, while the items before it still run,
+/// so the returned error is always the one of the first failing item, no
+/// matter in which order the workers finish.
+pub fn run_parallel<T, R, E, F>(items: Vec<T>, jobs: usize, job: F) -> Result<Vec<R>, PoolError<E>>
+where
+    T: Send + 'static,
+    R: Send + 'static,
+    E: Send + 'static,
+    F: Fn(T) -> Result<R, E> + Send + Sync + 'static,
+{
+    let job = Arc::new(job);
+    let first_failed = Arc::new(AtomicUsize::new(usize::MAX));
+    let results = Arc::new(Mutex::new(
+        items
+            .iter()
+            .map(|_| None)
+            .collect::<Vec<Option<Result<R, E>>>>(),
+    ));
+    let pool = ThreadPool::new(cmp::max(jobs, 1));
+    for (index, item) in items.into_iter().enumerate() {
+        let job = job.clone();
+        let first_failed = first_failed.clone();
+        let results = results.clone();
+        pool.execute(move || {
+            if first_failed.load(Ordering::SeqCst) < index {
+                return;
+            }
+            let result = job(item);
+            if result.is_err() {
+                first_failed.fetch_min(index, Ordering::SeqCst);
+            }
+            if let Ok(mut results) = results.lock() {
+                results[index] = Some(result);
+            }
+        })
+    }
+    pool.join();
+    if pool.panic_count() > 0 {
+        return Err(PoolError::Panicked);
+    }
+    let results = match results.lock() {
+        Ok(mut results) => results.drain(..).collect::<Vec<_>>(),
+        Err(_) => return Err(PoolError::Panicked),
+    };
+    let mut values = Vec::with_capacity(results.len());
+    for result in results {
+        match result {
+            Some(Ok(value)) => values.push(value),
+            Some(Err(error)) => return Err(PoolError::Job(error)),
+            // Only items after a failed one are skipped.
+            None => unreachable!(),
+        }
+    }
+    Ok(values)
+}
+
+/// New empty directory for a test.
+#[cfg(test)]
+pub(crate) fn test_dir() -> PathBuf {
+    let dir = std::env::temp_dir()
+        .join("iobot/tests")
+        .join(uuid::Uuid::new_v4().to_string());
+    std::fs::create_dir_all(&dir).expect("cannot create a test directory");
+    dir
+}
+
+/// Sorted paths of the files in `dir`, relative to it, for tests.
+#[cfg(test)]
+pub(crate) fn relative_files(dir: &Path) -> Vec<String> {
+    let mut files: Vec<String> = list_files(dir)
+        .unwrap_or_default()
+        .iter()
+        .map(|path| path.strip_prefix(dir).unwrap().display().to_string())
+        .collect();
+    files.sort();
+    files
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sorted(paths: &[&str]) -> Vec<String> {
+        let mut paths: Vec<PathBuf> = paths.iter().map(PathBuf::from).collect();
+        paths.sort_by_cached_key(|path| natural_sort_key(path));
+        paths
+            .iter()
+            .map(|path| path.to_string_lossy().into_owned())
+            .collect()
+    }
+
+    #[test]
+    fn diff_outputs_cuts_at_a_character_boundary() {
+        // `é` is two bytes, the limit cuts it in half.
+        let diff = diff_outputs("aé\n".as_bytes(), "aé\n".as_bytes(), 2).unwrap();
+        assert_eq!(diff, vec![DiffLine::Same("a".to_string())]);
+        assert!(diff_outputs(b"a\xff\n", b"a\n", 10).is_none());
+        // A character cut at the end of a whole output is invalid.
+        assert!(diff_outputs(&"aé".as_bytes()[..2], b"a", 10).is_none());
+    }
+
+    #[test]
+    fn natural_sort_compares_numbers_by_value() {
+        assert_eq!(
+            sorted(&["10.in", "2.in", "1.in", "a.in"]),
+            ["1.in", "2.in", "10.in", "a.in"]
+        );
+    }
+
+    #[test]
+    fn natural_sort_orders_by_component() {
+        assert_eq!(
+            sorted(&["g10/1.in", "g2/10.in", "g2/9.in", "g1.in"]),
+            ["g1.in", "g2/9.in", "g2/10.in", "g10/1.in"]
+        );
+    }
+
+    #[test]
+    fn natural_sort_breaks_ties_by_path() {
+        assert_eq!(
+            sorted(&["01.in", "1.in", "001.in"]),
+            ["001.in", "01.in", "1.in"]
+        );
+    }
+
+    #[test]
+    fn run_parallel_keeps_the_item_order() {
+        // Earlier items take longer, so they finish last.
+        let results = run_parallel((0..8u64).collect(), 4, |item| -> Result<u64, ()> {
+            std::thread::sleep(std::time::Duration::from_millis(40 - 5 * item));
+            Ok(item * 10)
+        })
+        .unwrap();
+        assert_eq!(results, [0, 10, 20, 30, 40, 50, 60, 70]);
+    }
+
+    #[test]
+    fn run_parallel_returns_the_first_error() {
+        for jobs in [1, 4] {
+            let result = run_parallel((0..8u64).collect(), jobs, |item| {
+                // The later failure happens first.
+                std::thread::sleep(std::time::Duration::from_millis(if item == 2 {
+                    50
+                } else {
+                    0
+                }));
+                match item {
+                    2 | 5 => Err(item),
+                    item => Ok(item),
+                }
+            });
+            assert!(matches!(result, Err(PoolError::Job(2))), "{:?}", result);
+        }
+    }
+
+    #[test]
+    fn run_parallel_reports_panics() {
+        let result = run_parallel(vec![1, 2], 2, |item: i32| -> Result<i32, ()> {
+            if item == 2 {
+                panic!("job panicked");
+            }
+            Ok(item)
+        });
+        assert!(matches!(result, Err(PoolError::Panicked)));
+    }
+}
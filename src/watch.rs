@@ -0,0 +1,149 @@
+//! Waiting for changes of package files, for the `--watch` option.
+
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher as _};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::time::Duration;
+
+/// Changes closer together than this are reported at once, as editors and
+/// iobot itself often write several files in a row.
+const DEBOUNCE: Duration = Duration::from_millis(200);
+
+pub struct Watcher {
+    watcher: RecommendedWatcher,
+    events: Receiver<notify::Result<Event>>,
+    /// Watched paths with whether they are watched recursively.
+    watched: Vec<(PathBuf, RecursiveMode)>,
+    /// Files of interest outside of the recursively watched directories.
+    files: Vec<PathBuf>,
+    ignored: Vec<PathBuf>,
+}
+
+fn canonical(path: &Path) -> PathBuf {
+    path.canonicalize().unwrap_or_else(|_| path.to_path_buf())
+}
+
+impl Watcher {
+    /// Changes in `ignored`, such as the generated directory, are never
+    /// reported.
+    pub fn new(ignored: &[PathBuf]) -> notify::Result<Watcher> {
+        let (sender, events) = mpsc::channel();
+        let watcher = notify::recommended_watcher(move |event| {
+            // The receiver is only gone when the watcher is being dropped.
+            let _ = sender.send(event);
+        })?;
+        Ok(Watcher {
+            watcher,
+            events,
+            watched: vec![],
+            files: vec![],
+            ignored: ignored.iter().map(|path| canonical(path)).collect(),
+        })
+    }
+
+    /// Watches `dir` recursively and the single `files`, replacing the paths
+    /// watched before. Files are watched through their parent directories, as
+    /// editors often replace files instead of writing to them.
+    pub fn watch(&mut self, dir: &Path, files: &[PathBuf]) -> notify::Result<()> {
+        let dir = canonical(dir);
+        let files: Vec<PathBuf> = files
+            .iter()
+            .map(|file| canonical(file))
+            .filter(|file| !file.starts_with(&dir))
+            .collect();
+        let mut watched = vec![(dir, RecursiveMode::Recursive)];
+        for file in &files {
+            if let Some(parent) = file.parent() {
+                if !watched.iter().any(|(path, _)| path == parent) {
+                    watched.push((parent.to_path_buf(), RecursiveMode::NonRecursive));
+                }
+            }
+        }
+        for (path, _) in &self.watched {
+            if !watched.iter().any(|(new, _)| new == path) {
+                self.watcher.unwatch(path)?;
+            }
+        }
+        for (path, mode) in &watched {
+            if !self.watched.contains(&(path.clone(), *mode)) {
+                self.watcher.watch(path, *mode)?;
+            }
+        }
+        self.watched = watched;
+        self.files = files;
+        Ok(())
+    }
+
+    fn is_relevant(&self, path: &Path) -> bool {
+        if self.ignored.iter().any(|ignored| path.starts_with(ignored)) {
+            return false;
+        }
+        self.watched
+            .iter()
+            .any(|(dir, mode)| *mode == RecursiveMode::Recursive && path.starts_with(dir))
+            || self.files.iter().any(|file| file == path)
+    }
+
+    /// Blocks until watched files change and returns the changed paths, once
+    /// no more changes happen for a moment.
+    pub fn wait(&self) -> notify::Result<Vec<PathBuf>> {
+        let mut changed: Vec<PathBuf> = vec![];
+        loop {
+            let event = if changed.is_empty() {
+                self.events.recv().map_err(|_| disconnected())?
+            } else {
+                match self.events.recv_timeout(DEBOUNCE) {
+                    Ok(event) => event,
+                    Err(RecvTimeoutError::Timeout) => return Ok(changed),
+                    Err(RecvTimeoutError::Disconnected) => return Err(disconnected()),
+                }
+            };
+            let event = event?;
+            if matches!(event.kind, EventKind::Access(_)) {
+                continue;
+            }
+            for path in event.paths {
+                if self.is_relevant(&path) && !changed.contains(&path) {
+                    changed.push(path);
+                }
+            }
+        }
+    }
+}
+
+fn disconnected() -> notify::Error {
+    notify::Error::generic("the file watcher stopped")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_dir;
+    use std::{fs, slice, thread};
+
+    #[test]
+    fn reports_changes_together() {
+        let dir = canonical(&test_dir());
+        let generated = dir.join("generated");
+        let outside = canonical(&test_dir()).join("model.py");
+        fs::create_dir(&generated).unwrap();
+        let mut watcher = Watcher::new(slice::from_ref(&generated)).unwrap();
+        watcher.watch(&dir, slice::from_ref(&outside)).unwrap();
+        let writes = {
+            let (dir, outside) = (dir.clone(), outside.clone());
+            thread::spawn(move || {
+                fs::write(dir.join("gen.py"), "").unwrap();
+                fs::write(generated.join("1.in"), "").unwrap();
+                thread::sleep(DEBOUNCE / 4);
+                fs::write(outside.with_file_name("other.py"), "").unwrap();
+                fs::write(&outside, "").unwrap();
+            })
+        };
+        let mut changed = watcher.wait().unwrap();
+        writes.join().unwrap();
+        let mut expected = vec![dir.join("gen.py"), outside];
+        changed.sort();
+        expected.sort();
+        assert_eq!(changed, expected);
+    }
+}
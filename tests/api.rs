@@ -0,0 +1,77 @@
+//! Generates and judges a package through the library API only.
+
+use indicatif::ProgressBar;
+use iobot::config::{Config, InputRef};
+use iobot::generator::{generate_outputs, write_listed_inputs};
+use iobot::judge::{Judge, Verdict};
+use iobot::runner::Runner;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::{env, fs};
+use uuid::Uuid;
+
+fn dir(name: &str) -> PathBuf {
+    let dir = env::temp_dir()
+        .join("iobot/tests")
+        .join(Uuid::new_v4().to_string())
+        .join(name);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+#[test]
+fn generates_and_judges_a_package() {
+    let source = dir("source");
+    fs::write(
+        source.join("iobot.yaml"),
+        "input:\n  type: tests\n  tests:\n  - input: \"1 2\\n\"\n  - input: \"20 22\\n\"\n\
+         modelProgram: model.py\n",
+    )
+    .unwrap();
+    fs::write(
+        source.join("model.py"),
+        "print(sum(map(int, input().split())))\n",
+    )
+    .unwrap();
+    let config = match Config::load(&source).unwrap() {
+        Config::ModelProgram(config) => config,
+        _ => panic!("not a config with a model program"),
+    };
+    let list = match config.input.as_input_ref() {
+        InputRef::Tests(list) => list,
+        _ => panic!("not a test list"),
+    };
+
+    let generated = dir("generated");
+    let inputs = write_listed_inputs(list, None, &source, &generated, ".in").unwrap();
+    let model = Runner::build(&config.model_program.to_program().unwrap(), &source).unwrap();
+    generate_outputs(Arc::new(model), &inputs.files, &generated, ".out", 1, &[]).unwrap();
+    fs::write(
+        generated.join("iobot.yaml"),
+        "input:\n  type: files\n  path: in\noutputFiles:\n  path: out\n",
+    )
+    .unwrap();
+
+    let package = Config::load(&generated).unwrap();
+    let judge = Arc::new(Judge::load(&package, &generated).unwrap());
+    fs::write(
+        source.join("wrong.py"),
+        "a, b = map(int, input().split())\nprint(a + b if a < 10 else a)\n",
+    )
+    .unwrap();
+    let solution = Runner::build(
+        &iobot::config::Program::Python {
+            path: source.join("wrong.py"),
+            checksum: None,
+        },
+        &source,
+    )
+    .unwrap();
+    let verdicts: Vec<Verdict> = judge
+        .judge_all(&Arc::new(solution), 1, &ProgressBar::hidden())
+        .unwrap()
+        .into_iter()
+        .map(|result| result.verdict)
+        .collect();
+    assert_eq!(verdicts, [Verdict::Accepted, Verdict::WrongAnswer]);
+}
@@ -0,0 +1,133 @@
+//! Runs the iobot binary and checks its output and exit codes.
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::{env, fs};
+use uuid::Uuid;
+
+fn dir() -> PathBuf {
+    let dir = env::temp_dir()
+        .join("iobot/tests")
+        .join(Uuid::new_v4().to_string());
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// Runs iobot with `args`, returns its exit code, stdout and stderr.
+fn iobot(args: &[&Path]) -> (Option<i32>, String, String) {
+    let output = Command::new(env!("CARGO_BIN_EXE_iobot"))
+        .args(args)
+        .output()
+        .unwrap();
+    (
+        output.status.code(),
+        String::from_utf8_lossy(&output.stdout).into_owned(),
+        String::from_utf8_lossy(&output.stderr).into_owned(),
+    )
+}
+
+/// Runs `iobot generate source generated`, returns its exit code and stderr.
+fn generate(source: &Path, generated: &Path) -> (Option<i32>, String) {
+    let (code, _, stderr) = iobot(&[Path::new("generate"), source, generated]);
+    (code, stderr)
+}
+
+#[test]
+fn source_file_is_a_usage_error() {
+    let dir = dir();
+    fs::write(dir.join("source"), "").unwrap();
+    let (code, stderr) = generate(&dir.join("source"), &dir.join("generated"));
+    assert_eq!(code, Some(2));
+    assert!(
+        stderr.contains("Source path should be a directory"),
+        "{}",
+        stderr
+    );
+}
+
+#[test]
+fn missing_config_is_a_config_error() {
+    let dir = dir();
+    let (code, stderr) = generate(&dir, &dir.join("generated"));
+    assert_eq!(code, Some(3));
+    assert!(stderr.contains("cannot read iobot.yaml"), "{}", stderr);
+}
+
+#[test]
+fn invalid_config_is_a_config_error() {
+    let dir = dir();
+    fs::write(dir.join("iobot.yaml"), "input: 5\n").unwrap();
+    let (code, stderr) = generate(&dir, &dir.join("generated"));
+    assert_eq!(code, Some(3));
+    assert!(stderr.contains("invalid iobot.yaml"), "{}", stderr);
+}
+
+#[test]
+fn failing_model_program_is_a_run_error() {
+    let dir = dir();
+    fs::write(
+        dir.join("iobot.yaml"),
+        "input:\n  type: tests\n  tests:\n  - input: \"1\\n\"\nmodelProgram: model.py\n",
+    )
+    .unwrap();
+    fs::write(dir.join("model.py"), "exit(7)\n").unwrap();
+    let (code, stderr) = generate(&dir, &dir.join("generated"));
+    assert_eq!(code, Some(5), "{}", stderr);
+}
+
+/// A generated package of two tests with the solutions `sum.py` and
+/// `first.py`, which is wrong on the second test, expected to get `first`.
+fn package_with_solutions(first: &str) -> PathBuf {
+    let dir = dir();
+    for (name, contents) in [
+        ("in/1.in", "1 2\n"),
+        ("out/1.out", "3\n"),
+        ("in/2.in", "5 5\n"),
+        ("out/2.out", "10\n"),
+        ("sum.py", "print(sum(map(int, input().split())))\n"),
+        ("first.py", "print(3)\n"),
+    ] {
+        fs::create_dir_all(dir.join(name).parent().unwrap()).unwrap();
+        fs::write(dir.join(name), contents).unwrap();
+    }
+    fs::write(
+        dir.join("iobot.yaml"),
+        format!(
+            "input:\n  type: files\n  path: in\noutputFiles:\n  path: out\nsolutions:\n\
+             - program: sum.py\n- program: first.py\n  expected: {}\n",
+            first
+        ),
+    )
+    .unwrap();
+    dir
+}
+
+#[test]
+fn solutions_get_their_expected_outcome() {
+    let package = package_with_solutions("wrong-answer");
+    let (code, stdout, stderr) = iobot(&[Path::new("test"), &package]);
+    assert_eq!(code, Some(0), "{}", stderr);
+    assert!(stdout.contains("wrong answer on 1 test"), "{}", stdout);
+    assert!(
+        stdout.contains("All 2 solutions got their expected outcome"),
+        "{}",
+        stdout
+    );
+}
+
+#[test]
+fn unexpected_outcomes_fail() {
+    let package = package_with_solutions("accepted");
+    let (code, stdout, stderr) = iobot(&[Path::new("test"), &package]);
+    assert_eq!(code, Some(1), "{}", stderr);
+    assert!(
+        stdout.contains("wrong answer on 1 test, expected accepted"),
+        "{}",
+        stdout
+    );
+    assert!(
+        stderr.contains("1 of 2 solutions did not get their expected outcome"),
+        "{}",
+        stderr
+    );
+}